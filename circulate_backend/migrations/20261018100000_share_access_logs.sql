@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE shared_links
+    ADD COLUMN download_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN downloaded_at TIMESTAMP WITH TIME ZONE;
+
+--Share access logs TABLE
+CREATE TABLE share_access_logs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    shared_link_id UUID REFERENCES shared_links(id) ON DELETE CASCADE,
+    requested_shared_id UUID NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    outcome VARCHAR(32) NOT NULL,
+    ip_address VARCHAR(64),
+    user_agent TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX share_access_logs_shared_link_id_idx
+    ON share_access_logs (shared_link_id, created_at DESC);
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE shared_links
+    ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_failed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN locked_at TIMESTAMP WITH TIME ZONE;
+
+--Share password failures TABLE (per recipient, across all shares)
+CREATE TABLE share_password_failures (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE shared_links
+    ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN expiry_notified_at TIMESTAMP WITH TIME ZONE;
+
+--Notification preferences TABLE
+CREATE TABLE notification_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    file_received BOOLEAN NOT NULL DEFAULT TRUE,
+    file_downloaded BOOLEAN NOT NULL DEFAULT TRUE,
+    share_expiring BOOLEAN NOT NULL DEFAULT TRUE,
+    share_revoked BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
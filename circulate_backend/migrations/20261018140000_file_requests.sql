@@ -0,0 +1,21 @@
+-- Add migration script here
+--File requests TABLE
+CREATE TABLE file_requests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    password VARCHAR(255),
+    deadline TIMESTAMP WITH TIME ZONE NOT NULL,
+    upload_count INTEGER NOT NULL DEFAULT 0,
+    closed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX file_requests_user_id_idx ON file_requests (user_id, created_at DESC);
+
+-- Files uploaded through a request have no sender account; the uploader's
+-- self-reported email stands in for it.
+ALTER TABLE files
+    ADD COLUMN file_request_id UUID REFERENCES file_requests(id) ON DELETE SET NULL,
+    ADD COLUMN uploader_email VARCHAR(100);
@@ -0,0 +1,13 @@
+-- Add migration script here
+ALTER TABLE shared_links
+    ADD COLUMN recipient_state VARCHAR(16) NOT NULL DEFAULT 'new',
+    ADD COLUMN recipient_state_updated_at TIMESTAMP WITH TIME ZONE;
+
+-- Shares that were already downloaded have been opened.
+UPDATE shared_links SET recipient_state = 'opened' WHERE download_count > 0;
+
+CREATE INDEX shared_links_recipient_state_idx
+    ON shared_links (recipient_user_id, recipient_state, created_at DESC);
+
+ALTER TABLE notification_preferences
+    ADD COLUMN share_declined BOOLEAN NOT NULL DEFAULT TRUE;
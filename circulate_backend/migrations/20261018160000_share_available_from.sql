@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE shared_links
+    ADD COLUMN available_from TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Send list: the sender's files, joined to their shares.
+CREATE INDEX files_user_id_created_at_idx ON files (user_id, created_at DESC);
+CREATE INDEX shared_links_file_id_idx ON shared_links (file_id);
+
+-- Receive list date filters and sorting.
+CREATE INDEX shared_links_recipient_expiration_idx
+    ON shared_links (recipient_user_id, expiration_date);
+CREATE INDEX shared_links_recipient_created_at_idx
+    ON shared_links (recipient_user_id, created_at DESC);
+
+-- Substring search on file names and counterpart emails.
+CREATE INDEX files_file_name_trgm_idx ON files USING GIN (file_name gin_trgm_ops);
+CREATE INDEX users_email_trgm_idx ON users USING GIN (email gin_trgm_ops);
+CREATE INDEX files_uploader_email_trgm_idx ON files USING GIN (uploader_email gin_trgm_ops);
@@ -0,0 +1,3 @@
+-- Hex SHA-256 of the plaintext, taken before encryption. Files uploaded before this
+-- column existed have no digest.
+ALTER TABLE files ADD COLUMN sha256 VARCHAR(64);
@@ -0,0 +1,25 @@
+-- Add migration script here
+--Two-factor TABLE
+-- A row with a secret but no enabled_at is an enrollment waiting for confirmation.
+-- `required` is set by admins and can exist before the user has enrolled.
+CREATE TABLE two_factor (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    totp_secret VARCHAR(64),
+    enabled_at TIMESTAMP WITH TIME ZONE,
+    -- Last accepted TOTP time step, so a code cannot be replayed.
+    last_used_step BIGINT,
+    required BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+--Backup codes TABLE
+CREATE TABLE two_factor_backup_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX two_factor_backup_codes_user_id_idx ON two_factor_backup_codes (user_id);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Users must prove they own their address before they can be sent files.
+ALTER TABLE users ADD COLUMN verified_at TIMESTAMP WITH TIME ZONE;
+
+-- Accounts that existed before verification was introduced are trusted as they are.
+UPDATE users SET verified_at = COALESCE(created_at, NOW());
@@ -0,0 +1,16 @@
+-- Add migration script here
+--Personal access tokens TABLE
+-- Long-lived credentials for scripts and CI. Only SHA-256 hashes are stored, and a
+-- token can only do what its scopes allow.
+CREATE TABLE access_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX access_tokens_user_id_idx ON access_tokens (user_id, created_at DESC);
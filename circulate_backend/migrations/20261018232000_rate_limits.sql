@@ -0,0 +1,12 @@
+-- Add migration script here
+--Rate limit buckets TABLE
+-- Token buckets for RATE_LIMIT_BACKEND=postgres. A bucket untouched until `idle_at`
+-- has refilled completely, so the cleanup job can drop it.
+CREATE TABLE rate_limit_buckets (
+    key VARCHAR(255) PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    idle_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX rate_limit_buckets_idle_at_idx ON rate_limit_buckets (idle_at);
@@ -0,0 +1,24 @@
+-- Add migration script here
+--Login failures TABLE
+-- Keyed by the email that was tried rather than the user, so unknown addresses lock
+-- exactly like real ones and the lockout does not reveal which accounts exist.
+CREATE TABLE login_failures (
+    email VARCHAR(255) PRIMARY KEY,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP WITH TIME ZONE
+);
+
+--Login events TABLE (user_id NULL when the email matched no account)
+CREATE TABLE login_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    outcome VARCHAR(32) NOT NULL,
+    ip_address VARCHAR(64),
+    user_agent TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX login_events_user_id_idx ON login_events (user_id, created_at DESC);
+CREATE INDEX login_events_ip_address_idx ON login_events (ip_address, created_at DESC);
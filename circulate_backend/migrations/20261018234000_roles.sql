@@ -0,0 +1,25 @@
+-- Add migration script here
+--Roles TABLE (built-in roles cannot be changed or deleted)
+CREATE TABLE roles (
+    name VARCHAR(50) PRIMARY KEY,
+    description TEXT,
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    built_in BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+INSERT INTO roles (name, description, permissions, built_in)
+VALUES
+    ('user', 'Every account starts with this role', '{}', TRUE),
+    ('admin', 'Full access to administration',
+        '{users:manage,roles:manage,webhooks:manage,stats:read}', TRUE);
+
+-- Roles already handed out, for example through OIDC group mappings, become custom
+-- roles without permissions.
+INSERT INTO roles (name)
+SELECT DISTINCT role FROM users
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE users
+    ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name);
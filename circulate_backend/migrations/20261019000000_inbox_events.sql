@@ -0,0 +1,12 @@
+-- Add migration script here
+--Inbox events TABLE
+-- pg_notify payloads are capped at 8000 bytes, so the notification only carries the
+-- event id and every instance reads the event back from here.
+CREATE TABLE inbox_events (
+    id UUID PRIMARY KEY,
+    recipient_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX inbox_events_created_at_idx ON inbox_events (created_at);
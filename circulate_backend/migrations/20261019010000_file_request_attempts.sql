@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Wrong request passwords back off like share passwords. Requests are never locked:
+-- anyone with the link could otherwise lock the requester out of their own uploads.
+ALTER TABLE file_requests
+    ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_failed_at TIMESTAMP WITH TIME ZONE;
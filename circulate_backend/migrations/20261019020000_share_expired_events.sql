@@ -0,0 +1,5 @@
+-- Add migration script here
+-- When the file.expired event went out for a share, so the cleanup job emits it once
+-- at expiry instead of when the file is finally purged after the grace period.
+ALTER TABLE shared_links
+    ADD COLUMN expired_event_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,13 @@
+-- Add migration script here
+--Two factor challenges TABLE
+-- Handed out between the password and 2FA steps of login. Only SHA-256 hashes are
+-- stored; a challenge takes a few codes and is gone once one of them works.
+CREATE TABLE two_factor_challenges (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX two_factor_challenges_expires_at_idx ON two_factor_challenges (expires_at);
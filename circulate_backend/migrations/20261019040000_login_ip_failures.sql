@@ -0,0 +1,10 @@
+-- Add migration script here
+--Login IP failures TABLE
+-- Failed logins per client IP within a window that starts at the first of them.
+-- Attempts are counted before the password is checked and handed back when it was
+-- right, so parallel guesses cannot all slip under the limit.
+CREATE TABLE login_ip_failures (
+    ip_address VARCHAR(64) PRIMARY KEY,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    window_started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
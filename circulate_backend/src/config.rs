@@ -1,9 +1,49 @@
+use std::collections::HashMap;
+
+use crate::{
+    oidc::OidcProvider,
+    ratelimit::{RateLimitGroup, RateLimitPolicy},
+    utils::expiry::ExpiryPolicy,
+};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
     pub jwt_maxage: i64,
+    pub refresh_token_maxage_days: i64,
+    pub totp_issuer: String,
+    pub email_verification_hours: i64,
+    pub password_reset_minutes: i64,
     pub port: u16,
+    pub trust_proxy_headers: bool,
+    pub trusted_proxy_count: usize,
+    pub share_max_failed_attempts: i32,
+    pub share_backoff_free_attempts: i32,
+    pub share_backoff_base_seconds: i64,
+    pub login_max_failed_attempts: i32,
+    pub login_lockout_minutes: i32,
+    pub login_ip_max_failed_attempts: i64,
+    pub app_url: String,
+    pub mail_transport: String,
+    pub mail_from: String,
+    pub mail_dir: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_tls: String,
+    pub cleanup_schedule: String,
+    pub expiry_reminder_schedule: String,
+    pub expiry_reminder_hours: i64,
+    pub expired_grace_hours: i64,
+    pub share_expiry_policy: ExpiryPolicy,
+    pub share_expiry_role_policies: HashMap<String, ExpiryPolicy>,
+    pub oidc_providers: Vec<OidcProvider>,
+    pub admin_emails: Vec<String>,
+    pub webhook_allow_private_targets: bool,
+    pub rate_limit_backend: String,
+    pub rate_limit_policies: Vec<(RateLimitGroup, RateLimitPolicy)>,
 }
 
 impl Config {
@@ -11,12 +51,161 @@ impl Config {
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let jwt_secret = std::env::var("JWT_SECRET_KEY").expect("JWT_SECRET must be set");
         let jwt_maxage = std::env::var("JWT_MAXAGE").expect("JWT_MAXAGE must be set");
+        let refresh_token_maxage_days = std::env::var("REFRESH_TOKEN_MAXAGE_DAYS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(30);
+        let email_verification_hours = std::env::var("EMAIL_VERIFICATION_HOURS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(24);
+        let password_reset_minutes = std::env::var("PASSWORD_RESET_MINUTES")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(60);
+        let totp_issuer = std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "Circulate".to_string());
+        let trust_proxy_headers = std::env::var("TRUST_PROXY_HEADERS")
+            .map(|value| value == "true")
+            .unwrap_or(false);
+        let trusted_proxy_count = std::env::var("TRUSTED_PROXY_COUNT")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|count| *count > 0)
+            .unwrap_or(1);
+        let share_max_failed_attempts = std::env::var("SHARE_MAX_FAILED_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<i32>().ok())
+            .unwrap_or(10);
+        let share_backoff_free_attempts = std::env::var("SHARE_BACKOFF_FREE_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<i32>().ok())
+            .unwrap_or(3);
+        let share_backoff_base_seconds = std::env::var("SHARE_BACKOFF_BASE_SECONDS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(2);
+        let login_max_failed_attempts = std::env::var("LOGIN_MAX_FAILED_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<i32>().ok())
+            .unwrap_or(5);
+        let login_lockout_minutes = std::env::var("LOGIN_LOCKOUT_MINUTES")
+            .ok()
+            .and_then(|value| value.parse::<i32>().ok())
+            .unwrap_or(15);
+        let login_ip_max_failed_attempts = std::env::var("LOGIN_IP_MAX_FAILED_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(20);
+        let app_url =
+            std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let mail_transport = std::env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string());
+        let mail_from = std::env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "Circulate <no-reply@localhost>".to_string());
+        let mail_dir = std::env::var("MAIL_DIR").unwrap_or_else(|_| "assets/mail".to_string());
+        let smtp_host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let smtp_port = std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|value| value.parse::<u16>().ok())
+            .unwrap_or(1025);
+        let smtp_username = std::env::var("SMTP_USERNAME").ok();
+        let smtp_password = std::env::var("SMTP_PASSWORD").ok();
+        let smtp_tls = std::env::var("SMTP_TLS").unwrap_or_else(|_| "none".to_string());
+        let cleanup_schedule =
+            std::env::var("CLEANUP_SCHEDULE").unwrap_or_else(|_| "0 0 * * * *".to_string());
+        let expiry_reminder_schedule = std::env::var("EXPIRY_REMINDER_SCHEDULE")
+            .unwrap_or_else(|_| "0 30 * * * *".to_string());
+        let expiry_reminder_hours = std::env::var("EXPIRY_REMINDER_HOURS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(24);
+        // A negative grace period would purge shares before they expire.
+        let expired_grace_hours = std::env::var("EXPIRED_GRACE_HOURS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|hours| *hours >= 0)
+            .unwrap_or(0);
+        let (share_expiry_policy, share_expiry_role_policies) = ExpiryPolicy::from_env();
+        let oidc_providers = OidcProvider::from_env(&app_url);
+        let webhook_allow_private_targets = std::env::var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+            .map(|value| value == "true")
+            .unwrap_or(false);
+        let admin_emails = std::env::var("ADMIN_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty())
+            .collect();
+        let rate_limit_backend =
+            std::env::var("RATE_LIMIT_BACKEND").unwrap_or_else(|_| "memory".to_string());
+        let rate_limit_policies = RateLimitPolicy::from_env();
 
         Config {
             database_url,
             jwt_secret,
             jwt_maxage: jwt_maxage.parse::<i64>().unwrap(),
+            refresh_token_maxage_days,
+            totp_issuer,
+            email_verification_hours,
+            password_reset_minutes,
             port: 8000,
+            trust_proxy_headers,
+            trusted_proxy_count,
+            share_max_failed_attempts,
+            share_backoff_free_attempts,
+            share_backoff_base_seconds,
+            login_max_failed_attempts,
+            login_lockout_minutes,
+            login_ip_max_failed_attempts,
+            app_url,
+            mail_transport,
+            mail_from,
+            mail_dir,
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            smtp_tls,
+            cleanup_schedule,
+            expiry_reminder_schedule,
+            expiry_reminder_hours,
+            expired_grace_hours,
+            share_expiry_policy,
+            share_expiry_role_policies,
+            oidc_providers,
+            admin_emails,
+            webhook_allow_private_targets,
+            rate_limit_backend,
+            rate_limit_policies,
         }
     }
+
+    pub fn expiry_policy(&self, role: &str) -> &ExpiryPolicy {
+        self.share_expiry_role_policies
+            .get(role)
+            .unwrap_or(&self.share_expiry_policy)
+    }
+
+    pub fn oidc_provider(&self, name: &str) -> Option<&OidcProvider> {
+        self.oidc_providers
+            .iter()
+            .find(|provider| provider.name == name)
+    }
+
+    /// How many `x-forwarded-for` entries, counted from the right, were appended by
+    /// proxies in front of the API; zero when the header is not trusted at all.
+    pub fn trusted_proxies(&self) -> usize {
+        if self.trust_proxy_headers {
+            self.trusted_proxy_count
+        } else {
+            0
+        }
+    }
+
+    /// `None` when the group's limit is switched off.
+    pub fn rate_limit_policy(&self, group: RateLimitGroup) -> Option<&RateLimitPolicy> {
+        self.rate_limit_policies
+            .iter()
+            .find(|(policy_group, _)| *policy_group == group)
+            .map(|(_, policy)| policy)
+    }
 }
@@ -1,9 +1,165 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{Pool, Postgres};
+use serde_json::Value;
+use sqlx::{Pool, Postgres, QueryBuilder, Transaction};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
 use uuid::Uuid;
 
-use crate::models::{File, ReceiveFileDetails, SendFileDetails, SharedLink, User};
+use crate::models::{
+    AccessToken, ExpiringShareDetails, File, FileRequest, KnownLogin, LoginAttemptClaim,
+    LoginEvent, LoginOutcome, NotificationPreferences, OidcLoginState, Permission,
+    ReceiveFileDetails, RecipientState, RefreshRotation, Role, SendFileDetails, Session,
+    SessionRevokeReason, ShareAccessLog, ShareAccessOutcome, ShareAttemptClaim, ShareDetails,
+    ShareDirection, ShareEventDetails, ShareHistoryRow, SharePasswordFailure, ShareSortField,
+    ShareStatus, SharedLink, StatsBucket, TransferStats, TransferStatsBucket, TwoFactor, User,
+    WebhookDelivery, WebhookDeliveryAttempt, WebhookEndpoint,
+};
+use crate::utils::{backoff, cursor::ShareCursor};
+
+/// Optional narrowing and ordering for the send and receive lists.
+#[derive(Debug, Clone)]
+pub struct ShareListFilter {
+    /// Case-insensitive substring of the file name.
+    pub search: Option<String>,
+    /// Case-insensitive substring of the other party's email.
+    pub email: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub expires_from: Option<DateTime<Utc>>,
+    pub expires_to: Option<DateTime<Utc>>,
+    /// Shares matching any of these are kept; empty keeps everything.
+    pub statuses: Vec<ShareStatus>,
+    pub sort: ShareSortField,
+    pub descending: bool,
+    /// Keyset position; only used with the default `created_at` sort.
+    pub cursor: Option<ShareCursor>,
+}
+
+/// Escapes `LIKE` wildcards so user input only ever matches literally.
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Appends `AND ...` conditions for `filter`. Expects `sl` (shared_links) and `f`
+/// (files) in scope; `email_column` is the SQL expression for the other party's email.
+fn push_share_list_filter(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filter: &ShareListFilter,
+    email_column: &'static str,
+) {
+    if let Some(search) = &filter.search {
+        query
+            .push(" AND f.file_name ILIKE ")
+            .push_bind(like_pattern(search));
+    }
+
+    if let Some(email) = &filter.email {
+        query
+            .push(format!(" AND {} ILIKE ", email_column))
+            .push_bind(like_pattern(email));
+    }
+
+    if let Some(created_from) = filter.created_from {
+        query.push(" AND sl.created_at >= ").push_bind(created_from);
+    }
+
+    if let Some(created_to) = filter.created_to {
+        query.push(" AND sl.created_at < ").push_bind(created_to);
+    }
+
+    if let Some(expires_from) = filter.expires_from {
+        query
+            .push(" AND sl.expiration_date >= ")
+            .push_bind(expires_from);
+    }
+
+    if let Some(expires_to) = filter.expires_to {
+        query
+            .push(" AND sl.expiration_date < ")
+            .push_bind(expires_to);
+    }
+
+    if !filter.statuses.is_empty() {
+        query.push(" AND (");
+        for (index, status) in filter.statuses.iter().enumerate() {
+            if index > 0 {
+                query.push(" OR ");
+            }
+            query.push(match status {
+                ShareStatus::Active => "(sl.revoked_at IS NULL AND sl.expiration_date > Now())",
+                ShareStatus::Expired => "(sl.revoked_at IS NULL AND sl.expiration_date <= Now())",
+                ShareStatus::Downloaded => "sl.download_count > 0",
+                ShareStatus::Revoked => "sl.revoked_at IS NOT NULL",
+            });
+        }
+        query.push(")");
+    }
+}
+
+/// Appends `ORDER BY`, breaking ties on the share id so pages never overlap.
+fn push_share_list_order(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filter: &ShareListFilter,
+    email_column: &'static str,
+    descending: bool,
+) {
+    let column = match filter.sort {
+        ShareSortField::CreatedAt => "sl.created_at",
+        ShareSortField::ExpirationDate => "sl.expiration_date",
+        ShareSortField::FileName => "LOWER(f.file_name)",
+        ShareSortField::FileSize => "f.file_size",
+        ShareSortField::Email => email_column,
+    };
+
+    let direction = if descending { "DESC" } else { "ASC" };
+    query.push(format!(
+        " ORDER BY {column} {direction} NULLS LAST, sl.id {direction}"
+    ));
+}
+
+/// Appends the ordering and the page window. A cursor scans from its position
+/// (towards the start of the list for `prev` cursors); otherwise `page` is used as
+/// an offset. One extra row is fetched so callers can tell whether more follow.
+fn push_share_list_page(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filter: &ShareListFilter,
+    email_column: &'static str,
+    page: u32,
+    limit: usize,
+) {
+    let descending = match &filter.cursor {
+        Some(cursor) => filter.descending != cursor.backward,
+        None => filter.descending,
+    };
+
+    if let Some(cursor) = &filter.cursor {
+        query
+            .push(if descending {
+                " AND (sl.created_at, sl.id) < ("
+            } else {
+                " AND (sl.created_at, sl.id) > ("
+            })
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    push_share_list_order(query, filter, email_column, descending);
+
+    query.push(" LIMIT ").push_bind(limit as i64 + 1);
+
+    if filter.cursor.is_none() {
+        query
+            .push(" OFFSET ")
+            .push_bind(page.saturating_sub(1) as i64 * limit as i64);
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DBClient {
@@ -14,6 +170,105 @@ impl DBClient {
     pub fn new(pool: Pool<Postgres>) -> Self {
         DBClient { pool }
     }
+
+    pub fn pool(&self) -> &Pool<Postgres> {
+        &self.pool
+    }
+}
+
+/// Inserts a file and its share. Files uploaded through a request carry the request
+/// and the uploader's self-reported email instead of a sender account; anyone with
+/// the link can type any address, so listings show it marked "(unverified)".
+#[allow(clippy::too_many_arguments)]
+async fn insert_encrypted_file(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Option<Uuid>,
+    request: Option<(Uuid, String)>,
+    file_name: String,
+    file_size: i64,
+    sha256: String,
+    recipient_user_id: Uuid,
+    password: String,
+    expiration_date: DateTime<Utc>,
+    available_from: Option<DateTime<Utc>>,
+    encrypted_aes_key: Vec<u8>,
+    encrypted_file: Vec<u8>,
+    iv: Vec<u8>,
+) -> Result<Uuid, sqlx::Error> {
+    let (file_request_id, uploader_email) = request.unzip();
+
+    let file_id: Uuid = sqlx::query_scalar!(
+        r#"
+        INSERT INTO files (user_id, file_name, file_size, sha256, encrypted_aes_key, encrypted_file, iv, file_request_id, uploader_email, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, Now())
+        RETURNING id
+        "#,
+        user_id,
+        file_name,
+        file_size,
+        sha256,
+        encrypted_aes_key,
+        encrypted_file,
+        iv,
+        file_request_id,
+        uploader_email
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    let shared_id: Uuid = sqlx::query_scalar!(
+        r#"
+        INSERT INTO shared_links (file_id, recipient_user_id, password, expiration_date, available_from, created_at)
+        VALUES ($1, $2, $3, $4, $5, Now())
+        RETURNING id
+        "#,
+        file_id,
+        recipient_user_id,
+        password,
+        expiration_date,
+        available_from
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(shared_id)
+}
+
+/// Revokes the user's sessions except `keep_session_id` and deletes their access
+/// tokens. Whoever knew the old password could have opened either.
+async fn revoke_user_credentials(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    keep_session_id: Option<Uuid>,
+    reason: SessionRevokeReason,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW(), revoked_reason = $3
+        WHERE user_id = $1
+        AND ($2::uuid IS NULL OR id <> $2)
+        AND revoked_at IS NULL
+        AND expires_at > NOW()
+        "#,
+        user_id,
+        keep_session_id,
+        reason.to_str(),
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM access_tokens
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
 }
 
 #[async_trait]
@@ -25,6 +280,12 @@ pub trait UserExt {
         email: Option<&str>,
     ) -> Result<Option<User>, sqlx::Error>;
 
+    /// The user and what their role grants, in one query for authenticating requests.
+    async fn get_user_with_permissions(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<(User, Vec<String>)>, sqlx::Error>;
+
     async fn save_user<T: Into<String> + Send>(
         &self,
         username: T,
@@ -38,29 +299,49 @@ pub trait UserExt {
         username: T,
     ) -> Result<User, sqlx::Error>;
 
+    /// Sets the new password, revokes every session except `keep_session_id` and deletes
+    /// the user's access tokens in one transaction, so nothing opened with the old
+    /// password outlives a failed revoke.
     async fn update_user_password(
         &self,
         user_id: Uuid,
         password: String,
+        keep_session_id: Uuid,
     ) -> Result<User, sqlx::Error>;
 
     async fn save_user_key(&self, user_id: Uuid, public_key: String) -> Result<(), sqlx::Error>;
 
+    /// Marks the email address as verified. False if it already was.
+    async fn verify_user_email(&self, user_id: Uuid) -> Result<bool, sqlx::Error>;
+
+    /// `None` when it would take `roles:manage` from its last holder.
+    async fn update_user_role(
+        &self,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    /// Makes the user an admin if nobody can manage roles yet. False otherwise.
+    async fn promote_first_admin(&self, user_id: Uuid) -> Result<bool, sqlx::Error>;
+
     async fn search_by_email(&self, user_id: Uuid, query: String)
         -> Result<Vec<User>, sqlx::Error>;
 
+    #[allow(clippy::too_many_arguments)]
     async fn save_encrypted_file(
         &self,
-        user_id: Uuid,
+        user_id: Option<Uuid>,
         file_name: String,
         file_size: i64,
+        sha256: String,
         recipient_user_id: Uuid,
         password: String,
         expiration_date: DateTime<Utc>,
+        available_from: Option<DateTime<Utc>>,
         encrypted_aes_key: Vec<u8>,
         encrypted_file: Vec<u8>,
         iv: Vec<u8>,
-    ) -> Result<(), sqlx::Error>;
+    ) -> Result<Uuid, sqlx::Error>;
 
     async fn get_shared(
         &self,
@@ -68,11 +349,109 @@ pub trait UserExt {
         user_id: Uuid,
     ) -> Result<Option<SharedLink>, sqlx::Error>;
 
+    async fn get_shared_by_id(&self, shared_id: Uuid) -> Result<Option<SharedLink>, sqlx::Error>;
+
+    async fn get_sent_shared(
+        &self,
+        shared_id: Uuid,
+        sender_id: Uuid,
+    ) -> Result<Option<SharedLink>, sqlx::Error>;
+
+    async fn get_share_details(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ShareDetails>, sqlx::Error>;
+
+    async fn mark_shared_downloaded(&self, shared_id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Counts a password attempt against the share and the recipient before the
+    /// password is compared, unless the share is locked or either backoff is still
+    /// running. Claims are serialised per share, so parallel requests cannot get past
+    /// the limits.
+    async fn claim_share_password_attempt(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+        max_failed_attempts: i32,
+        free_attempts: i32,
+        base_seconds: i64,
+    ) -> Result<ShareAttemptClaim, sqlx::Error>;
+
+    /// Locks the share once its failures reach `max_failed_attempts`; true if locked.
+    async fn lock_exhausted_shared(
+        &self,
+        shared_id: Uuid,
+        max_failed_attempts: i32,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Settles a claimed attempt whose password was right: the share's own failures
+    /// are cleared and the recipient gets back the attempt the claim counted. Their
+    /// failures on other shares stay.
+    async fn settle_share_password_attempt(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn unlock_shared(&self, shared_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn revoke_shared(&self, shared_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn update_recipient_state(
+        &self,
+        shared_id: Uuid,
+        recipient_id: Uuid,
+        state: RecipientState,
+    ) -> Result<bool, sqlx::Error>;
+
+    async fn delete_shared_file(&self, shared_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn get_shares_expiring_within(
+        &self,
+        hours: i64,
+    ) -> Result<Vec<ExpiringShareDetails>, sqlx::Error>;
+
+    async fn mark_expiry_notified(&self, shared_ids: &[Uuid]) -> Result<(), sqlx::Error>;
+
+    async fn get_notification_preferences(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<NotificationPreferences>, sqlx::Error>;
+
+    async fn update_notification_preferences(
+        &self,
+        user_id: Uuid,
+        file_received: Option<bool>,
+        file_downloaded: Option<bool>,
+        share_expiring: Option<bool>,
+        share_revoked: Option<bool>,
+        share_declined: Option<bool>,
+    ) -> Result<NotificationPreferences, sqlx::Error>;
+
+    async fn save_share_access_log(
+        &self,
+        shared_link_id: Option<Uuid>,
+        requested_shared_id: Uuid,
+        user_id: Uuid,
+        outcome: ShareAccessOutcome,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_share_access_logs(
+        &self,
+        shared_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<ShareAccessLog>, i64), sqlx::Error>;
+
     async fn get_file(&self, file_id: Uuid) -> Result<Option<File>, sqlx::Error>;
 
     async fn get_send_files(
         &self,
         user_id: Uuid,
+        filter: &ShareListFilter,
         page: u32,
         limit: usize,
     ) -> Result<(Vec<SendFileDetails>, i64), sqlx::Error>;
@@ -80,11 +459,40 @@ pub trait UserExt {
     async fn get_receive_files(
         &self,
         user_id: Uuid,
+        states: &[String],
+        filter: &ShareListFilter,
         page: u32,
         limit: usize,
     ) -> Result<(Vec<ReceiveFileDetails>, i64), sqlx::Error>;
 
-    async fn delete_expired_files(&self) -> Result<(), sqlx::Error>;
+    /// Sends the user's whole history in one direction over `tx`, row by row, so it
+    /// never has to be held in memory. Revoked and expired shares are included. An
+    /// error is returned rather than sent, so the caller decides how to end the stream.
+    async fn stream_share_history(
+        &self,
+        user_id: Uuid,
+        direction: &ShareDirection,
+        states: Option<&[String]>,
+        filter: &ShareListFilter,
+        tx: &mpsc::Sender<Result<ShareHistoryRow, sqlx::Error>>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_share_event_details(
+        &self,
+        shared_id: Uuid,
+    ) -> Result<Option<ShareEventDetails>, sqlx::Error>;
+
+    async fn restore_shared(
+        &self,
+        shared_id: Uuid,
+        expiration_date: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Marks shares that expired since the last call and returns them, so each
+    /// expiry is announced once. Restoring a share makes it eligible again.
+    async fn claim_newly_expired_shares(&self) -> Result<Vec<ShareEventDetails>, sqlx::Error>;
+
+    async fn delete_expired_files(&self, grace_hours: i64) -> Result<(), sqlx::Error>;
 }
 
 #[async_trait]
@@ -99,7 +507,7 @@ impl UserExt for DBClient {
         if let Some(user_id) = user_id {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, username, email,password, public_key, created_at, updated_at 
+                r#"SELECT id, username, email,password, public_key, role, verified_at, created_at, updated_at 
                    FROM users WHERE id = $1"#,
                 user_id
             )
@@ -108,7 +516,7 @@ impl UserExt for DBClient {
         } else if let Some(username) = username {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, username, email,password, public_key, created_at, updated_at 
+                r#"SELECT id, username, email,password, public_key, role, verified_at, created_at, updated_at 
                    FROM users WHERE username = $1"#,
                 username
             )
@@ -117,7 +525,7 @@ impl UserExt for DBClient {
         } else if let Some(email) = email {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, username, email,password, public_key, created_at, updated_at 
+                r#"SELECT id, username, email,password, public_key, role, verified_at, created_at, updated_at 
                    FROM users WHERE email = $1"#,
                 email
             )
@@ -128,6 +536,41 @@ impl UserExt for DBClient {
         Ok(user)
     }
 
+    async fn get_user_with_permissions(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<(User, Vec<String>)>, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT u.id, u.username, u.email, u.password, u.public_key, u.role,
+                u.verified_at, u.created_at, u.updated_at,
+                COALESCE(r.permissions, '{}') AS "permissions!"
+            FROM users u
+            LEFT JOIN roles r ON r.name = u.role
+            WHERE u.id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| {
+            let user = User {
+                id: row.id,
+                username: row.username,
+                email: row.email,
+                password: row.password,
+                public_key: row.public_key,
+                role: row.role,
+                verified_at: row.verified_at,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            };
+
+            (user, row.permissions)
+        }))
+    }
+
     async fn save_user<T: Into<String> + Send>(
         &self,
         username: T,
@@ -139,7 +582,7 @@ impl UserExt for DBClient {
             r#"
             INSERT INTO users (username, email, password)
             VALUES ($1, $2, $3)
-            RETURNING id, username, email, password, public_key, created_at, updated_at
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
             "#,
             username.into(),
             email.into(),
@@ -162,7 +605,7 @@ impl UserExt for DBClient {
             UPDATE users
             SET username = $1, updated_at = Now()
             WHERE id = $2
-            RETURNING id, username, email, password, public_key, created_at, updated_at
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
             "#,
             new_name.into(),
             user_id
@@ -176,20 +619,33 @@ impl UserExt for DBClient {
         &self,
         user_id: Uuid,
         new_password: String,
+        keep_session_id: Uuid,
     ) -> Result<User, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
         let user = sqlx::query_as!(
             User,
             r#"
             UPDATE users
             SET password = $1, updated_at = Now()
             WHERE id = $2
-            RETURNING id, username, email, password, public_key, created_at, updated_at
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
             "#,
             new_password,
             user_id
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
+
+        revoke_user_credentials(
+            &mut tx,
+            user_id,
+            Some(keep_session_id),
+            SessionRevokeReason::PasswordChanged,
+        )
+        .await?;
+
+        tx.commit().await?;
         Ok(user)
     }
 
@@ -200,7 +656,7 @@ impl UserExt for DBClient {
             UPDATE users
             SET public_key = $1, updated_at = Now()
             WHERE id = $2
-            RETURNING id, username, email, password, public_key, created_at, updated_at
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
             "#,
             public_key,
             user_id
@@ -210,6 +666,91 @@ impl UserExt for DBClient {
         Ok(())
     }
 
+    async fn verify_user_email(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified_at = NOW(), updated_at = Now()
+            WHERE id = $1
+            AND verified_at IS NULL
+            "#,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_user_role(
+        &self,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        lock_role_managers(&mut tx).await?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET role = $1, updated_at = Now()
+            WHERE id = $2
+            AND (
+                EXISTS (SELECT 1 FROM roles WHERE name = $1 AND $3 = ANY(permissions))
+                OR NOT EXISTS (
+                    SELECT 1 FROM roles WHERE name = users.role AND $3 = ANY(permissions)
+                )
+                OR EXISTS (
+                    SELECT 1
+                    FROM users holder
+                    JOIN roles ON roles.name = holder.role
+                    WHERE holder.id <> $2
+                    AND $3 = ANY(roles.permissions)
+                )
+            )
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
+            "#,
+            role,
+            user_id,
+            Permission::RolesManage.to_str(),
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn promote_first_admin(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        lock_role_managers(&mut tx).await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET role = $1, updated_at = Now()
+            WHERE id = $2
+            AND NOT EXISTS (
+                SELECT 1
+                FROM users holder
+                JOIN roles ON roles.name = holder.role
+                WHERE $3 = ANY(roles.permissions)
+            )
+            "#,
+            Role::ADMIN,
+            user_id,
+            Permission::RolesManage.to_str(),
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     async fn search_by_email(
         &self,
         user_id: Uuid,
@@ -218,10 +759,11 @@ impl UserExt for DBClient {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, email, password, public_key, created_at, updated_at
+            SELECT id, username, email, password, public_key, role, verified_at, created_at, updated_at
             FROM users
             WHERE email LIKE $1
             AND public_key IS NOT NULL
+            AND verified_at IS NOT NULL
             AND id != $2
             "#,
             query,
@@ -234,41 +776,39 @@ impl UserExt for DBClient {
 
     async fn save_encrypted_file(
         &self,
-        user_id: Uuid,
+        user_id: Option<Uuid>,
         file_name: String,
         file_size: i64,
+        sha256: String,
         recipient_user_id: Uuid,
         password: String,
         expiration_date: DateTime<Utc>,
+        available_from: Option<DateTime<Utc>>,
         encrypted_aes_key: Vec<u8>,
         encrypted_file: Vec<u8>,
         iv: Vec<u8>,
-    ) -> Result<(), sqlx::Error> {
-        let file_id: Uuid = sqlx::query_scalar!(
-            r#"
-            INSERT INTO files (user_id, file_name, file_size, encrypted_aes_key, encrypted_file, iv, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, Now())
-            RETURNING id
-            "#,
+    ) -> Result<Uuid, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let shared_id = insert_encrypted_file(
+            &mut tx,
             user_id,
+            None,
             file_name,
             file_size,
+            sha256,
+            recipient_user_id,
+            password,
+            expiration_date,
+            available_from,
             encrypted_aes_key,
             encrypted_file,
-            iv
-        ).fetch_one(&self.pool).await?;
+            iv,
+        )
+        .await?;
 
-        sqlx::query!(
-            r#"
-            INSERT INTO shared_links (file_id, recipient_user_id, password, expiration_date, created_at)
-            VALUES ($1, $2, $3, $4, Now())
-            "#,
-            file_id,
-            recipient_user_id,
-            password,
-            expiration_date
-        ).execute(&self.pool).await?;
-        Ok(())
+        tx.commit().await?;
+        Ok(shared_id)
     }
 
     async fn get_shared(
@@ -279,11 +819,14 @@ impl UserExt for DBClient {
         let shared_link = sqlx::query_as!(
             SharedLink,
             r#"
-            SELECT id, file_id, recipient_user_id, password, expiration_date, created_at
+            SELECT id, file_id, recipient_user_id, password, expiration_date, created_at,
+                failed_attempts, last_failed_at, locked_at, revoked_at, available_from
             FROM shared_links
             WHERE id = $1
             AND recipient_user_id = $2
             AND expiration_date > Now()
+            AND revoked_at IS NULL
+            AND (available_from IS NULL OR available_from <= Now())
             "#,
             shared_id,
             user_id
@@ -293,104 +836,3180 @@ impl UserExt for DBClient {
         Ok(shared_link)
     }
 
-    async fn get_file(&self, file_id: Uuid) -> Result<Option<File>, sqlx::Error> {
-        let file = sqlx::query_as!(
-            File,
+    async fn get_shared_by_id(&self, shared_id: Uuid) -> Result<Option<SharedLink>, sqlx::Error> {
+        let shared_link = sqlx::query_as!(
+            SharedLink,
             r#"
-            SELECT id, user_id, file_name, file_size, encrypted_aes_key, encrypted_file, iv, created_at
-            FROM files
+            SELECT id, file_id, recipient_user_id, password, expiration_date, created_at,
+                failed_attempts, last_failed_at, locked_at, revoked_at, available_from
+            FROM shared_links
             WHERE id = $1
             "#,
-            file_id
-        ).fetch_optional(&self.pool).await?;
-        Ok(file)
+            shared_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(shared_link)
     }
 
-    async fn get_send_files(
+    async fn get_sent_shared(
         &self,
-        user_id: Uuid,
-        page: u32,
-        limit: usize,
-    ) -> Result<(Vec<SendFileDetails>, i64), sqlx::Error> {
-        let offset = (page - 1) * limit as u32;
-        let files = sqlx::query_as!(
-            SendFileDetails,
+        shared_id: Uuid,
+        sender_id: Uuid,
+    ) -> Result<Option<SharedLink>, sqlx::Error> {
+        let shared_link = sqlx::query_as!(
+            SharedLink,
             r#"
-            SELECT 
-                f.id AS file_id,
-                f.file_name,
-                u.email AS recipient_email,
-                sl.expiration_date,
-                sl.created_at
-            FROM
-                shared_links sl
-            JOIN
-                files f ON sl.file_id = f.id
-            JOIN
-                users u ON sl.recipient_user_id = u.id
-            WHERE
-                f.user_id = $1
-            ORDER BY
-                sl.created_at DESC
-            LIMIT $2
-            OFFSET $3
+            SELECT sl.id, sl.file_id, sl.recipient_user_id, sl.password, sl.expiration_date, sl.created_at,
+                sl.failed_attempts, sl.last_failed_at, sl.locked_at, sl.revoked_at, sl.available_from
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            WHERE sl.id = $1
+            AND f.user_id = $2
             "#,
-            user_id,
-            limit as i64,
-            offset as i64,
+            shared_id,
+            sender_id
         )
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
+        Ok(shared_link)
+    }
 
-        let count_row = sqlx::query_scalar!(
+    async fn get_share_details(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ShareDetails>, sqlx::Error> {
+        let share = sqlx::query_as!(
+            ShareDetails,
             r#"
-            SELECT COUNT(*)
+            SELECT
+                sl.id AS share_id,
+                f.id AS file_id,
+                f.file_name,
+                f.file_size,
+                f.sha256,
+                COALESCE(s.email, f.uploader_email || ' (unverified)') AS "sender_email!",
+                r.id AS recipient_id,
+                r.email AS recipient_email,
+                f.file_request_id,
+                sl.recipient_state,
+                sl.download_count,
+                sl.downloaded_at,
+                sl.revoked_at,
+                sl.available_from,
+                sl.expiration_date,
+                sl.created_at
             FROM shared_links sl
             JOIN files f ON sl.file_id = f.id
-            WHERE f.user_id = $1
+            JOIN users r ON sl.recipient_user_id = r.id
+            LEFT JOIN users s ON f.user_id = s.id
+            WHERE sl.id = $1
+            AND (f.user_id = $2 OR sl.recipient_user_id = $2)
             "#,
+            shared_id,
             user_id,
         )
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
-        let total_count = count_row.unwrap_or(0);
+        Ok(share)
+    }
+
+    async fn mark_shared_downloaded(&self, shared_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET download_count = download_count + 1,
+                downloaded_at = COALESCE(downloaded_at, Now()),
+                recipient_state = CASE
+                    WHEN recipient_state = 'new' THEN 'opened'
+                    ELSE recipient_state
+                END
+            WHERE id = $1
+            "#,
+            shared_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_share_password_attempt(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+        max_failed_attempts: i32,
+        free_attempts: i32,
+        base_seconds: i64,
+    ) -> Result<ShareAttemptClaim, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let share = sqlx::query!(
+            r#"
+            SELECT failed_attempts, last_failed_at, locked_at
+            FROM shared_links
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            shared_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if share.locked_at.is_some() || share.failed_attempts >= max_failed_attempts {
+            return Ok(ShareAttemptClaim::Locked);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO share_password_failures (user_id, failed_attempts, last_failed_at)
+            VALUES ($1, 0, NULL)
+            ON CONFLICT (user_id) DO NOTHING
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let user_failures = sqlx::query_as!(
+            SharePasswordFailure,
+            r#"
+            SELECT user_id, failed_attempts, last_failed_at
+            FROM share_password_failures
+            WHERE user_id = $1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let decayed = user_failures.last_failed_at.is_none_or(|last_failed_at| {
+            Utc::now() - last_failed_at > chrono::Duration::hours(backoff::FAILURE_DECAY_HOURS)
+        });
+        let user_attempts = if decayed {
+            0
+        } else {
+            user_failures.failed_attempts
+        };
+
+        let share_wait = backoff::retry_after(
+            share.failed_attempts,
+            share.last_failed_at,
+            free_attempts,
+            base_seconds,
+        );
+        let user_wait = backoff::retry_after(
+            user_attempts,
+            user_failures.last_failed_at,
+            free_attempts,
+            base_seconds,
+        );
+
+        if let Some(wait) = share_wait.max(user_wait) {
+            return Ok(ShareAttemptClaim::Throttled(wait));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET failed_attempts = failed_attempts + 1, last_failed_at = Now()
+            WHERE id = $1
+            "#,
+            shared_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE share_password_failures
+            SET failed_attempts = $2 + 1, last_failed_at = Now()
+            WHERE user_id = $1
+            "#,
+            user_id,
+            user_attempts
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(ShareAttemptClaim::Claimed)
+    }
+
+    async fn lock_exhausted_shared(
+        &self,
+        shared_id: Uuid,
+        max_failed_attempts: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let locked = sqlx::query_scalar!(
+            r#"
+            UPDATE shared_links
+            SET locked_at = COALESCE(locked_at, Now())
+            WHERE id = $1
+            AND failed_attempts >= $2
+            RETURNING id
+            "#,
+            shared_id,
+            max_failed_attempts
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(locked.is_some())
+    }
+
+    async fn settle_share_password_attempt(
+        &self,
+        shared_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET failed_attempts = 0, last_failed_at = NULL
+            WHERE id = $1
+            "#,
+            shared_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE share_password_failures
+            SET failed_attempts = GREATEST(failed_attempts - 1, 0)
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn unlock_shared(&self, shared_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET failed_attempts = 0, last_failed_at = NULL, locked_at = NULL
+            WHERE id = $1
+            "#,
+            shared_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn revoke_shared(&self, shared_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET revoked_at = COALESCE(revoked_at, Now())
+            WHERE id = $1
+            "#,
+            shared_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_recipient_state(
+        &self,
+        shared_id: Uuid,
+        recipient_id: Uuid,
+        state: RecipientState,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET recipient_state = $3,
+                recipient_state_updated_at = Now()
+            WHERE id = $1
+            AND recipient_user_id = $2
+            AND revoked_at IS NULL
+            "#,
+            shared_id,
+            recipient_id,
+            state.to_str()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_shared_file(&self, shared_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM files
+            WHERE id = (SELECT file_id FROM shared_links WHERE id = $1)
+            "#,
+            shared_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_shares_expiring_within(
+        &self,
+        hours: i64,
+    ) -> Result<Vec<ExpiringShareDetails>, sqlx::Error> {
+        let shares = sqlx::query_as!(
+            ExpiringShareDetails,
+            r#"
+            SELECT
+                sl.id AS share_id,
+                f.file_name,
+                sl.expiration_date,
+                r.id AS recipient_user_id,
+                r.username AS recipient_name,
+                r.email AS recipient_email,
+                COALESCE(s.email, f.uploader_email || ' (unverified)') AS "sender_email!"
+            FROM
+                shared_links sl
+            JOIN
+                files f ON sl.file_id = f.id
+            JOIN
+                users r ON sl.recipient_user_id = r.id
+            LEFT JOIN
+                users s ON f.user_id = s.id
+            WHERE
+                sl.expiration_date > Now()
+                AND sl.expiration_date <= Now() + make_interval(hours => $1::int)
+                AND sl.expiry_notified_at IS NULL
+                AND sl.revoked_at IS NULL
+                AND sl.download_count = 0
+            "#,
+            hours as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(shares)
+    }
+
+    async fn mark_expiry_notified(&self, shared_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET expiry_notified_at = Now()
+            WHERE id = ANY($1)
+            "#,
+            shared_ids
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_notification_preferences(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<NotificationPreferences>, sqlx::Error> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            SELECT user_id, file_received, file_downloaded, share_expiring, share_revoked,
+                share_declined
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(preferences)
+    }
+
+    async fn update_notification_preferences(
+        &self,
+        user_id: Uuid,
+        file_received: Option<bool>,
+        file_downloaded: Option<bool>,
+        share_expiring: Option<bool>,
+        share_revoked: Option<bool>,
+        share_declined: Option<bool>,
+    ) -> Result<NotificationPreferences, sqlx::Error> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            INSERT INTO notification_preferences
+                (user_id, file_received, file_downloaded, share_expiring, share_revoked,
+                share_declined, updated_at)
+            VALUES ($1, COALESCE($2, TRUE), COALESCE($3, TRUE), COALESCE($4, TRUE), COALESCE($5, TRUE),
+                COALESCE($6, TRUE), Now())
+            ON CONFLICT (user_id) DO UPDATE
+            SET file_received = COALESCE($2, notification_preferences.file_received),
+                file_downloaded = COALESCE($3, notification_preferences.file_downloaded),
+                share_expiring = COALESCE($4, notification_preferences.share_expiring),
+                share_revoked = COALESCE($5, notification_preferences.share_revoked),
+                share_declined = COALESCE($6, notification_preferences.share_declined),
+                updated_at = Now()
+            RETURNING user_id, file_received, file_downloaded, share_expiring, share_revoked,
+                share_declined
+            "#,
+            user_id,
+            file_received,
+            file_downloaded,
+            share_expiring,
+            share_revoked,
+            share_declined
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(preferences)
+    }
+
+    async fn save_share_access_log(
+        &self,
+        shared_link_id: Option<Uuid>,
+        requested_shared_id: Uuid,
+        user_id: Uuid,
+        outcome: ShareAccessOutcome,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO share_access_logs (shared_link_id, requested_shared_id, user_id, outcome, ip_address, user_agent, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, Now())
+            "#,
+            shared_link_id,
+            requested_shared_id,
+            user_id,
+            outcome.to_str(),
+            ip_address,
+            user_agent
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_share_access_logs(
+        &self,
+        shared_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<ShareAccessLog>, i64), sqlx::Error> {
+        let offset = page.saturating_sub(1) as i64 * limit as i64;
+
+        let logs = sqlx::query_as!(
+            ShareAccessLog,
+            r#"
+            SELECT id, shared_link_id, user_id, outcome, ip_address, user_agent, created_at
+            FROM share_access_logs
+            WHERE shared_link_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            shared_id,
+            limit as i64,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let count_row = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM share_access_logs
+            WHERE shared_link_id = $1
+            "#,
+            shared_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let total_count = count_row.unwrap_or(0);
+        Ok((logs, total_count))
+    }
+
+    async fn get_file(&self, file_id: Uuid) -> Result<Option<File>, sqlx::Error> {
+        let file = sqlx::query_as!(
+            File,
+            r#"
+            SELECT id, user_id, file_name, file_size, encrypted_aes_key, encrypted_file, iv, created_at
+            FROM files
+            WHERE id = $1
+            "#,
+            file_id
+        ).fetch_optional(&self.pool).await?;
+        Ok(file)
+    }
+
+    async fn get_send_files(
+        &self,
+        user_id: Uuid,
+        filter: &ShareListFilter,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<SendFileDetails>, i64), sqlx::Error> {
+        let email_column = "u.email";
+
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT
+                f.id AS file_id,
+                sl.id AS share_id,
+                f.file_name,
+                u.email AS recipient_email,
+                sl.expiration_date,
+                sl.created_at,
+                sl.download_count,
+                sl.downloaded_at
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            JOIN users u ON sl.recipient_user_id = u.id
+            WHERE f.user_id = "#,
+        );
+        query.push_bind(user_id);
+        push_share_list_filter(&mut query, filter, email_column);
+        push_share_list_page(&mut query, filter, email_column, page, limit);
+
+        let files = query
+            .build_query_as::<SendFileDetails>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut count_query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT COUNT(*)
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            JOIN users u ON sl.recipient_user_id = u.id
+            WHERE f.user_id = "#,
+        );
+        count_query.push_bind(user_id);
+        push_share_list_filter(&mut count_query, filter, email_column);
+
+        let total_count: i64 = count_query
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((files, total_count))
+    }
+
+    async fn get_receive_files(
+        &self,
+        user_id: Uuid,
+        states: &[String],
+        filter: &ShareListFilter,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<ReceiveFileDetails>, i64), sqlx::Error> {
+        let email_column = "COALESCE(u.email, f.uploader_email || ' (unverified)')";
+
+        // Revoked and expired shares never show up in the inbox.
+        let base_filter = |query: &mut QueryBuilder<Postgres>| {
+            query
+                .push_bind(user_id)
+                .push(" AND sl.revoked_at IS NULL AND sl.expiration_date > Now()")
+                .push(" AND sl.recipient_state = ANY(")
+                .push_bind(states.to_vec())
+                .push(")");
+            push_share_list_filter(query, filter, email_column);
+        };
+
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT
+                sl.id AS share_id,
+                f.id AS file_id,
+                f.file_name,
+                COALESCE(u.email, f.uploader_email || ' (unverified)') AS sender_email,
+                f.file_request_id,
+                sl.recipient_state,
+                sl.available_from,
+                sl.expiration_date,
+                sl.created_at
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            LEFT JOIN users u ON f.user_id = u.id
+            WHERE sl.recipient_user_id = "#,
+        );
+        base_filter(&mut query);
+        push_share_list_page(&mut query, filter, email_column, page, limit);
+
+        let files = query
+            .build_query_as::<ReceiveFileDetails>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut count_query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT COUNT(*)
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            LEFT JOIN users u ON f.user_id = u.id
+            WHERE sl.recipient_user_id = "#,
+        );
+        base_filter(&mut count_query);
+
+        let total_count: i64 = count_query
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await?;
+
         Ok((files, total_count))
     }
 
-    async fn get_receive_files(
+    async fn stream_share_history(
+        &self,
+        user_id: Uuid,
+        direction: &ShareDirection,
+        states: Option<&[String]>,
+        filter: &ShareListFilter,
+        tx: &mpsc::Sender<Result<ShareHistoryRow, sqlx::Error>>,
+    ) -> Result<(), sqlx::Error> {
+        let email_column = match direction {
+            ShareDirection::Sent => "r.email",
+            ShareDirection::Received => "COALESCE(s.email, f.uploader_email || ' (unverified)')",
+        };
+
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            r#"
+            SELECT
+                sl.id AS share_id,
+                f.id AS file_id,
+                f.file_name,
+                f.file_size,
+                f.sha256,
+                {email_column} AS counterpart_email,
+                CASE
+                    WHEN sl.revoked_at IS NOT NULL THEN 'revoked'
+                    WHEN sl.expiration_date <= Now() THEN 'expired'
+                    ELSE 'active'
+                END AS status,
+                sl.recipient_state,
+                sl.download_count,
+                sl.downloaded_at,
+                sl.revoked_at,
+                sl.available_from,
+                sl.expiration_date,
+                sl.created_at
+            FROM shared_links sl
+            JOIN files f ON sl.file_id = f.id
+            JOIN users r ON sl.recipient_user_id = r.id
+            LEFT JOIN users s ON f.user_id = s.id
+            WHERE "#
+        ));
+        query.push(match direction {
+            ShareDirection::Sent => "f.user_id = ",
+            ShareDirection::Received => "sl.recipient_user_id = ",
+        });
+        query.push_bind(user_id);
+
+        if let Some(states) = states {
+            query
+                .push(" AND sl.recipient_state = ANY(")
+                .push_bind(states.to_vec())
+                .push(")");
+        }
+
+        push_share_list_filter(&mut query, filter, email_column);
+        push_share_list_order(&mut query, filter, email_column, filter.descending);
+
+        let mut rows = query.build_query_as::<ShareHistoryRow>().fetch(&self.pool);
+
+        // Stops at the first error, or as soon as the client goes away.
+        while let Some(row) = rows.next().await {
+            if tx.send(Ok(row?)).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_share_event_details(
+        &self,
+        shared_id: Uuid,
+    ) -> Result<Option<ShareEventDetails>, sqlx::Error> {
+        let details = sqlx::query_as!(
+            ShareEventDetails,
+            r#"
+            SELECT
+                sl.id AS share_id,
+                f.id AS file_id,
+                f.file_name,
+                f.file_size,
+                s.id AS "sender_id?",
+                COALESCE(s.email, f.uploader_email || ' (unverified)') AS "sender_email?",
+                r.id AS recipient_id,
+                r.email AS recipient_email,
+                sl.expiration_date
+            FROM
+                shared_links sl
+            JOIN
+                files f ON sl.file_id = f.id
+            JOIN
+                users r ON sl.recipient_user_id = r.id
+            LEFT JOIN
+                users s ON f.user_id = s.id
+            WHERE
+                sl.id = $1
+            "#,
+            shared_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(details)
+    }
+
+    async fn restore_shared(
+        &self,
+        shared_id: Uuid,
+        expiration_date: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE shared_links
+            SET expiration_date = $2,
+                expiry_notified_at = NULL,
+                expired_event_at = NULL
+            WHERE id = $1
+            "#,
+            shared_id,
+            expiration_date
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_newly_expired_shares(&self) -> Result<Vec<ShareEventDetails>, sqlx::Error> {
+        let expired_shares = sqlx::query_as!(
+            ShareEventDetails,
+            r#"
+            WITH claimed AS (
+                UPDATE shared_links
+                SET expired_event_at = NOW()
+                WHERE expiration_date < NOW()
+                AND expired_event_at IS NULL
+                AND revoked_at IS NULL
+                RETURNING id
+            )
+            SELECT
+                sl.id AS share_id,
+                f.id AS file_id,
+                f.file_name,
+                f.file_size,
+                s.id AS "sender_id?",
+                COALESCE(s.email, f.uploader_email || ' (unverified)') AS "sender_email?",
+                r.id AS recipient_id,
+                r.email AS recipient_email,
+                sl.expiration_date
+            FROM
+                claimed c
+            JOIN
+                shared_links sl ON sl.id = c.id
+            JOIN
+                files f ON sl.file_id = f.id
+            JOIN
+                users r ON sl.recipient_user_id = r.id
+            LEFT JOIN
+                users s ON f.user_id = s.id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(expired_shares)
+    }
+
+    async fn delete_expired_files(&self, grace_hours: i64) -> Result<(), sqlx::Error> {
+        let expired_shared_links: Vec<Uuid> = sqlx::query_scalar!(
+            r#"
+            SELECT sl.id
+            FROM shared_links sl
+            WHERE sl.expiration_date < NOW() - make_interval(hours => $1::int)
+            OR sl.revoked_at IS NOT NULL
+            "#,
+            grace_hours as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        if expired_shared_links.is_empty() {
+            println!("No expired shared links found.");
+            return Ok(());
+        }
+
+        let expired_file_ids: Vec<Uuid> = sqlx::query_scalar!(
+            r#"
+            SELECT f.id
+            FROM files f
+            WHERE f.id IN(
+                SELECT sl.file_id
+                FROM shared_links sl
+                WHERE sl.expiration_date < NOW() - make_interval(hours => $1::int)
+                OR sl.revoked_at IS NOT NULL
+            )
+            "#,
+            grace_hours as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM shared_links
+            WHERE id = ANY($1)
+            "#,
+            &expired_shared_links[..]
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM files
+            WHERE id = ANY($1)
+            "#,
+            &expired_file_ids[..]
+        )
+        .execute(&self.pool)
+        .await?;
+
+        println!("Successfully deleted expired files and shared links.");
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait WebhookExt {
+    async fn save_webhook_endpoint(
+        &self,
+        user_id: Option<Uuid>,
+        url: String,
+        secret: String,
+        events: Vec<String>,
+    ) -> Result<WebhookEndpoint, sqlx::Error>;
+
+    async fn get_webhook_endpoints(
+        &self,
+        user_id: Option<Uuid>,
+    ) -> Result<Vec<WebhookEndpoint>, sqlx::Error>;
+
+    async fn get_webhook_endpoint(
+        &self,
+        endpoint_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<Option<WebhookEndpoint>, sqlx::Error>;
+
+    async fn delete_webhook_endpoint(&self, endpoint_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn enqueue_webhook_event(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: Value,
+        user_ids: &[Uuid],
+    ) -> Result<u64, sqlx::Error>;
+
+    async fn claim_webhook_deliveries(
+        &self,
+        limit: i64,
+        lease_seconds: f64,
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error>;
+
+    async fn save_webhook_attempt(
+        &self,
+        delivery_id: Uuid,
+        response_status: Option<i32>,
+        error: Option<String>,
+        duration_ms: i32,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn mark_webhook_delivered(&self, delivery_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn reschedule_webhook_delivery(
+        &self,
+        delivery_id: Uuid,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_webhook_attempts(
+        &self,
+        endpoint_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<WebhookDeliveryAttempt>, i64), sqlx::Error>;
+}
+
+#[async_trait]
+impl WebhookExt for DBClient {
+    async fn save_webhook_endpoint(
+        &self,
+        user_id: Option<Uuid>,
+        url: String,
+        secret: String,
+        events: Vec<String>,
+    ) -> Result<WebhookEndpoint, sqlx::Error> {
+        let endpoint = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            INSERT INTO webhook_endpoints (user_id, url, secret, events, created_at)
+            VALUES ($1, $2, $3, $4, Now())
+            RETURNING id, user_id, url, secret, events, active, created_at
+            "#,
+            user_id,
+            url,
+            secret,
+            &events[..]
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(endpoint)
+    }
+
+    async fn get_webhook_endpoints(
+        &self,
+        user_id: Option<Uuid>,
+    ) -> Result<Vec<WebhookEndpoint>, sqlx::Error> {
+        let endpoints = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            SELECT id, user_id, url, secret, events, active, created_at
+            FROM webhook_endpoints
+            WHERE user_id IS NOT DISTINCT FROM $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(endpoints)
+    }
+
+    async fn get_webhook_endpoint(
+        &self,
+        endpoint_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<Option<WebhookEndpoint>, sqlx::Error> {
+        let endpoint = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            SELECT id, user_id, url, secret, events, active, created_at
+            FROM webhook_endpoints
+            WHERE id = $1
+            AND user_id IS NOT DISTINCT FROM $2
+            "#,
+            endpoint_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(endpoint)
+    }
+
+    async fn delete_webhook_endpoint(&self, endpoint_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM webhook_endpoints
+            WHERE id = $1
+            "#,
+            endpoint_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enqueue_webhook_event(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: Value,
+        user_ids: &[Uuid],
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (endpoint_id, event_id, event_type, payload, next_attempt_at, created_at)
+            SELECT id, $1, $2, $3, Now(), Now()
+            FROM webhook_endpoints
+            WHERE active
+            AND $2::varchar = ANY(events)
+            AND (user_id IS NULL OR user_id = ANY($4))
+            "#,
+            event_id,
+            event_type,
+            payload,
+            user_ids
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn claim_webhook_deliveries(
+        &self,
+        limit: i64,
+        lease_seconds: f64,
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        // Claimed rows are pushed into the future for the lease period, so another
+        // instance polling the queue skips them while they are being delivered.
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            UPDATE webhook_deliveries d
+            SET attempts = d.attempts + 1,
+                next_attempt_at = Now() + make_interval(secs => $2)
+            FROM webhook_endpoints e
+            WHERE d.endpoint_id = e.id
+            AND d.id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status = 'pending'
+                AND next_attempt_at <= Now()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING d.id, d.event_type, d.payload, d.attempts, e.url, e.secret
+            "#,
+            limit,
+            lease_seconds
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(deliveries)
+    }
+
+    async fn save_webhook_attempt(
+        &self,
+        delivery_id: Uuid,
+        response_status: Option<i32>,
+        error: Option<String>,
+        duration_ms: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_delivery_attempts (delivery_id, response_status, error, duration_ms, created_at)
+            VALUES ($1, $2, $3, $4, Now())
+            "#,
+            delivery_id,
+            response_status,
+            error,
+            duration_ms
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_webhook_delivered(&self, delivery_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered', delivered_at = Now()
+            WHERE id = $1
+            "#,
+            delivery_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reschedule_webhook_delivery(
+        &self,
+        delivery_id: Uuid,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $2::timestamptz IS NULL THEN 'failed' ELSE status END,
+                next_attempt_at = COALESCE($2, next_attempt_at)
+            WHERE id = $1
+            "#,
+            delivery_id,
+            next_attempt_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_webhook_attempts(
+        &self,
+        endpoint_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<WebhookDeliveryAttempt>, i64), sqlx::Error> {
+        let offset = page.saturating_sub(1) as i64 * limit as i64;
+
+        let attempts = sqlx::query_as!(
+            WebhookDeliveryAttempt,
+            r#"
+            SELECT
+                a.id,
+                a.delivery_id,
+                d.event_id,
+                d.event_type,
+                d.status AS delivery_status,
+                a.response_status,
+                a.error,
+                a.duration_ms,
+                a.created_at
+            FROM
+                webhook_delivery_attempts a
+            JOIN
+                webhook_deliveries d ON a.delivery_id = d.id
+            WHERE
+                d.endpoint_id = $1
+            ORDER BY
+                a.created_at DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            endpoint_id,
+            limit as i64,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let count_row = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM webhook_delivery_attempts a
+            JOIN webhook_deliveries d ON a.delivery_id = d.id
+            WHERE d.endpoint_id = $1
+            "#,
+            endpoint_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let total_count = count_row.unwrap_or(0);
+        Ok((attempts, total_count))
+    }
+}
+
+#[async_trait]
+pub trait NotifyExt {
+    /// Stores an inbox event and announces its id on `channel`, in one statement.
+    async fn publish_inbox_event(
+        &self,
+        channel: &str,
+        event_id: Uuid,
+        recipient_id: Uuid,
+        payload: Value,
+    ) -> Result<(), sqlx::Error>;
+
+    /// The recipient and payload of an announced inbox event.
+    async fn get_inbox_event(&self, event_id: Uuid) -> Result<Option<(Uuid, Value)>, sqlx::Error>;
+
+    async fn delete_old_inbox_events(&self, retention_hours: i32) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl NotifyExt for DBClient {
+    async fn publish_inbox_event(
+        &self,
+        channel: &str,
+        event_id: Uuid,
+        recipient_id: Uuid,
+        payload: Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            WITH event AS (
+                INSERT INTO inbox_events (id, recipient_id, payload)
+                VALUES ($1, $2, $3)
+                RETURNING id
+            )
+            SELECT pg_notify($4, event.id::text)
+            FROM event
+            "#,
+            event_id,
+            recipient_id,
+            payload,
+            channel,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_inbox_event(&self, event_id: Uuid) -> Result<Option<(Uuid, Value)>, sqlx::Error> {
+        let event = sqlx::query!(
+            r#"
+            SELECT recipient_id, payload
+            FROM inbox_events
+            WHERE id = $1
+            "#,
+            event_id,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(event.map(|event| (event.recipient_id, event.payload)))
+    }
+
+    async fn delete_old_inbox_events(&self, retention_hours: i32) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM inbox_events
+            WHERE created_at < NOW() - make_interval(hours => $1::int)
+            "#,
+            retention_hours,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait FileRequestExt {
+    async fn save_file_request(
+        &self,
+        user_id: Uuid,
+        title: String,
+        description: Option<String>,
+        password: Option<String>,
+        deadline: DateTime<Utc>,
+    ) -> Result<FileRequest, sqlx::Error>;
+
+    async fn get_file_request(&self, request_id: Uuid) -> Result<Option<FileRequest>, sqlx::Error>;
+
+    async fn get_file_requests(
+        &self,
+        user_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<FileRequest>, i64), sqlx::Error>;
+
+    async fn close_file_request(
+        &self,
+        request_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<FileRequest>, sqlx::Error>;
+
+    /// Counts a request password attempt as a failure before the password is
+    /// compared. `Some(seconds)` while earlier failures still hold the request back.
+    async fn claim_request_password_attempt(
+        &self,
+        request_id: Uuid,
+        free_attempts: i32,
+        base_seconds: i64,
+    ) -> Result<Option<u64>, sqlx::Error>;
+
+    /// Clears the request's failures once a password was right.
+    async fn settle_request_password_attempt(&self, request_id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Saves an upload to the request, counting it in the same transaction. `None`
+    /// if the request was closed or passed its deadline in the meantime.
+    #[allow(clippy::too_many_arguments)]
+    async fn save_requested_file(
+        &self,
+        request_id: Uuid,
+        uploader_email: String,
+        file_name: String,
+        file_size: i64,
+        sha256: String,
+        recipient_user_id: Uuid,
+        password: String,
+        expiration_date: DateTime<Utc>,
+        encrypted_aes_key: Vec<u8>,
+        encrypted_file: Vec<u8>,
+        iv: Vec<u8>,
+    ) -> Result<Option<Uuid>, sqlx::Error>;
+}
+
+#[async_trait]
+impl FileRequestExt for DBClient {
+    async fn save_file_request(
+        &self,
+        user_id: Uuid,
+        title: String,
+        description: Option<String>,
+        password: Option<String>,
+        deadline: DateTime<Utc>,
+    ) -> Result<FileRequest, sqlx::Error> {
+        let file_request = sqlx::query_as!(
+            FileRequest,
+            r#"
+            INSERT INTO file_requests (user_id, title, description, password, deadline)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, title, description, password, deadline, upload_count,
+                closed_at, created_at
+            "#,
+            user_id,
+            title,
+            description,
+            password,
+            deadline
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(file_request)
+    }
+
+    async fn get_file_request(&self, request_id: Uuid) -> Result<Option<FileRequest>, sqlx::Error> {
+        let file_request = sqlx::query_as!(
+            FileRequest,
+            r#"
+            SELECT id, user_id, title, description, password, deadline, upload_count,
+                closed_at, created_at
+            FROM file_requests
+            WHERE id = $1
+            "#,
+            request_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(file_request)
+    }
+
+    async fn get_file_requests(
+        &self,
+        user_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<FileRequest>, i64), sqlx::Error> {
+        let offset = page.saturating_sub(1) as i64 * limit as i64;
+
+        let file_requests = sqlx::query_as!(
+            FileRequest,
+            r#"
+            SELECT id, user_id, title, description, password, deadline, upload_count,
+                closed_at, created_at
+            FROM file_requests
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            user_id,
+            limit as i64,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let count_row = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM file_requests WHERE user_id = $1"#,
+            user_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let total_count = count_row.unwrap_or(0);
+        Ok((file_requests, total_count))
+    }
+
+    async fn close_file_request(
+        &self,
+        request_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<FileRequest>, sqlx::Error> {
+        let file_request = sqlx::query_as!(
+            FileRequest,
+            r#"
+            UPDATE file_requests
+            SET closed_at = COALESCE(closed_at, Now())
+            WHERE id = $1
+            AND user_id = $2
+            RETURNING id, user_id, title, description, password, deadline, upload_count,
+                closed_at, created_at
+            "#,
+            request_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(file_request)
+    }
+
+    async fn claim_request_password_attempt(
+        &self,
+        request_id: Uuid,
+        free_attempts: i32,
+        base_seconds: i64,
+    ) -> Result<Option<u64>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let request = sqlx::query!(
+            r#"
+            SELECT failed_attempts, last_failed_at
+            FROM file_requests
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            request_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let decayed = request.last_failed_at.is_none_or(|last_failed_at| {
+            Utc::now() - last_failed_at > chrono::Duration::hours(backoff::FAILURE_DECAY_HOURS)
+        });
+        let failed_attempts = if decayed { 0 } else { request.failed_attempts };
+
+        if let Some(wait) = backoff::retry_after(
+            failed_attempts,
+            request.last_failed_at,
+            free_attempts,
+            base_seconds,
+        ) {
+            return Ok(Some(wait));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE file_requests
+            SET failed_attempts = $2 + 1, last_failed_at = Now()
+            WHERE id = $1
+            "#,
+            request_id,
+            failed_attempts
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(None)
+    }
+
+    async fn settle_request_password_attempt(&self, request_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE file_requests
+            SET failed_attempts = 0, last_failed_at = NULL
+            WHERE id = $1
+            "#,
+            request_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn save_requested_file(
+        &self,
+        request_id: Uuid,
+        uploader_email: String,
+        file_name: String,
+        file_size: i64,
+        sha256: String,
+        recipient_user_id: Uuid,
+        password: String,
+        expiration_date: DateTime<Utc>,
+        encrypted_aes_key: Vec<u8>,
+        encrypted_file: Vec<u8>,
+        iv: Vec<u8>,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let counted = sqlx::query_scalar!(
+            r#"
+            UPDATE file_requests
+            SET upload_count = upload_count + 1
+            WHERE id = $1
+            AND closed_at IS NULL
+            AND deadline > Now()
+            RETURNING id
+            "#,
+            request_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if counted.is_none() {
+            return Ok(None);
+        }
+
+        let shared_id = insert_encrypted_file(
+            &mut tx,
+            None,
+            Some((request_id, uploader_email)),
+            file_name,
+            file_size,
+            sha256,
+            recipient_user_id,
+            password,
+            expiration_date,
+            None,
+            encrypted_aes_key,
+            encrypted_file,
+            iv,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(shared_id))
+    }
+}
+
+/// Transfer statistics. `user_id` limits them to shares the user sent or received;
+/// `None` covers the whole organisation, where every share counts as both sent and
+/// received and counterparts are everyone who took part.
+#[async_trait]
+pub trait StatsExt {
+    async fn get_transfer_stats(
+        &self,
+        user_id: Option<Uuid>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<TransferStats, sqlx::Error>;
+
+    async fn get_transfer_stats_buckets(
+        &self,
+        user_id: Option<Uuid>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: &StatsBucket,
+    ) -> Result<Vec<TransferStatsBucket>, sqlx::Error>;
+}
+
+#[async_trait]
+impl StatsExt for DBClient {
+    async fn get_transfer_stats(
+        &self,
+        user_id: Option<Uuid>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<TransferStats, sqlx::Error> {
+        let stats = sqlx::query_as!(
+            TransferStats,
+            r#"
+            WITH shares AS (
+                SELECT
+                    sl.created_at,
+                    sl.expiration_date,
+                    sl.download_count,
+                    f.file_size,
+                    ($1::uuid IS NULL OR f.user_id = $1) AS is_sent,
+                    ($1::uuid IS NULL OR sl.recipient_user_id = $1) AS is_received,
+                    COALESCE(s.email, f.uploader_email || ' (unverified)') AS sender_email,
+                    r.email AS recipient_email
+                FROM shared_links sl
+                JOIN files f ON sl.file_id = f.id
+                JOIN users r ON sl.recipient_user_id = r.id
+                LEFT JOIN users s ON f.user_id = s.id
+                WHERE sl.created_at >= $2
+                AND sl.created_at < $3
+                AND ($1::uuid IS NULL OR f.user_id = $1 OR sl.recipient_user_id = $1)
+            )
+            SELECT
+                COUNT(*) FILTER (WHERE is_sent) AS "files_sent!",
+                COALESCE(SUM(file_size) FILTER (WHERE is_sent), 0)::BIGINT AS "bytes_sent!",
+                COUNT(*) FILTER (WHERE is_received) AS "files_received!",
+                COALESCE(SUM(file_size) FILTER (WHERE is_received), 0)::BIGINT AS "bytes_received!",
+                (
+                    SELECT COUNT(DISTINCT email)
+                    FROM (
+                        SELECT recipient_email AS email FROM shares WHERE is_sent
+                        UNION
+                        SELECT sender_email FROM shares WHERE is_received
+                    ) counterparts
+                ) AS "unique_counterparts!",
+                AVG(EXTRACT(EPOCH FROM expiration_date - created_at))
+                    FILTER (WHERE is_sent)::FLOAT8 AS avg_lifetime_seconds,
+                AVG((download_count > 0)::INT) FILTER (WHERE is_sent)::FLOAT8 AS download_rate
+            FROM shares
+            "#,
+            user_id,
+            from,
+            to,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    async fn get_transfer_stats_buckets(
+        &self,
+        user_id: Option<Uuid>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: &StatsBucket,
+    ) -> Result<Vec<TransferStatsBucket>, sqlx::Error> {
+        // Buckets are cut in UTC and empty ones are still returned, so charts have no gaps.
+        let buckets = sqlx::query_as!(
+            TransferStatsBucket,
+            r#"
+            WITH shares AS (
+                SELECT
+                    date_trunc($4, sl.created_at AT TIME ZONE 'UTC') AS bucket,
+                    f.file_size,
+                    ($1::uuid IS NULL OR f.user_id = $1) AS is_sent,
+                    ($1::uuid IS NULL OR sl.recipient_user_id = $1) AS is_received
+                FROM shared_links sl
+                JOIN files f ON sl.file_id = f.id
+                WHERE sl.created_at >= $2
+                AND sl.created_at < $3
+                AND ($1::uuid IS NULL OR f.user_id = $1 OR sl.recipient_user_id = $1)
+            ),
+            buckets AS (
+                SELECT bucket
+                FROM generate_series(
+                    date_trunc($4, $2 AT TIME ZONE 'UTC'),
+                    ($3 AT TIME ZONE 'UTC') - INTERVAL '1 microsecond',
+                    ('1 ' || $4)::INTERVAL
+                ) bucket
+            )
+            SELECT
+                b.bucket AT TIME ZONE 'UTC' AS "bucket_start!",
+                COUNT(s.bucket) FILTER (WHERE s.is_sent) AS "files_sent!",
+                COALESCE(SUM(s.file_size) FILTER (WHERE s.is_sent), 0)::BIGINT AS "bytes_sent!",
+                COUNT(s.bucket) FILTER (WHERE s.is_received) AS "files_received!",
+                COALESCE(SUM(s.file_size) FILTER (WHERE s.is_received), 0)::BIGINT AS "bytes_received!"
+            FROM buckets b
+            LEFT JOIN shares s ON s.bucket = b.bucket
+            GROUP BY b.bucket
+            ORDER BY b.bucket
+            "#,
+            user_id,
+            from,
+            to,
+            bucket.to_str(),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(buckets)
+    }
+}
+
+#[async_trait]
+pub trait SessionExt {
+    /// Starts a session together with its first refresh token.
+    async fn save_session(
+        &self,
+        user_id: Uuid,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        expires_at: DateTime<Utc>,
+        refresh_token_hash: String,
+    ) -> Result<Session, sqlx::Error>;
+
+    async fn get_active_session(
+        &self,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<Session>, sqlx::Error>;
+
+    /// Swaps a refresh token for a new one in a single transaction.
+    async fn rotate_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: String,
+    ) -> Result<RefreshRotation, sqlx::Error>;
+
+    /// Active sessions, most recently used first.
+    async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, sqlx::Error>;
+
+    async fn touch_session(&self, session_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn revoke_session(
+        &self,
+        session_id: Uuid,
+        reason: SessionRevokeReason,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Revokes one of the user's own sessions; false if it is not theirs or already ended.
+    async fn revoke_user_session(
+        &self,
+        session_id: Uuid,
+        user_id: Uuid,
+        reason: SessionRevokeReason,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Revokes every active session of the user except `keep_session_id`.
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        keep_session_id: Option<Uuid>,
+        reason: SessionRevokeReason,
+    ) -> Result<u64, sqlx::Error>;
+
+    /// Removes sessions that expired or were revoked more than `retention_days` ago.
+    async fn delete_stale_sessions(&self, retention_days: i64) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl SessionExt for DBClient {
+    async fn save_session(
+        &self,
+        user_id: Uuid,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        expires_at: DateTime<Utc>,
+        refresh_token_hash: String,
+    ) -> Result<Session, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+            INSERT INTO sessions (user_id, user_agent, ip_address, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, user_agent, ip_address, expires_at, last_used_at, created_at
+            "#,
+            user_id,
+            user_agent,
+            ip_address,
+            expires_at,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, session_id)
+            VALUES ($1, $2)
+            "#,
+            refresh_token_hash,
+            session.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(session)
+    }
+
+    async fn get_active_session(
+        &self,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<Session>, sqlx::Error> {
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT id, user_id, user_agent, ip_address, expires_at, last_used_at, created_at
+            FROM sessions
+            WHERE id = $1
+            AND user_id = $2
+            AND revoked_at IS NULL
+            AND expires_at > NOW()
+            "#,
+            session_id,
+            user_id,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: String,
+    ) -> Result<RefreshRotation, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Marking the token used and checking it was unused is one statement, so two
+        // concurrent refreshes with the same token cannot both succeed.
+        let session_id = sqlx::query_scalar!(
+            r#"
+            UPDATE refresh_tokens
+            SET used_at = NOW()
+            WHERE token_hash = $1
+            AND used_at IS NULL
+            RETURNING session_id
+            "#,
+            refresh_token_hash,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let session_id = match session_id {
+            Some(session_id) => session_id,
+            None => {
+                let reused_session_id = sqlx::query_scalar!(
+                    r#"
+                    SELECT session_id
+                    FROM refresh_tokens
+                    WHERE token_hash = $1
+                    "#,
+                    refresh_token_hash,
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                let Some(reused_session_id) = reused_session_id else {
+                    return Ok(RefreshRotation::Invalid);
+                };
+
+                sqlx::query!(
+                    r#"
+                    UPDATE sessions
+                    SET revoked_at = NOW(), revoked_reason = $2
+                    WHERE id = $1
+                    AND revoked_at IS NULL
+                    "#,
+                    reused_session_id,
+                    SessionRevokeReason::RefreshTokenReused.to_str(),
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+
+                return Ok(RefreshRotation::Reused);
+            }
+        };
+
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+            UPDATE sessions
+            SET last_used_at = NOW()
+            WHERE id = $1
+            AND revoked_at IS NULL
+            AND expires_at > NOW()
+            RETURNING id, user_id, user_agent, ip_address, expires_at, last_used_at, created_at
+            "#,
+            session_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(session) = session else {
+            return Ok(RefreshRotation::Invalid);
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, session_id)
+            VALUES ($1, $2)
+            "#,
+            new_refresh_token_hash,
+            session.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(RefreshRotation::Rotated(session))
+    }
+
+    async fn revoke_session(
+        &self,
+        session_id: Uuid,
+        reason: SessionRevokeReason,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = NOW(), revoked_reason = $2
+            WHERE id = $1
+            AND revoked_at IS NULL
+            "#,
+            session_id,
+            reason.to_str(),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, sqlx::Error> {
+        let sessions = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT id, user_id, user_agent, ip_address, expires_at, last_used_at, created_at
+            FROM sessions
+            WHERE user_id = $1
+            AND revoked_at IS NULL
+            AND expires_at > NOW()
+            ORDER BY last_used_at DESC NULLS LAST, created_at DESC
+            "#,
+            user_id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn touch_session(&self, session_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_used_at = NOW()
+            WHERE id = $1
+            "#,
+            session_id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_session(
+        &self,
+        session_id: Uuid,
+        user_id: Uuid,
+        reason: SessionRevokeReason,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = NOW(), revoked_reason = $3
+            WHERE id = $1
+            AND user_id = $2
+            AND revoked_at IS NULL
+            AND expires_at > NOW()
+            "#,
+            session_id,
+            user_id,
+            reason.to_str(),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        keep_session_id: Option<Uuid>,
+        reason: SessionRevokeReason,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = NOW(), revoked_reason = $3
+            WHERE user_id = $1
+            AND ($2::uuid IS NULL OR id <> $2)
+            AND revoked_at IS NULL
+            AND expires_at > NOW()
+            "#,
+            user_id,
+            keep_session_id,
+            reason.to_str(),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_stale_sessions(&self, retention_days: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE COALESCE(revoked_at, expires_at) < NOW() - make_interval(days => $1::int)
+            "#,
+            retention_days as i32,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait TwoFactorExt {
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<Option<TwoFactor>, sqlx::Error>;
+
+    /// Stores a new secret awaiting confirmation. False if 2FA is already enabled.
+    async fn save_pending_totp_secret(
+        &self,
+        user_id: Uuid,
+        totp_secret: String,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Turns on the pending secret, consuming `step`, and replaces the backup codes.
+    async fn enable_totp(
+        &self,
+        user_id: Uuid,
+        step: i64,
+        backup_code_hashes: Vec<String>,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Records a TOTP step as used. False if it, or a later one, was used already.
+    async fn record_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error>;
+
+    /// Consumes a backup code. False if no unused code has this hash.
+    async fn use_backup_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error>;
+
+    async fn count_backup_codes(&self, user_id: Uuid) -> Result<i64, sqlx::Error>;
+
+    /// Forgets the secret and backup codes. Whether 2FA is required is kept.
+    async fn disable_totp(&self, user_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn set_two_factor_required(
+        &self,
+        user_id: Uuid,
+        required: bool,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn save_two_factor_challenge(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Counts an attempt against the challenge before the code is checked and
+    /// returns whose it is. `None` once it is unknown, expired or out of attempts.
+    async fn claim_two_factor_challenge(
+        &self,
+        token_hash: &str,
+        max_attempts: i32,
+    ) -> Result<Option<Uuid>, sqlx::Error>;
+
+    /// Removes a challenge whose code checked out, so it cannot start a second session.
+    async fn delete_two_factor_challenge(&self, token_hash: &str) -> Result<(), sqlx::Error>;
+
+    async fn delete_expired_two_factor_challenges(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl TwoFactorExt for DBClient {
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<Option<TwoFactor>, sqlx::Error> {
+        let two_factor = sqlx::query_as!(
+            TwoFactor,
+            r#"
+            SELECT user_id, totp_secret, enabled_at, last_used_step, required
+            FROM two_factor
+            WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    async fn save_pending_totp_secret(
+        &self,
+        user_id: Uuid,
+        totp_secret: String,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO two_factor (user_id, totp_secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET totp_secret = EXCLUDED.totp_secret, last_used_step = NULL, updated_at = NOW()
+            WHERE two_factor.enabled_at IS NULL
+            "#,
+            user_id,
+            totp_secret,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn enable_totp(
+        &self,
+        user_id: Uuid,
+        step: i64,
+        backup_code_hashes: Vec<String>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE two_factor
+            SET enabled_at = NOW(), last_used_step = $2, updated_at = NOW()
+            WHERE user_id = $1
+            AND enabled_at IS NULL
+            AND totp_secret IS NOT NULL
+            "#,
+            user_id,
+            step,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM two_factor_backup_codes
+            WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO two_factor_backup_codes (user_id, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::text[]) AS code_hash
+            "#,
+            user_id,
+            &backup_code_hashes,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn record_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE two_factor
+            SET last_used_step = $2, updated_at = NOW()
+            WHERE user_id = $1
+            AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn use_backup_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE two_factor_backup_codes
+            SET used_at = NOW()
+            WHERE user_id = $1
+            AND code_hash = $2
+            AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_backup_codes(&self, user_id: Uuid) -> Result<i64, sqlx::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM two_factor_backup_codes
+            WHERE user_id = $1
+            AND used_at IS NULL
+            "#,
+            user_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    async fn disable_totp(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE two_factor
+            SET totp_secret = NULL, enabled_at = NULL, last_used_step = NULL, updated_at = NOW()
+            WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM two_factor_backup_codes
+            WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn set_two_factor_required(
+        &self,
+        user_id: Uuid,
+        required: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO two_factor (user_id, required)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET required = EXCLUDED.required, updated_at = NOW()
+            "#,
+            user_id,
+            required,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_two_factor_challenge(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO two_factor_challenges (token_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            token_hash,
+            user_id,
+            expires_at,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_two_factor_challenge(
+        &self,
+        token_hash: &str,
+        max_attempts: i32,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+            UPDATE two_factor_challenges
+            SET failed_attempts = failed_attempts + 1
+            WHERE token_hash = $1
+            AND failed_attempts < $2
+            AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+            token_hash,
+            max_attempts,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    async fn delete_two_factor_challenge(&self, token_hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM two_factor_challenges
+            WHERE token_hash = $1
+            "#,
+            token_hash,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_expired_two_factor_challenges(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM two_factor_challenges
+            WHERE expires_at <= NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait PasswordResetExt {
+    /// Stores a new reset token for the user, replacing any unused earlier one.
+    async fn save_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Uses up the token, sets the new password, revokes every session and deletes the
+    /// user's access tokens in one transaction. Following the emailed link also proves
+    /// the address, so the user counts as verified after it. `None` if the token is
+    /// unknown, used or expired.
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        new_password: String,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn delete_stale_password_reset_tokens(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl PasswordResetExt for DBClient {
+    async fn save_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE user_id = $1
+            AND used_at IS NULL
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            token_hash,
+            user_id,
+            expires_at,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        new_password: String,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_id = sqlx::query_scalar!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = NOW()
+            WHERE token_hash = $1
+            AND used_at IS NULL
+            AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+            token_hash,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+
+        let was_verified = sqlx::query_scalar!(
+            r#"SELECT verified_at IS NOT NULL AS "verified!" FROM users WHERE id = $1 FOR UPDATE"#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // Proving control of the inbox is what verifies the account, so whatever was
+        // set up before then belongs to whoever registered the address, not its owner.
+        if !was_verified {
+            sqlx::query!("DELETE FROM webhook_endpoints WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query!("DELETE FROM two_factor_backup_codes WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query!("DELETE FROM two_factor_challenges WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query!("DELETE FROM two_factor WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query!("DELETE FROM notification_preferences WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET password = $1, verified_at = COALESCE(verified_at, NOW()), updated_at = Now()
+            WHERE id = $2
+            RETURNING id, username, email, password, public_key, role, verified_at, created_at, updated_at
+            "#,
+            new_password,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        revoke_user_credentials(&mut tx, user_id, None, SessionRevokeReason::PasswordReset).await?;
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+
+    async fn delete_stale_password_reset_tokens(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE used_at IS NOT NULL
+            OR expires_at < NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait OidcExt {
+    async fn save_oidc_login_state(
+        &self,
+        state: String,
+        provider: &str,
+        nonce: String,
+        code_verifier: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Removes and returns a pending login; `None` if unknown, expired or for
+    /// another provider.
+    async fn take_oidc_login_state(
+        &self,
+        state: &str,
+        provider: &str,
+    ) -> Result<Option<OidcLoginState>, sqlx::Error>;
+
+    async fn get_identity_user(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    /// Links the provider account to the user, or records another login with it.
+    async fn save_user_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        user_id: Uuid,
+        email: Option<String>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn delete_expired_oidc_login_states(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl OidcExt for DBClient {
+    async fn save_oidc_login_state(
+        &self,
+        state: String,
+        provider: &str,
+        nonce: String,
+        code_verifier: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oidc_login_states (state, provider, nonce, code_verifier, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            state,
+            provider,
+            nonce,
+            code_verifier,
+            expires_at,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_oidc_login_state(
+        &self,
+        state: &str,
+        provider: &str,
+    ) -> Result<Option<OidcLoginState>, sqlx::Error> {
+        let login_state = sqlx::query_as!(
+            OidcLoginState,
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE state = $1
+            AND provider = $2
+            AND expires_at > NOW()
+            RETURNING nonce, code_verifier
+            "#,
+            state,
+            provider,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(login_state)
+    }
+
+    async fn get_identity_user(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT u.id, u.username, u.email, u.password, u.public_key, u.role, u.verified_at,
+                u.created_at, u.updated_at
+            FROM user_identities i
+            JOIN users u ON u.id = i.user_id
+            WHERE i.provider = $1
+            AND i.subject = $2
+            "#,
+            provider,
+            subject,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn save_user_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        user_id: Uuid,
+        email: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_identities (provider, subject, user_id, email)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (provider, subject) DO UPDATE
+            SET email = EXCLUDED.email, last_login_at = NOW()
+            "#,
+            provider,
+            subject,
+            user_id,
+            email,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_expired_oidc_login_states(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE expires_at < NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait AccessTokenExt {
+    async fn save_access_token(
+        &self,
+        user_id: Uuid,
+        name: String,
+        token_hash: String,
+        scopes: Vec<String>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<AccessToken, sqlx::Error>;
+
+    /// The user's tokens, newest first, including expired ones until cleanup removes them.
+    async fn get_access_tokens(&self, user_id: Uuid) -> Result<Vec<AccessToken>, sqlx::Error>;
+
+    async fn get_active_access_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<AccessToken>, sqlx::Error>;
+
+    /// Whether the token still exists and has not expired.
+    async fn is_access_token_active(&self, token_id: Uuid) -> Result<bool, sqlx::Error>;
+
+    async fn touch_access_token(&self, token_id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Deletes one of the user's own tokens; false if it is not theirs.
+    async fn delete_access_token(&self, token_id: Uuid, user_id: Uuid)
+        -> Result<bool, sqlx::Error>;
+
+    async fn delete_expired_access_tokens(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl AccessTokenExt for DBClient {
+    async fn save_access_token(
+        &self,
+        user_id: Uuid,
+        name: String,
+        token_hash: String,
+        scopes: Vec<String>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<AccessToken, sqlx::Error> {
+        let access_token = sqlx::query_as!(
+            AccessToken,
+            r#"
+            INSERT INTO access_tokens (user_id, name, token_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, name, scopes, expires_at, last_used_at, created_at
+            "#,
+            user_id,
+            name,
+            token_hash,
+            &scopes[..],
+            expires_at,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(access_token)
+    }
+
+    async fn get_access_tokens(&self, user_id: Uuid) -> Result<Vec<AccessToken>, sqlx::Error> {
+        let access_tokens = sqlx::query_as!(
+            AccessToken,
+            r#"
+            SELECT id, user_id, name, scopes, expires_at, last_used_at, created_at
+            FROM access_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(access_tokens)
+    }
+
+    async fn get_active_access_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<AccessToken>, sqlx::Error> {
+        let access_token = sqlx::query_as!(
+            AccessToken,
+            r#"
+            SELECT id, user_id, name, scopes, expires_at, last_used_at, created_at
+            FROM access_tokens
+            WHERE token_hash = $1
+            AND expires_at > NOW()
+            "#,
+            token_hash,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(access_token)
+    }
+
+    async fn is_access_token_active(&self, token_id: Uuid) -> Result<bool, sqlx::Error> {
+        let active = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM access_tokens
+                WHERE id = $1
+                AND expires_at > NOW()
+            ) AS "active!"
+            "#,
+            token_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(active)
+    }
+
+    async fn touch_access_token(&self, token_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE access_tokens
+            SET last_used_at = NOW()
+            WHERE id = $1
+            "#,
+            token_id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_access_token(
+        &self,
+        token_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM access_tokens
+            WHERE id = $1
+            AND user_id = $2
+            "#,
+            token_id,
+            user_id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_expired_access_tokens(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM access_tokens
+            WHERE expires_at < NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait RateLimitExt {
+    /// Refills the bucket for the time since it was last used and takes one token,
+    /// in a single statement so concurrent requests cannot overdraw it. `None` when a
+    /// token was taken, otherwise the tokens available (less than one).
+    async fn take_rate_limit_token(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_second: f64,
+        period_seconds: f64,
+    ) -> Result<Option<f64>, sqlx::Error>;
+
+    async fn delete_idle_rate_limit_buckets(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl RateLimitExt for DBClient {
+    async fn take_rate_limit_token(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_second: f64,
+        period_seconds: f64,
+    ) -> Result<Option<f64>, sqlx::Error> {
+        let taken = sqlx::query_scalar!(
+            r#"
+            INSERT INTO rate_limit_buckets AS bucket (key, tokens, updated_at, idle_at)
+            VALUES ($1, $2::float8 - 1, NOW(), NOW() + make_interval(secs => $4::float8))
+            ON CONFLICT (key) DO UPDATE
+            SET tokens = LEAST(
+                    $2::float8,
+                    bucket.tokens
+                        + EXTRACT(EPOCH FROM (NOW() - bucket.updated_at))::float8 * $3::float8
+                ) - 1,
+                updated_at = NOW(),
+                idle_at = NOW() + make_interval(secs => $4::float8)
+            WHERE LEAST(
+                $2::float8,
+                bucket.tokens
+                    + EXTRACT(EPOCH FROM (NOW() - bucket.updated_at))::float8 * $3::float8
+            ) >= 1
+            RETURNING tokens
+            "#,
+            key,
+            capacity,
+            refill_per_second,
+            period_seconds,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if taken.is_some() {
+            return Ok(None);
+        }
+
+        let available = sqlx::query_scalar!(
+            r#"
+            SELECT LEAST(
+                $2::float8,
+                tokens + EXTRACT(EPOCH FROM (NOW() - updated_at))::float8 * $3::float8
+            ) AS "tokens!"
+            FROM rate_limit_buckets
+            WHERE key = $1
+            "#,
+            key,
+            capacity,
+            refill_per_second,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(Some(available.unwrap_or(0.0)))
+    }
+
+    async fn delete_idle_rate_limit_buckets(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM rate_limit_buckets
+            WHERE idle_at < NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait LoginSecurityExt {
+    /// When the email's current lock runs out; `None` if it is not locked.
+    async fn get_login_lock(&self, email: &str) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+
+    /// Counts an attempt for the email as failed before its password or code is
+    /// checked, starting over when the last failure is older than the lockout window or
+    /// an earlier lock has run out. Taking the last of `max_failed_attempts` locks the
+    /// email. The count and the comparison are one statement, so concurrent attempts
+    /// cannot get past the limit together.
+    async fn claim_login_attempt(
+        &self,
+        email: &str,
+        max_failed_attempts: i32,
+        lockout_minutes: i32,
+    ) -> Result<LoginAttemptClaim, sqlx::Error>;
+
+    /// Hands back an attempt that turned out to be right, lifting the lock it took.
+    async fn settle_login_attempt(
+        &self,
+        email: &str,
+        max_failed_attempts: i32,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn clear_login_failures(&self, email: &str) -> Result<bool, sqlx::Error>;
+
+    /// Counts an attempt from the IP as failed before it is checked. `Some(seconds)`
+    /// while the IP has used up `max_failed_attempts` in the current window.
+    async fn claim_login_ip_attempt(
+        &self,
+        ip_address: &str,
+        max_failed_attempts: i64,
+        window_minutes: i32,
+    ) -> Result<Option<u64>, sqlx::Error>;
+
+    /// Hands back an attempt from the IP that turned out to be right or was refused
+    /// for other reasons.
+    async fn settle_login_ip_attempt(&self, ip_address: &str) -> Result<(), sqlx::Error>;
+
+    async fn save_login_event(
+        &self,
+        user_id: Option<Uuid>,
+        email: &str,
+        outcome: LoginOutcome,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_login_events(
         &self,
         user_id: Uuid,
         page: u32,
         limit: usize,
-    ) -> Result<(Vec<ReceiveFileDetails>, i64), sqlx::Error> {
-        let offset = (page - 1) * limit as u32;
+    ) -> Result<(Vec<LoginEvent>, i64), sqlx::Error>;
+
+    /// Looks at the user's earlier successful logins; call it before recording the
+    /// current one.
+    async fn get_known_login(
+        &self,
+        user_id: Uuid,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<KnownLogin, sqlx::Error>;
+
+    async fn delete_old_login_events(&self, retention_days: i32) -> Result<u64, sqlx::Error>;
+
+    async fn delete_stale_login_failures(&self, lockout_minutes: i32) -> Result<u64, sqlx::Error>;
+}
 
-        let files = sqlx::query_as!(
-            ReceiveFileDetails,
+#[async_trait]
+impl LoginSecurityExt for DBClient {
+    async fn get_login_lock(&self, email: &str) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let locked_until = sqlx::query_scalar!(
             r#"
-            SELECT 
-                sl.id AS file_id,
-                f.file_name,
-                u.email AS sender_email,
-                sl.expiration_date,
-                sl.created_at
-            FROM
-                shared_links sl
-            JOIN
-                files f ON sl.file_id = f.id
-            JOIN
-                users u ON f.user_id = u.id
-            WHERE
-                sl.recipient_user_id = $1
-            ORDER BY
-                sl.created_at DESC
+            SELECT locked_until AS "locked_until!"
+            FROM login_failures
+            WHERE email = $1
+            AND locked_until > NOW()
+            "#,
+            email,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(locked_until)
+    }
+
+    async fn claim_login_attempt(
+        &self,
+        email: &str,
+        max_failed_attempts: i32,
+        lockout_minutes: i32,
+    ) -> Result<LoginAttemptClaim, sqlx::Error> {
+        // A locked row is left alone, so no row comes back while the lock holds.
+        let claimed = sqlx::query_scalar!(
+            r#"
+            INSERT INTO login_failures AS failure
+                (email, failed_attempts, last_failed_at, locked_until)
+            VALUES (
+                $1,
+                1,
+                NOW(),
+                CASE WHEN $2 <= 1 THEN NOW() + make_interval(mins => $3::int) END
+            )
+            ON CONFLICT (email) DO UPDATE
+            SET failed_attempts = CASE
+                    WHEN failure.last_failed_at < NOW() - make_interval(mins => $3::int)
+                        OR failure.locked_until IS NOT NULL
+                    THEN 1
+                    ELSE failure.failed_attempts + 1
+                END,
+                locked_until = CASE
+                    WHEN CASE
+                            WHEN failure.last_failed_at < NOW() - make_interval(mins => $3::int)
+                                OR failure.locked_until IS NOT NULL
+                            THEN 1
+                            ELSE failure.failed_attempts + 1
+                        END >= $2
+                    THEN NOW() + make_interval(mins => $3::int)
+                END,
+                last_failed_at = NOW()
+            WHERE failure.locked_until IS NULL
+            OR failure.locked_until <= NOW()
+            RETURNING locked_until
+            "#,
+            email,
+            max_failed_attempts,
+            lockout_minutes,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some(locked_until) = claimed {
+            return Ok(LoginAttemptClaim::Claimed {
+                locks: locked_until.is_some(),
+            });
+        }
+
+        let retry_after = self
+            .get_login_lock(email)
+            .await?
+            .map(|locked_until| (locked_until - Utc::now()).num_seconds().max(1) as u64)
+            .unwrap_or(1);
+
+        Ok(LoginAttemptClaim::Locked(retry_after))
+    }
+
+    async fn settle_login_attempt(
+        &self,
+        email: &str,
+        max_failed_attempts: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE login_failures
+            SET failed_attempts = GREATEST(failed_attempts - 1, 0),
+                locked_until = CASE
+                    WHEN failed_attempts - 1 < $2 THEN NULL
+                    ELSE locked_until
+                END
+            WHERE email = $1
+            "#,
+            email,
+            max_failed_attempts,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, email: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM login_failures
+            WHERE email = $1
+            "#,
+            email,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn claim_login_ip_attempt(
+        &self,
+        ip_address: &str,
+        max_failed_attempts: i64,
+        window_minutes: i32,
+    ) -> Result<Option<u64>, sqlx::Error> {
+        // An IP at its limit is left alone, so no row comes back until the window ends.
+        let claimed = sqlx::query_scalar!(
+            r#"
+            INSERT INTO login_ip_failures AS failure
+                (ip_address, failed_attempts, window_started_at)
+            VALUES ($1, 1, NOW())
+            ON CONFLICT (ip_address) DO UPDATE
+            SET failed_attempts = CASE
+                    WHEN failure.window_started_at < NOW() - make_interval(mins => $3::int)
+                    THEN 1
+                    ELSE failure.failed_attempts + 1
+                END,
+                window_started_at = CASE
+                    WHEN failure.window_started_at < NOW() - make_interval(mins => $3::int)
+                    THEN NOW()
+                    ELSE failure.window_started_at
+                END
+            WHERE failure.failed_attempts < $2::bigint
+            OR failure.window_started_at < NOW() - make_interval(mins => $3::int)
+            RETURNING failed_attempts
+            "#,
+            ip_address,
+            max_failed_attempts,
+            window_minutes,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        let window_started_at = sqlx::query_scalar!(
+            r#"
+            SELECT window_started_at
+            FROM login_ip_failures
+            WHERE ip_address = $1
+            "#,
+            ip_address,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let retry_after = window_started_at
+            .map(|started_at| {
+                started_at + chrono::Duration::minutes(window_minutes as i64) - Utc::now()
+            })
+            .map(|wait| wait.num_seconds().max(1) as u64)
+            .unwrap_or(1);
+
+        Ok(Some(retry_after))
+    }
+
+    async fn settle_login_ip_attempt(&self, ip_address: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE login_ip_failures
+            SET failed_attempts = GREATEST(failed_attempts - 1, 0)
+            WHERE ip_address = $1
+            "#,
+            ip_address,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_login_event(
+        &self,
+        user_id: Option<Uuid>,
+        email: &str,
+        outcome: LoginOutcome,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_events (user_id, email, outcome, ip_address, user_agent)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            user_id,
+            email,
+            outcome.to_str(),
+            ip_address,
+            user_agent,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_login_events(
+        &self,
+        user_id: Uuid,
+        page: u32,
+        limit: usize,
+    ) -> Result<(Vec<LoginEvent>, i64), sqlx::Error> {
+        let offset = page.saturating_sub(1) as i64 * limit as i64;
+
+        let events = sqlx::query_as!(
+            LoginEvent,
+            r#"
+            SELECT id, outcome, ip_address, user_agent, created_at
+            FROM login_events
+            WHERE user_id = $1
+            ORDER BY created_at DESC
             LIMIT $2
             OFFSET $3
             "#,
             user_id,
             limit as i64,
-            offset as i64,
+            offset,
         )
         .fetch_all(&self.pool)
         .await?;
@@ -398,9 +4017,8 @@ impl UserExt for DBClient {
         let count_row = sqlx::query_scalar!(
             r#"
             SELECT COUNT(*)
-            FROM shared_links sl
-            JOIN files f ON sl.file_id = f.id
-            WHERE sl.recipient_user_id = $1
+            FROM login_events
+            WHERE user_id = $1
             "#,
             user_id,
         )
@@ -408,60 +4026,236 @@ impl UserExt for DBClient {
         .await?;
 
         let total_count = count_row.unwrap_or(0);
-        Ok((files, total_count))
+        Ok((events, total_count))
     }
 
-    async fn delete_expired_files(&self) -> Result<(), sqlx::Error> {
-        let expired_shared_links: Vec<Uuid> = sqlx::query_scalar!(
+    async fn get_known_login(
+        &self,
+        user_id: Uuid,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<KnownLogin, sqlx::Error> {
+        let row = sqlx::query!(
             r#"
-            SELECT sl.id
-            FROM shared_links sl
-            WHERE sl.expiration_date < NOW()
+            SELECT
+                COUNT(*) > 0 AS "has_previous_login!",
+                COALESCE(BOOL_OR(ip_address IS NOT DISTINCT FROM $2), FALSE) AS "ip_address_seen!",
+                COALESCE(BOOL_OR(user_agent IS NOT DISTINCT FROM $3), FALSE) AS "user_agent_seen!"
+            FROM login_events
+            WHERE user_id = $1
+            AND outcome = 'success'
             "#,
+            user_id,
+            ip_address,
+            user_agent,
         )
-        .fetch_all(&self.pool)
+        .fetch_one(&self.pool)
         .await?;
 
-        if expired_shared_links.is_empty() {
-            println!("No expired shared links found.");
-            return Ok(());
-        }
+        Ok(KnownLogin {
+            has_previous_login: row.has_previous_login,
+            ip_address_seen: row.ip_address_seen,
+            user_agent_seen: row.user_agent_seen,
+        })
+    }
 
-        let expired_file_ids: Vec<Uuid> = sqlx::query_scalar!(
+    async fn delete_old_login_events(&self, retention_days: i32) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
             r#"
-            SELECT f.id
-            FROM files f
-            WHERE f.id IN(
-                SELECT sl.file_id
-                FROM shared_links sl
-                WHERE sl.expiration_date < NOW()
-            )
+            DELETE FROM login_events
+            WHERE created_at < NOW() - make_interval(days => $1::int)
             "#,
+            retention_days,
         )
-        .fetch_all(&self.pool)
+        .execute(&self.pool)
         .await?;
 
-        sqlx::query!(
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_stale_login_failures(&self, lockout_minutes: i32) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
             r#"
-            DELETE FROM shared_links
-            WHERE id = ANY($1)
+            DELETE FROM login_failures
+            WHERE last_failed_at < NOW() - make_interval(mins => $1::int)
+            AND (locked_until IS NULL OR locked_until < NOW())
             "#,
-            &expired_shared_links[..]
+            lockout_minutes,
         )
         .execute(&self.pool)
         .await?;
 
-        sqlx::query!(
+        let ip_result = sqlx::query!(
             r#"
-            DELETE FROM files
-            WHERE id = ANY($1)
+            DELETE FROM login_ip_failures
+            WHERE window_started_at < NOW() - make_interval(mins => $1::int)
             "#,
-            &expired_file_ids[..]
+            lockout_minutes,
         )
         .execute(&self.pool)
         .await?;
 
-        println!("Successfully deleted expired files and shared links.");
-        Ok(())
+        Ok(result.rows_affected() + ip_result.rows_affected())
+    }
+}
+
+/// Locks the roles that grant `roles:manage`, so concurrent role changes cannot each
+/// count on the other's holders and together leave nobody able to manage roles.
+async fn lock_role_managers(tx: &mut Transaction<'_, Postgres>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        SELECT name
+        FROM roles
+        WHERE $1 = ANY(permissions)
+        FOR UPDATE
+        "#,
+        Permission::RolesManage.to_str(),
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+pub trait RoleExt {
+    /// Built-in roles first, then custom ones by name.
+    async fn get_roles(&self) -> Result<Vec<Role>, sqlx::Error>;
+
+    async fn get_role(&self, name: &str) -> Result<Option<Role>, sqlx::Error>;
+
+    async fn save_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<String>,
+    ) -> Result<Role, sqlx::Error>;
+
+    /// Changes a custom role; `None` if there is no custom role by that name or the
+    /// change would take `roles:manage` from its last holders.
+    async fn update_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<String>,
+    ) -> Result<Option<Role>, sqlx::Error>;
+
+    /// Deletes a custom role; false if there is no custom role by that name. Fails
+    /// with a foreign key violation while users still have it.
+    async fn delete_role(&self, name: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+impl RoleExt for DBClient {
+    async fn get_roles(&self) -> Result<Vec<Role>, sqlx::Error> {
+        let roles = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT name, description, permissions, built_in, created_at, updated_at
+            FROM roles
+            ORDER BY built_in DESC, name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    async fn get_role(&self, name: &str) -> Result<Option<Role>, sqlx::Error> {
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT name, description, permissions, built_in, created_at, updated_at
+            FROM roles
+            WHERE name = $1
+            "#,
+            name,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    async fn save_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<String>,
+    ) -> Result<Role, sqlx::Error> {
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            INSERT INTO roles (name, description, permissions)
+            VALUES ($1, $2, $3)
+            RETURNING name, description, permissions, built_in, created_at, updated_at
+            "#,
+            name,
+            description,
+            &permissions[..],
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    async fn update_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<String>,
+    ) -> Result<Option<Role>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        lock_role_managers(&mut tx).await?;
+
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            UPDATE roles
+            SET description = $2, permissions = $3, updated_at = NOW()
+            WHERE name = $1
+            AND built_in = FALSE
+            AND (
+                $4 = ANY($3)
+                OR NOT $4 = ANY(roles.permissions)
+                OR NOT EXISTS (SELECT 1 FROM users WHERE role = $1)
+                OR EXISTS (
+                    SELECT 1
+                    FROM users holder
+                    JOIN roles other ON other.name = holder.role
+                    WHERE other.name <> $1
+                    AND $4 = ANY(other.permissions)
+                )
+            )
+            RETURNING name, description, permissions, built_in, created_at, updated_at
+            "#,
+            name,
+            description,
+            &permissions[..],
+            Permission::RolesManage.to_str(),
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(role)
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM roles
+            WHERE name = $1
+            AND built_in = FALSE
+            "#,
+            name,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
     }
 }
@@ -1,10 +1,21 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use core::str;
 use serde::{Deserialize, Serialize};
-use std::iter::Filter;
 use validator::{Validate, ValidationError};
 
-use crate::models::{ReceiveFileDetails, SendFileDetails, User};
+use crate::{
+    db::ShareListFilter,
+    models::{
+        AccessToken, AccessTokenScope, FileRequest, LoginEvent, NotificationPreferences,
+        Permission, ReceiveFileDetails, RecipientState, Role, SendFileDetails, Session,
+        ShareAccessLog, ShareDetails, ShareDirection, ShareHistoryRow, ShareSortField, ShareStatus,
+        StatsBucket, TransferStats, TransferStatsBucket, TwoFactor, User, WebhookDeliveryAttempt,
+        WebhookEndpoint,
+    },
+    oidc::OidcProvider,
+    utils::{cursor::ShareCursor, expiry::parse_duration, export::ExportFormat},
+    webhook::WEBHOOK_EVENTS,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, Default)]
 pub struct RegisterUserDto {
@@ -54,12 +65,262 @@ pub struct RequestQueryDto {
     pub limit: Option<usize>,
 }
 
+/// Query for the send and receive lists. Dates are RFC 3339 timestamps or plain
+/// `YYYY-MM-DD` days; a plain day in a `_to` bound covers the whole day.
+///
+/// `cursor` takes a `next_cursor`/`prev_cursor` from an earlier response and replaces
+/// `page`; the filters and order must be sent again unchanged.
+#[derive(Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_share_list_query", skip_on_field_errors = false))]
+pub struct ShareListQueryDto {
+    #[validate(range(min = 1))]
+    pub page: Option<usize>,
+    #[validate(range(min = 1, max = 50))]
+    pub limit: Option<usize>,
+    /// Comma-separated recipient states, or `all`. Archived shares are hidden by default.
+    /// Only used by the receive list.
+    #[validate(custom = "validate_receive_state_filter")]
+    pub state: Option<String>,
+    #[validate(length(max = 255))]
+    pub search: Option<String>,
+    #[validate(length(max = 255))]
+    pub email: Option<String>,
+    #[validate(custom = "validate_date_bound")]
+    pub created_from: Option<String>,
+    #[validate(custom = "validate_date_bound")]
+    pub created_to: Option<String>,
+    #[validate(custom = "validate_date_bound")]
+    pub expires_from: Option<String>,
+    #[validate(custom = "validate_date_bound")]
+    pub expires_to: Option<String>,
+    /// Comma-separated share statuses; a share matching any of them is listed.
+    #[validate(custom = "validate_share_status_filter")]
+    pub status: Option<String>,
+    #[validate(custom = "validate_share_sort")]
+    pub sort: Option<String>,
+    #[validate(custom = "validate_sort_order")]
+    pub order: Option<String>,
+    #[validate(custom = "validate_share_cursor")]
+    pub cursor: Option<String>,
+}
+
+impl ShareListQueryDto {
+    /// Call after `validate()`; unparseable values have already been rejected there.
+    pub fn to_filter(&self) -> ShareListFilter {
+        let text = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+        let bound = |value: &Option<String>, end_of_day: bool| {
+            value
+                .as_deref()
+                .and_then(|value| parse_date_bound(value, end_of_day))
+        };
+
+        ShareListFilter {
+            search: text(&self.search),
+            email: text(&self.email),
+            created_from: bound(&self.created_from, false),
+            created_to: bound(&self.created_to, true),
+            expires_from: bound(&self.expires_from, false),
+            expires_to: bound(&self.expires_to, true),
+            statuses: self
+                .status
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|s| ShareStatus::parse(s.trim()))
+                .collect(),
+            sort: self
+                .sort
+                .as_deref()
+                .and_then(ShareSortField::parse)
+                .unwrap_or(ShareSortField::CreatedAt),
+            descending: self.order.as_deref() != Some("asc"),
+            cursor: self.cursor.as_deref().and_then(ShareCursor::decode),
+        }
+    }
+
+    pub fn states(&self) -> Vec<String> {
+        match self.state.as_deref() {
+            None | Some("") => vec![
+                RecipientState::New.to_str().to_string(),
+                RecipientState::Opened.to_str().to_string(),
+            ],
+            Some("all") => RecipientState::ALL.iter().map(|s| s.to_string()).collect(),
+            Some(states) => states.split(',').map(|s| s.trim().to_string()).collect(),
+        }
+    }
+}
+
+fn validate_receive_state_filter(state: &str) -> Result<(), ValidationError> {
+    if state.is_empty() || state == "all" {
+        return Ok(());
+    }
+
+    if let Some(unknown) = state
+        .split(',')
+        .map(str::trim)
+        .find(|s| RecipientState::parse(s).is_none())
+    {
+        let mut error = ValidationError::new("recipient_state_unknown");
+        error.message = Some(
+            format!(
+                "Unknown state {}. Use {} or all.",
+                unknown,
+                RecipientState::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Parses a list date filter. Plain days start at midnight UTC; as an upper bound
+/// they run until the start of the next day.
+fn parse_date_bound(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    let day = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let day = if end_of_day { day.succ_opt()? } else { day };
+
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+fn validate_date_bound(value: &str) -> Result<(), ValidationError> {
+    if parse_date_bound(value, false).is_none() {
+        let mut error = ValidationError::new("invalid_date");
+        error.message = Some("Use an RFC3339 date or YYYY-MM-DD.".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn validate_share_status_filter(status: &str) -> Result<(), ValidationError> {
+    if let Some(unknown) = status
+        .split(',')
+        .map(str::trim)
+        .find(|s| ShareStatus::parse(s).is_none())
+    {
+        let mut error = ValidationError::new("share_status_unknown");
+        error.message = Some(
+            format!(
+                "Unknown status {}. Use {}.",
+                unknown,
+                ShareStatus::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn validate_share_sort(sort: &str) -> Result<(), ValidationError> {
+    if ShareSortField::parse(sort).is_none() {
+        let mut error = ValidationError::new("share_sort_unknown");
+        error.message = Some(
+            format!(
+                "Unknown sort field {}. Use {}.",
+                sort,
+                ShareSortField::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn validate_share_cursor(cursor: &str) -> Result<(), ValidationError> {
+    if ShareCursor::decode(cursor).is_none() {
+        let mut error = ValidationError::new("invalid_cursor");
+        error.message = Some("Cursor is not valid.".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Cursors are keyed on creation time, so they cannot be mixed with other sorts or
+/// with offset pages.
+fn validate_share_list_query(query: &ShareListQueryDto) -> Result<(), ValidationError> {
+    if query.cursor.is_none() {
+        return Ok(());
+    }
+
+    if query.page.is_some() {
+        let mut error = ValidationError::new("cursor_with_page");
+        error.message = Some("Use either cursor or page, not both.".into());
+        return Err(error);
+    }
+
+    if query
+        .sort
+        .as_deref()
+        .is_some_and(|sort| ShareSortField::parse(sort) != Some(ShareSortField::CreatedAt))
+    {
+        let mut error = ValidationError::new("cursor_with_sort");
+        error.message = Some("Cursors can only be used when sorting by created_at.".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn validate_sort_order(order: &str) -> Result<(), ValidationError> {
+    if order != "asc" && order != "desc" {
+        let mut error = ValidationError::new("sort_order_unknown");
+        error.message = Some("Order must be asc or desc.".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RecipientStateUpdateDto {
+    #[validate(custom = "validate_recipient_state")]
+    pub state: String,
+}
+
+fn validate_recipient_state(state: &str) -> Result<(), ValidationError> {
+    if RecipientState::parse(state).is_none() {
+        let mut error = ValidationError::new("recipient_state_unknown");
+        error.message = Some(
+            format!(
+                "Unknown state {}. Use {}.",
+                state,
+                RecipientState::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FilterUserDto {
     pub id: String,
     pub username: String,
     pub email: String,
     pub public_key: Option<String>,
+    pub role: String,
+    pub verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -71,93 +332,834 @@ impl FilterUserDto {
             username: user.username.to_owned(),
             email: user.email.to_owned(),
             public_key: user.public_key.to_owned(),
-            created_at: user.created_at.unwrap_or_else(|| Utc::now()), //might have to change the unwrap.
-            updated_at: user.updated_at.unwrap_or_else(|| Utc::now()),
+            role: user.role.to_owned(),
+            verified_at: user.verified_at,
+            created_at: user.created_at.unwrap_or_else(Utc::now), //might have to change the unwrap.
+            updated_at: user.updated_at.unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserData {
+    pub user: FilterUserDto,
+    /// What the user's role grants.
+    pub permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserResponseDto {
+    pub status: String,
+    pub data: UserData,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserSendFileDto {
+    pub file_id: String,
+    pub share_id: String,
+    pub file_name: String,
+    pub recipient_email: String,
+    pub expiration_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
+}
+
+impl UserSendFileDto {
+    pub fn filter_send_user_file(file_data: &SendFileDetails) -> Self {
+        UserSendFileDto {
+            file_id: file_data.file_id.to_string(),
+            share_id: file_data.share_id.to_string(),
+            file_name: file_data.file_name.to_owned(),
+            recipient_email: file_data.recipient_email.to_owned(),
+            expiration_date: file_data.expiration_date.unwrap(),
+            created_at: file_data.created_at.unwrap(),
+            download_count: file_data.download_count,
+            downloaded_at: file_data.downloaded_at,
+        }
+    }
+    pub fn filter_send_user_files(user: &[SendFileDetails]) -> Vec<UserSendFileDto> {
+        user.iter()
+            .map(UserSendFileDto::filter_send_user_file)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserSendFileListResponseDto {
+    pub status: String,
+    pub files: Vec<UserSendFileDto>,
+    pub results: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareAccessLogDto {
+    pub id: String,
+    pub outcome: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ShareAccessLogDto {
+    pub fn filter_access_log(log: &ShareAccessLog) -> Self {
+        ShareAccessLogDto {
+            id: log.id.to_string(),
+            outcome: log.outcome.to_owned(),
+            ip_address: log.ip_address.to_owned(),
+            user_agent: log.user_agent.to_owned(),
+            created_at: log.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+    pub fn filter_access_logs(logs: &[ShareAccessLog]) -> Vec<ShareAccessLogDto> {
+        logs.iter()
+            .map(ShareAccessLogDto::filter_access_log)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareAccessLogListResponseDto {
+    pub status: String,
+    pub logs: Vec<ShareAccessLogDto>,
+    pub results: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserReceiveFileDto {
+    pub share_id: String,
+    pub file_id: String,
+    pub file_name: String,
+    pub sender_email: String,
+    pub file_request_id: Option<String>,
+    pub state: String,
+    pub available_from: Option<DateTime<Utc>>,
+    pub expiration_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserReceiveFileDto {
+    pub fn filter_receive_user_file(file_data: &ReceiveFileDetails) -> Self {
+        UserReceiveFileDto {
+            share_id: file_data.share_id.to_string(),
+            file_id: file_data.file_id.to_string(),
+            file_name: file_data.file_name.to_owned(),
+            sender_email: file_data.sender_email.to_owned(),
+            file_request_id: file_data.file_request_id.map(|id| id.to_string()),
+            state: file_data.recipient_state.to_owned(),
+            available_from: file_data.available_from,
+            expiration_date: file_data.expiration_date.unwrap(),
+            created_at: file_data.created_at.unwrap(),
+        }
+    }
+    pub fn filter_receive_user_files(user: &[ReceiveFileDetails]) -> Vec<UserReceiveFileDto> {
+        user.iter()
+            .map(UserReceiveFileDto::filter_receive_user_file)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserReceiveFileListResponseDto {
+    pub status: String,
+    pub files: Vec<UserReceiveFileDto>,
+    pub results: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareDetailDto {
+    pub share_id: String,
+    pub file_id: String,
+    pub file_name: String,
+    pub file_size: i64,
+    pub sha256: Option<String>,
+    /// `sender` or `recipient`, from the caller's point of view.
+    pub role: String,
+    pub sender_email: String,
+    pub recipient_email: String,
+    pub file_request_id: Option<String>,
+    pub status: String,
+    /// Only shown to the recipient; it describes their inbox.
+    pub recipient_state: Option<String>,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub available_from: Option<DateTime<Utc>>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ShareDetailDto {
+    pub fn filter_share(share: &ShareDetails, user_id: uuid::Uuid) -> Self {
+        let is_recipient = share.recipient_id == user_id;
+
+        ShareDetailDto {
+            share_id: share.share_id.to_string(),
+            file_id: share.file_id.to_string(),
+            file_name: share.file_name.to_owned(),
+            file_size: share.file_size,
+            sha256: share.sha256.to_owned(),
+            role: if is_recipient { "recipient" } else { "sender" }.to_string(),
+            sender_email: share.sender_email.to_owned(),
+            recipient_email: share.recipient_email.to_owned(),
+            file_request_id: share.file_request_id.map(|id| id.to_string()),
+            status: share.status().to_str().to_string(),
+            recipient_state: is_recipient.then(|| share.recipient_state.to_owned()),
+            download_count: share.download_count,
+            downloaded_at: share.downloaded_at,
+            revoked_at: share.revoked_at,
+            available_from: share.available_from,
+            expiration_date: share.expiration_date,
+            created_at: share.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareDetailResponseDto {
+    pub status: String,
+    pub share: ShareDetailDto,
+}
+
+/// Range for `/stats`. Defaults to the last 30 days in daily buckets; dates take
+/// the same forms as the list filters.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct StatsQueryDto {
+    #[validate(custom = "validate_date_bound")]
+    pub from: Option<String>,
+    #[validate(custom = "validate_date_bound")]
+    pub to: Option<String>,
+    #[validate(custom = "validate_stats_bucket")]
+    pub bucket: Option<String>,
+}
+
+impl StatsQueryDto {
+    /// Call after `validate()`.
+    pub fn range(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let to = self
+            .to
+            .as_deref()
+            .and_then(|to| parse_date_bound(to, true))
+            .unwrap_or(now);
+        let from = self
+            .from
+            .as_deref()
+            .and_then(|from| parse_date_bound(from, false))
+            .unwrap_or(to - chrono::Duration::days(30));
+
+        (from, to)
+    }
+
+    pub fn bucket(&self) -> StatsBucket {
+        self.bucket
+            .as_deref()
+            .and_then(StatsBucket::parse)
+            .unwrap_or(StatsBucket::Day)
+    }
+}
+
+fn validate_stats_bucket(bucket: &str) -> Result<(), ValidationError> {
+    if StatsBucket::parse(bucket).is_none() {
+        let mut error = ValidationError::new("stats_bucket_unknown");
+        error.message = Some(
+            format!(
+                "Unknown bucket {}. Use {}.",
+                bucket,
+                StatsBucket::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TransferStatsDto {
+    pub files_sent: i64,
+    pub bytes_sent: i64,
+    pub files_received: i64,
+    pub bytes_received: i64,
+    pub unique_counterparts: i64,
+    pub avg_lifetime_seconds: Option<f64>,
+    /// Share of sent files downloaded at least once, from 0 to 1.
+    pub download_rate: Option<f64>,
+}
+
+impl TransferStatsDto {
+    pub fn filter_stats(stats: &TransferStats) -> Self {
+        TransferStatsDto {
+            files_sent: stats.files_sent,
+            bytes_sent: stats.bytes_sent,
+            files_received: stats.files_received,
+            bytes_received: stats.bytes_received,
+            unique_counterparts: stats.unique_counterparts,
+            avg_lifetime_seconds: stats.avg_lifetime_seconds,
+            download_rate: stats.download_rate,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TransferStatsBucketDto {
+    pub start: DateTime<Utc>,
+    pub files_sent: i64,
+    pub bytes_sent: i64,
+    pub files_received: i64,
+    pub bytes_received: i64,
+}
+
+impl TransferStatsBucketDto {
+    pub fn filter_buckets(buckets: &[TransferStatsBucket]) -> Vec<Self> {
+        buckets
+            .iter()
+            .map(|bucket| TransferStatsBucketDto {
+                start: bucket.bucket_start,
+                files_sent: bucket.files_sent,
+                bytes_sent: bucket.bytes_sent,
+                files_received: bucket.files_received,
+                bytes_received: bucket.bytes_received,
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TransferStatsResponseDto {
+    pub status: String,
+    pub scope: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket: String,
+    pub totals: TransferStatsDto,
+    pub buckets: Vec<TransferStatsBucketDto>,
+}
+
+/// Format of a history export; the list filters are read from the same query string.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ExportQueryDto {
+    #[validate(custom = "validate_export_format")]
+    pub format: Option<String>,
+}
+
+impl ExportQueryDto {
+    pub fn format(&self) -> ExportFormat {
+        self.format
+            .as_deref()
+            .and_then(ExportFormat::parse)
+            .unwrap_or(ExportFormat::Csv)
+    }
+}
+
+fn validate_export_format(format: &str) -> Result<(), ValidationError> {
+    if ExportFormat::parse(format).is_none() {
+        let mut error = ValidationError::new("export_format_unknown");
+        error.message = Some(
+            format!(
+                "Unknown format {}. Use {}.",
+                format,
+                ExportFormat::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareHistoryDto {
+    pub direction: String,
+    pub share_id: String,
+    pub file_id: String,
+    pub file_name: String,
+    pub file_size: i64,
+    pub sha256: Option<String>,
+    pub counterpart_email: String,
+    pub status: String,
+    /// Only exported for received shares; it describes the recipient's inbox.
+    pub recipient_state: Option<String>,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub available_from: Option<DateTime<Utc>>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ShareHistoryDto {
+    pub const CSV_HEADER: [&'static str; 15] = [
+        "direction",
+        "share_id",
+        "file_id",
+        "file_name",
+        "file_size",
+        "sha256",
+        "counterpart_email",
+        "status",
+        "recipient_state",
+        "download_count",
+        "downloaded_at",
+        "revoked_at",
+        "available_from",
+        "expiration_date",
+        "created_at",
+    ];
+
+    pub fn filter_row(row: &ShareHistoryRow, direction: &ShareDirection) -> Self {
+        ShareHistoryDto {
+            direction: direction.to_str().to_string(),
+            share_id: row.share_id.to_string(),
+            file_id: row.file_id.to_string(),
+            file_name: row.file_name.to_owned(),
+            file_size: row.file_size,
+            sha256: row.sha256.to_owned(),
+            counterpart_email: row.counterpart_email.to_owned(),
+            status: row.status.to_owned(),
+            recipient_state: (*direction == ShareDirection::Received)
+                .then(|| row.recipient_state.to_owned()),
+            download_count: row.download_count,
+            downloaded_at: row.downloaded_at,
+            revoked_at: row.revoked_at,
+            available_from: row.available_from,
+            expiration_date: row.expiration_date,
+            created_at: row.created_at,
+        }
+    }
+
+    /// Values in `CSV_HEADER` order; missing values are empty.
+    pub fn csv_record(&self) -> [String; 15] {
+        let time = |value: &Option<DateTime<Utc>>| {
+            value.map(|value| value.to_rfc3339()).unwrap_or_default()
+        };
+
+        [
+            self.direction.to_owned(),
+            self.share_id.to_owned(),
+            self.file_id.to_owned(),
+            self.file_name.to_owned(),
+            self.file_size.to_string(),
+            self.sha256.to_owned().unwrap_or_default(),
+            self.counterpart_email.to_owned(),
+            self.status.to_owned(),
+            self.recipient_state.to_owned().unwrap_or_default(),
+            self.download_count.to_string(),
+            time(&self.downloaded_at),
+            time(&self.revoked_at),
+            time(&self.available_from),
+            time(&self.expiration_date),
+            time(&self.created_at),
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserLoginResponseDto {
+    pub status: String,
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionDto {
+    pub id: String,
+    /// The session this request was made with.
+    pub current: bool,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SessionDto {
+    pub fn filter_session(session: &Session, current_session_id: uuid::Uuid) -> Self {
+        SessionDto {
+            id: session.id.to_string(),
+            current: session.id == current_session_id,
+            user_agent: session.user_agent.to_owned(),
+            ip_address: session.ip_address.to_owned(),
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            expires_at: session.expires_at,
+        }
+    }
+
+    pub fn filter_sessions(sessions: &[Session], current_session_id: uuid::Uuid) -> Vec<Self> {
+        sessions
+            .iter()
+            .map(|session| SessionDto::filter_session(session, current_session_id))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionListResponseDto {
+    pub status: String,
+    pub sessions: Vec<SessionDto>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginEventDto {
+    pub id: String,
+    pub outcome: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LoginEventDto {
+    pub fn filter_login_event(event: &LoginEvent) -> Self {
+        LoginEventDto {
+            id: event.id.to_string(),
+            outcome: event.outcome.to_owned(),
+            ip_address: event.ip_address.to_owned(),
+            user_agent: event.user_agent.to_owned(),
+            created_at: event.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+
+    pub fn filter_login_events(events: &[LoginEvent]) -> Vec<LoginEventDto> {
+        events
+            .iter()
+            .map(LoginEventDto::filter_login_event)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginEventListResponseDto {
+    pub status: String,
+    pub events: Vec<LoginEventDto>,
+    pub results: i64,
+}
+
+/// Returned by `login` instead of tokens when the account has 2FA enabled.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorChallengeResponseDto {
+    pub status: String,
+    pub challenge_token: String,
+}
+
+/// Second login step. `code` is a TOTP code or one of the backup codes.
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorLoginDto {
+    #[validate(length(min = 1, message = "Challenge token is required"))]
+    pub challenge_token: String,
+
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorCodeDto {
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+/// Turning 2FA off needs both the password and a current code or backup code.
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorDisableDto {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorStatusDto {
+    pub enabled: bool,
+    /// Set by an admin; files cannot be retrieved until 2FA is enabled.
+    pub required: bool,
+    pub backup_codes_remaining: i64,
+}
+
+impl TwoFactorStatusDto {
+    pub fn filter_two_factor(two_factor: Option<&TwoFactor>, backup_codes_remaining: i64) -> Self {
+        TwoFactorStatusDto {
+            enabled: two_factor.is_some_and(|two_factor| two_factor.is_enabled()),
+            required: two_factor.is_some_and(|two_factor| two_factor.required),
+            backup_codes_remaining,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorStatusResponseDto {
+    pub status: String,
+    pub two_factor: TwoFactorStatusDto,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorSetupResponseDto {
+    pub status: String,
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+/// Backup codes are only ever shown in this response.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorEnabledResponseDto {
+    pub status: String,
+    pub backup_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorRequirementDto {
+    pub required: bool,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyEmailDto {
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct ResendVerificationDto {
+    #[validate(
+        length(min = 1, message = "Email is required"),
+        email(message = "Email is invalid")
+    )]
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcProviderDto {
+    pub name: String,
+    pub display_name: String,
+}
+
+impl OidcProviderDto {
+    pub fn filter_providers(providers: &[OidcProvider]) -> Vec<Self> {
+        providers
+            .iter()
+            .map(|provider| OidcProviderDto {
+                name: provider.name.to_owned(),
+                display_name: provider.display_name.to_owned(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcProviderListResponseDto {
+    pub status: String,
+    pub providers: Vec<OidcProviderDto>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcAuthorizeResponseDto {
+    pub status: String,
+    pub authorization_url: String,
+}
+
+/// What the provider appended to the redirect URI.
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct OidcCallbackDto {
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+
+    #[validate(length(min = 1, message = "State is required"))]
+    pub state: String,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAccessTokenDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: String,
+
+    #[validate(custom = "validate_access_token_scopes")]
+    pub scopes: Vec<String>,
+
+    /// Defaults to 90 days.
+    #[validate(range(min = 1, max = 365, message = "Tokens can last 1 to 365 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+fn validate_access_token_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    if scopes.is_empty() {
+        let mut error = ValidationError::new("access_token_scopes_required");
+        error.message = Some("At least one scope is required.".into());
+        return Err(error);
+    }
+
+    if let Some(unknown) = scopes
+        .iter()
+        .find(|scope| AccessTokenScope::parse(scope).is_none())
+    {
+        let mut error = ValidationError::new("access_token_scope_unknown");
+        error.message = Some(
+            format!(
+                "Unknown scope {}. Supported scopes: {}.",
+                unknown,
+                AccessTokenScope::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccessTokenDto {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AccessTokenDto {
+    pub fn filter_access_token(access_token: &AccessToken) -> Self {
+        AccessTokenDto {
+            id: access_token.id.to_string(),
+            name: access_token.name.to_owned(),
+            scopes: access_token.scopes.to_owned(),
+            expires_at: access_token.expires_at,
+            last_used_at: access_token.last_used_at,
+            created_at: access_token.created_at,
         }
     }
+
+    pub fn filter_access_tokens(access_tokens: &[AccessToken]) -> Vec<Self> {
+        access_tokens
+            .iter()
+            .map(AccessTokenDto::filter_access_token)
+            .collect()
+    }
 }
 
+/// The token itself is only ever shown in this response.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UserData {
-    pub user: FilterUserDto,
+pub struct AccessTokenCreatedResponseDto {
+    pub status: String,
+    pub access_token: AccessTokenDto,
+    pub token: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UserResponseDto {
+pub struct AccessTokenListResponseDto {
     pub status: String,
-    pub data: UserData,
+    pub access_tokens: Vec<AccessTokenDto>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct UserSendFileDto {
-    pub file_id: String,
-    pub file_name: String,
-    pub recipient_email: String,
-    pub expiration_date: DateTime<Utc>,
-    pub created_at: DateTime<Utc>,
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRoleDto {
+    #[validate(custom = "validate_role_name")]
+    pub name: String,
+
+    #[validate(length(max = 255, message = "Description must be at most 255 characters"))]
+    pub description: Option<String>,
+
+    #[validate(custom = "validate_permissions")]
+    pub permissions: Vec<String>,
 }
 
-impl UserSendFileDto {
-    pub fn filter_send_user_file(file_data: &SendFileDetails) -> Self {
-        UserSendFileDto {
-            file_id: file_data.file_id.to_string(),
-            file_name: file_data.file_name.to_owned(),
-            recipient_email: file_data.recipient_email.to_owned(),
-            expiration_date: file_data.expiration_date.unwrap(),
-            created_at: file_data.created_at.unwrap(),
-        }
-    }
-    pub fn filter_send_user_files(user: &[SendFileDetails]) -> Vec<UserSendFileDto> {
-        user.iter()
-            .map(UserSendFileDto::filter_send_user_file)
-            .collect()
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateRoleDto {
+    #[validate(length(max = 255, message = "Description must be at most 255 characters"))]
+    pub description: Option<String>,
+
+    #[validate(custom = "validate_permissions")]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct AssignRoleDto {
+    #[validate(length(min = 1, message = "Role is required"))]
+    pub role: String,
+}
+
+fn validate_role_name(name: &str) -> Result<(), ValidationError> {
+    let valid = (1..=50).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+
+    if !valid {
+        let mut error = ValidationError::new("role_name_invalid");
+        error.message =
+            Some("Role names are 1 to 50 lowercase letters, digits, dashes or underscores.".into());
+        return Err(error);
     }
+
+    Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct UserSendFileListResponseDto {
-    pub status: String,
-    pub files: Vec<UserSendFileDto>,
-    pub results: i64,
+fn validate_permissions(permissions: &[String]) -> Result<(), ValidationError> {
+    if let Some(unknown) = permissions
+        .iter()
+        .find(|permission| Permission::parse(permission).is_none())
+    {
+        let mut error = ValidationError::new("permission_unknown");
+        error.message = Some(
+            format!(
+                "Unknown permission {}. Supported permissions: {}.",
+                unknown,
+                Permission::ALL.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UserReceiveFileDto {
-    pub file_id: String,
-    pub file_name: String,
-    pub sender_email: String,
-    pub expiration_date: DateTime<Utc>,
-    pub created_at: DateTime<Utc>,
+pub struct RoleDto {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub built_in: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
-impl UserReceiveFileDto {
-    pub fn filter_receive_user_file(file_data: &ReceiveFileDetails) -> Self {
-        UserReceiveFileDto {
-            file_id: file_data.file_id.to_string(),
-            file_name: file_data.file_name.to_owned(),
-            sender_email: file_data.sender_email.to_owned(),
-            expiration_date: file_data.expiration_date.unwrap(),
-            created_at: file_data.created_at.unwrap(),
+impl RoleDto {
+    pub fn filter_role(role: &Role) -> Self {
+        RoleDto {
+            name: role.name.to_owned(),
+            description: role.description.to_owned(),
+            permissions: role.permissions.to_owned(),
+            built_in: role.built_in,
+            created_at: role.created_at,
+            updated_at: role.updated_at,
         }
     }
-    pub fn filter_receive_user_files(user: &[ReceiveFileDetails]) -> Vec<UserReceiveFileDto> {
-        user.iter()
-            .map(UserReceiveFileDto::filter_receive_user_file)
-            .collect()
+
+    pub fn filter_roles(roles: &[Role]) -> Vec<Self> {
+        roles.iter().map(RoleDto::filter_role).collect()
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UserReceiveFileListResponseDto {
+pub struct RoleResponseDto {
     pub status: String,
-    pub files: Vec<UserReceiveFileDto>,
-    pub results: i64,
+    pub role: RoleDto,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UserLoginResponseDto {
+pub struct RoleListResponseDto {
     pub status: String,
-    pub token: String,
+    pub roles: Vec<RoleDto>,
+}
+
+/// The refresh token may instead come from the `refresh_token` cookie.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RefreshTokenDto {
+    pub refresh_token: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -197,8 +1199,39 @@ pub struct UserPasswordUpdateDto {
     pub old_password: String,
 }
 
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct ForgotPasswordDto {
+    #[validate(
+        length(min = 1, message = "Email is required"),
+        email(message = "Email is invalid")
+    )]
+    pub email: String,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct ResetPasswordDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+
+    #[validate(
+        length(min = 1, message = "New password is required."),
+        length(min = 6, message = "New password must be at least 6 characters.")
+    )]
+    pub new_password: String,
+
+    #[validate(
+        length(min = 1, message = "New password confirm is required."),
+        length(
+            min = 6,
+            message = "New password confirm must be at least 6 characters."
+        ),
+        must_match(other = "new_password", message = "passwords do not match")
+    )]
+    pub new_password_confirm: String,
+}
+
 #[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
-pub struct searchQueryByEmailDto {
+pub struct SearchQueryByEmailDto {
     #[validate(length(min = 1, message = "Query is required"))]
     pub query: String,
 }
@@ -237,23 +1270,55 @@ pub struct FileUploadDtos {
 
     #[validate(custom = "validate_expiration_date")]
     pub expiration_date: String,
+
+    /// Optional RFC3339 time before which the recipient cannot download the file.
+    #[validate(custom = "validate_available_from")]
+    pub available_from: String,
+}
+
+impl FileUploadDtos {
+    pub fn available_from(&self) -> Option<DateTime<Utc>> {
+        if self.available_from.is_empty() {
+            return None;
+        }
+
+        DateTime::parse_from_rfc3339(&self.available_from)
+            .ok()
+            .map(|available_from| available_from.with_timezone(&Utc))
+    }
 }
 
+fn validate_available_from(available_from: &str) -> Result<(), ValidationError> {
+    if available_from.is_empty() {
+        return Ok(());
+    }
+
+    DateTime::parse_from_rfc3339(available_from).map_err(|_| {
+        let mut error = ValidationError::new("invalid_date_format");
+        error.message = Some("Invalid available from format. Use RFC3339 format.".into());
+        error
+    })?;
+
+    Ok(())
+}
+
+/// Accepts an RFC3339 date, a duration such as `PT24H` or `7d`, or nothing for the
+/// server default. Whether the result fits the expiry policy is checked when the
+/// share is saved, since the policy depends on the user's role.
 fn validate_expiration_date(expiration_date: &str) -> Result<(), ValidationError> {
-    if expiration_date.is_empty() {
-        let mut error = ValidationError::new("expiration_date_required");
-        error.message = Some("Expiration date os required.".into());
-        return Err(error);
+    if expiration_date.is_empty() || parse_duration(expiration_date).is_some() {
+        return Ok(());
     }
 
     let parsed_data = DateTime::parse_from_rfc3339(expiration_date).map_err(|_| {
         let mut error = ValidationError::new("invalid_date_format");
-        error.message = Some("Invalid date format. Use RFC3339 format.".into());
+        error.message = Some(
+            "Invalid expiration. Use an RFC3339 date or a duration such as PT24H or 7d.".into(),
+        );
         error
     })?;
-    let now = Utc::now();
 
-    if parsed_data <= now {
+    if parsed_data <= Utc::now() {
         let mut error = ValidationError::new("expiration_date_future");
         error.message = Some("Expiration date must be in the future.".into());
         return Err(error);
@@ -262,6 +1327,21 @@ fn validate_expiration_date(expiration_date: &str) -> Result<(), ValidationError
     Ok(())
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileUploadResponseDto {
+    pub status: &'static str,
+    pub message: String,
+    pub share_id: String,
+    pub expiration_date: DateTime<Utc>,
+    pub available_from: Option<DateTime<Utc>>,
+}
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RestoreShareDto {
+    #[validate(custom = "validate_expiration_date")]
+    pub expiration_date: String,
+}
+
 #[derive(Validate, Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RetriveFileDto {
     #[validate(length(min = 1, message = "Shared id is required"))]
@@ -272,3 +1352,291 @@ pub struct RetriveFileDto {
     )]
     pub password: String,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotificationPreferencesDto {
+    pub file_received: bool,
+    pub file_downloaded: bool,
+    pub share_expiring: bool,
+    pub share_revoked: bool,
+    pub share_declined: bool,
+}
+
+impl NotificationPreferencesDto {
+    pub fn filter_preferences(preferences: Option<&NotificationPreferences>) -> Self {
+        match preferences {
+            Some(preferences) => NotificationPreferencesDto {
+                file_received: preferences.file_received,
+                file_downloaded: preferences.file_downloaded,
+                share_expiring: preferences.share_expiring,
+                share_revoked: preferences.share_revoked,
+                share_declined: preferences.share_declined,
+            },
+            None => NotificationPreferencesDto {
+                file_received: true,
+                file_downloaded: true,
+                share_expiring: true,
+                share_revoked: true,
+                share_declined: true,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotificationPreferencesResponseDto {
+    pub status: String,
+    pub preferences: NotificationPreferencesDto,
+}
+
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferencesUpdateDto {
+    pub file_received: Option<bool>,
+    pub file_downloaded: Option<bool>,
+    pub share_expiring: Option<bool>,
+    pub share_revoked: Option<bool>,
+    pub share_declined: Option<bool>,
+}
+
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CreateWebhookDto {
+    #[validate(url(message = "Webhook url is invalid"))]
+    pub url: String,
+
+    #[validate(custom = "validate_webhook_events")]
+    pub events: Vec<String>,
+}
+
+fn validate_webhook_events(events: &[String]) -> Result<(), ValidationError> {
+    if events.is_empty() {
+        let mut error = ValidationError::new("webhook_events_required");
+        error.message = Some("At least one event is required.".into());
+        return Err(error);
+    }
+
+    if let Some(unknown) = events
+        .iter()
+        .find(|event| !WEBHOOK_EVENTS.contains(&event.as_str()))
+    {
+        let mut error = ValidationError::new("webhook_event_unknown");
+        error.message = Some(
+            format!(
+                "Unknown event {}. Supported events: {}.",
+                unknown,
+                WEBHOOK_EVENTS.join(", ")
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookDto {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookDto {
+    pub fn filter_webhook(endpoint: &WebhookEndpoint) -> Self {
+        WebhookDto {
+            id: endpoint.id.to_string(),
+            url: endpoint.url.to_owned(),
+            events: endpoint.events.to_owned(),
+            active: endpoint.active,
+            created_at: endpoint.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+    pub fn filter_webhooks(endpoints: &[WebhookEndpoint]) -> Vec<WebhookDto> {
+        endpoints.iter().map(WebhookDto::filter_webhook).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookCreatedResponseDto {
+    pub status: String,
+    pub webhook: WebhookDto,
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookListResponseDto {
+    pub status: String,
+    pub webhooks: Vec<WebhookDto>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookAttemptDto {
+    pub id: String,
+    pub delivery_id: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub delivery_status: String,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookAttemptDto {
+    pub fn filter_attempt(attempt: &WebhookDeliveryAttempt) -> Self {
+        WebhookAttemptDto {
+            id: attempt.id.to_string(),
+            delivery_id: attempt.delivery_id.to_string(),
+            event_id: attempt.event_id.to_string(),
+            event_type: attempt.event_type.to_owned(),
+            delivery_status: attempt.delivery_status.to_owned(),
+            response_status: attempt.response_status,
+            error: attempt.error.to_owned(),
+            duration_ms: attempt.duration_ms,
+            created_at: attempt.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+    pub fn filter_attempts(attempts: &[WebhookDeliveryAttempt]) -> Vec<WebhookAttemptDto> {
+        attempts
+            .iter()
+            .map(WebhookAttemptDto::filter_attempt)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookAttemptListResponseDto {
+    pub status: String,
+    pub attempts: Vec<WebhookAttemptDto>,
+    pub results: i64,
+}
+
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CreateFileRequestDto {
+    #[validate(length(min = 1, max = 255, message = "Title is required"))]
+    pub title: String,
+
+    #[validate(length(max = 2000, message = "Description is too long"))]
+    pub description: Option<String>,
+
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub password: Option<String>,
+
+    #[validate(custom = "validate_deadline")]
+    pub deadline: String,
+}
+
+fn validate_deadline(deadline: &str) -> Result<(), ValidationError> {
+    let parsed_data = DateTime::parse_from_rfc3339(deadline).map_err(|_| {
+        let mut error = ValidationError::new("invalid_date_format");
+        error.message = Some("Invalid deadline format. Use RFC3339 format.".into());
+        error
+    })?;
+
+    if parsed_data <= Utc::now() {
+        let mut error = ValidationError::new("deadline_future");
+        error.message = Some("Deadline must be in the future.".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileRequestDto {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub deadline: DateTime<Utc>,
+    pub password_required: bool,
+    pub upload_count: i32,
+    pub open: bool,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FileRequestDto {
+    pub fn filter_file_request(file_request: &FileRequest) -> Self {
+        FileRequestDto {
+            id: file_request.id.to_string(),
+            title: file_request.title.to_owned(),
+            description: file_request.description.to_owned(),
+            deadline: file_request.deadline,
+            password_required: file_request.password.is_some(),
+            upload_count: file_request.upload_count,
+            open: file_request.is_open(),
+            closed_at: file_request.closed_at,
+            created_at: file_request.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+    pub fn filter_file_requests(file_requests: &[FileRequest]) -> Vec<FileRequestDto> {
+        file_requests
+            .iter()
+            .map(FileRequestDto::filter_file_request)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileRequestResponseDto {
+    pub status: String,
+    pub file_request: FileRequestDto,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileRequestListResponseDto {
+    pub status: String,
+    pub file_requests: Vec<FileRequestDto>,
+    pub results: i64,
+}
+
+/// What an uploader sees before sending files: enough to recognise the request,
+/// nothing about what has already been uploaded.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublicFileRequestDto {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub deadline: DateTime<Utc>,
+    pub password_required: bool,
+    pub requester_name: String,
+    pub requester_email: String,
+}
+
+impl PublicFileRequestDto {
+    pub fn filter_file_request(file_request: &FileRequest, requester: &User) -> Self {
+        PublicFileRequestDto {
+            id: file_request.id.to_string(),
+            title: file_request.title.to_owned(),
+            description: file_request.description.to_owned(),
+            deadline: file_request.deadline,
+            password_required: file_request.password.is_some(),
+            requester_name: requester.username.to_owned(),
+            requester_email: requester.email.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublicFileRequestResponseDto {
+    pub status: String,
+    pub file_request: PublicFileRequestDto,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, Clone, Default)]
+pub struct RequestedFileUploadDto {
+    #[validate(email(message = "Invalid email format"))]
+    pub uploader_email: String,
+
+    #[validate(
+        length(min = 1, message = "New password is required."),
+        length(min = 6, message = "New password must be at least 6 characters")
+    )]
+    pub password: String,
+
+    #[validate(custom = "validate_expiration_date")]
+    pub expiration_date: String,
+
+    pub request_password: Option<String>,
+}
@@ -1,9 +1,9 @@
 use std::fmt;
 
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Error, Json,
+    Json,
 };
 use serde::{Deserialize, Serialize};
 
@@ -29,11 +29,18 @@ pub enum ErrorMessage {
     EmailExist,
     UserNoLongerExist,
     TokenNotProvided,
+    SessionRevoked,
+    InvalidRefreshToken,
+    InvalidTwoFactorCode,
+    TooManyLoginAttempts,
+    SessionRequired,
+    MissingScope(&'static str),
+    MissingPermission(&'static str),
 }
 
-impl ToString for ErrorMessage {
-    fn to_string(&self) -> String {
-        self.to_str().to_owned()
+impl fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
     }
 }
 
@@ -53,6 +60,25 @@ impl ErrorMessage {
             ErrorMessage::TokenNotProvided => {
                 "You are not logged in, please provide a token".to_string()
             }
+            ErrorMessage::SessionRevoked => {
+                "Your session has ended, please log in again".to_string()
+            }
+            ErrorMessage::InvalidRefreshToken => {
+                "Refresh token is invalid or expired, please log in again".to_string()
+            }
+            ErrorMessage::InvalidTwoFactorCode => "The authentication code is invalid".to_string(),
+            ErrorMessage::TooManyLoginAttempts => {
+                "Too many failed login attempts, please try again later".to_string()
+            }
+            ErrorMessage::SessionRequired => {
+                "Access tokens cannot be used for this action, please log in".to_string()
+            }
+            ErrorMessage::MissingScope(scope) => {
+                format!("This access token does not have the {} scope", scope)
+            }
+            ErrorMessage::MissingPermission(permission) => {
+                format!("Your role does not have the {} permission", permission)
+            }
         }
     }
 }
@@ -61,6 +87,7 @@ impl ErrorMessage {
 pub struct HttpError {
     pub message: String,
     pub status: StatusCode,
+    pub retry_after: Option<u64>,
 }
 
 impl HttpError {
@@ -68,30 +95,57 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status,
+            retry_after: None,
         }
     }
     pub fn server_error(message: impl Into<String>) -> Self {
         HttpError {
             message: message.into(),
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            retry_after: None,
         }
     }
     pub fn bad_request(message: impl Into<String>) -> Self {
         HttpError {
             message: message.into(),
             status: StatusCode::BAD_REQUEST,
+            retry_after: None,
         }
     }
     pub fn unique_constraint_violation(message: impl Into<String>) -> Self {
         HttpError {
             message: message.into(),
             status: StatusCode::CONFLICT,
+            retry_after: None,
         }
     }
     pub fn unauthorized(message: impl Into<String>) -> Self {
         HttpError {
             message: message.into(),
             status: StatusCode::UNAUTHORIZED,
+            retry_after: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        HttpError {
+            message: message.into(),
+            status: StatusCode::FORBIDDEN,
+            retry_after: None,
+        }
+    }
+    pub fn too_many_requests(message: impl Into<String>, retry_after: u64) -> Self {
+        HttpError {
+            message: message.into(),
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(retry_after),
+        }
+    }
+    pub fn locked(message: impl Into<String>) -> Self {
+        HttpError {
+            message: message.into(),
+            status: StatusCode::LOCKED,
+            retry_after: None,
         }
     }
 
@@ -100,7 +154,13 @@ impl HttpError {
             status: "fail".to_string(),
             message: self.message.clone(),
         });
-        (self.status, json_response).into_response()
+        let mut response = (self.status, json_response).into_response();
+        if let Some(retry_after) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use validator::Validate;
+
+use crate::{
+    db::AccessTokenExt,
+    dtos::{
+        AccessTokenCreatedResponseDto, AccessTokenDto, AccessTokenListResponseDto,
+        CreateAccessTokenDto, Response,
+    },
+    error::HttpError,
+    middleware::JWTAuthMiddleware,
+    utils::token,
+    AppState,
+};
+
+const DEFAULT_ACCESS_TOKEN_DAYS: i64 = 90;
+
+pub fn access_token_handler() -> Router {
+    Router::new()
+        .route("/", get(get_access_tokens).post(create_access_token))
+        .route("/:token_id", delete(delete_access_token))
+}
+
+pub async fn create_access_token(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<CreateAccessTokenDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let mut scopes = body.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+
+    let expires_at =
+        Utc::now() + Duration::days(body.expires_in_days.unwrap_or(DEFAULT_ACCESS_TOKEN_DAYS));
+
+    let token = token::generate_access_token();
+
+    let access_token = app_state
+        .db_client
+        .save_access_token(
+            user.user.id,
+            body.name.trim().to_string(),
+            token::hash_access_token(&token),
+            scopes,
+            expires_at,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = AccessTokenCreatedResponseDto {
+        status: "success".to_string(),
+        access_token: AccessTokenDto::filter_access_token(&access_token),
+        token,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn get_access_tokens(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let access_tokens = app_state
+        .db_client
+        .get_access_tokens(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = AccessTokenListResponseDto {
+        status: "success".to_string(),
+        access_tokens: AccessTokenDto::filter_access_tokens(&access_tokens),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn delete_access_token(
+    Path(token_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let deleted = app_state
+        .db_client
+        .delete_access_token(token_id, user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !deleted {
+        return Err(HttpError::new(
+            "The requested access token does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let response = Response {
+        status: "success",
+        message: "Access token revoked".to_string(),
+    };
+
+    Ok(Json(response))
+}
@@ -0,0 +1,47 @@
+use axum::{middleware, routing::put, Router};
+
+use crate::{
+    handler::{
+        auth::unlock_login,
+        role::{role_handler, set_user_role},
+        stats::{stats_handler, StatsScope},
+        two_factor::set_two_factor_requirement,
+        webhook::{webhook_handler, WebhookScope},
+    },
+    middleware::require_permission,
+    models::Permission,
+};
+
+pub fn admin_handler() -> Router {
+    let users_manage = middleware::from_fn_with_state(Permission::UsersManage, require_permission);
+    let roles_manage = middleware::from_fn_with_state(Permission::RolesManage, require_permission);
+
+    Router::new()
+        .nest(
+            "/webhooks",
+            webhook_handler(WebhookScope::Organization).layer(middleware::from_fn_with_state(
+                Permission::WebhooksManage,
+                require_permission,
+            )),
+        )
+        .nest(
+            "/stats",
+            stats_handler(StatsScope::Organization).layer(middleware::from_fn_with_state(
+                Permission::StatsRead,
+                require_permission,
+            )),
+        )
+        .nest("/roles", role_handler().layer(roles_manage.clone()))
+        .route(
+            "/users/:user_id/two-factor",
+            put(set_two_factor_requirement).layer(users_manage.clone()),
+        )
+        .route(
+            "/users/:user_id/unlock",
+            put(unlock_login).layer(users_manage),
+        )
+        .route(
+            "/users/:user_id/role",
+            put(set_user_role).layer(roles_manage),
+        )
+}
@@ -1,26 +1,60 @@
 use std::sync::Arc;
 
 use axum::{
+    extract::Path,
     http::{header, HeaderMap, StatusCode},
+    middleware,
     response::IntoResponse,
     routing::post,
     Extension, Json, Router,
 };
-use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use chrono::{Duration, Utc};
 use validator::Validate;
 
 use crate::{
-    db::UserExt,
-    dtos::{LoginUserDto, RegisterUserDto, Response, UserLoginResponseDto},
+    db::{LoginSecurityExt, PasswordResetExt, SessionExt, TwoFactorExt, UserExt},
+    dtos::{
+        ForgotPasswordDto, LoginUserDto, RefreshTokenDto, RegisterUserDto, ResendVerificationDto,
+        ResetPasswordDto, Response, TwoFactorChallengeResponseDto, TwoFactorLoginDto,
+        UserLoginResponseDto, VerifyEmailDto,
+    },
     error::{ErrorMessage, HttpError},
-    utils::{keys::generate_key, password, token},
+    handler::{oidc::oidc_handler, two_factor::verify_second_factor},
+    mail::{notify::send_mail, templates},
+    middleware::{auth, JWTAuthMiddleware},
+    models::{
+        LoginAttemptClaim, LoginOutcome, RefreshRotation, Role, Session, SessionRevokeReason, User,
+    },
+    ratelimit::{layer::RateLimitLayer, RateLimitGroup},
+    utils::{client::ClientInfo, keys::generate_key, password, token},
     AppState,
 };
 
+const REFRESH_COOKIE: &str = "refresh_token";
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+/// How long the user has to enter their 2FA code after the password step.
+const TWO_FACTOR_CHALLENGE_MINUTES: i64 = 5;
+/// Codes one challenge takes before the password has to be entered again.
+const TWO_FACTOR_CHALLENGE_ATTEMPTS: i32 = 3;
+
 pub fn auth_handler() -> Router {
+    let rate_limit = RateLimitLayer::new(RateLimitGroup::Auth);
+
     Router::new()
-        .route("/register", post(register))
-        .route("/login", post(login))
+        .route("/register", post(register).layer(rate_limit))
+        .route("/login", post(login).layer(rate_limit))
+        .route("/login/2fa", post(login_two_factor).layer(rate_limit))
+        .route("/refresh", post(refresh))
+        .route("/verify-email", post(verify_email))
+        .route(
+            "/verify-email/resend",
+            post(resend_verification_email).layer(rate_limit),
+        )
+        .route("/forgot-password", post(forgot_password).layer(rate_limit))
+        .route("/reset-password", post(reset_password).layer(rate_limit))
+        .route("/logout", post(logout).layer(middleware::from_fn(auth)))
+        .nest("/oidc", oidc_handler())
 }
 
 pub async fn register(
@@ -40,12 +74,15 @@ pub async fn register(
 
     match result {
         Ok(user) => {
+            send_verification_email(&app_state, &user)?;
+
             let _key_result = generate_key(app_state, user).await?;
 
             Ok((
                 StatusCode::CREATED,
                 Json(Response {
-                    message: "Registration successful!".to_string(),
+                    message: "Registration successful! Check your email to verify your address"
+                        .to_string(),
                     status: "success",
                 }),
             ))
@@ -63,8 +100,180 @@ pub async fn register(
     }
 }
 
+/// Mails a link that proves the user owns their address. Until they follow it,
+/// nobody can find them in search or send them files.
+fn send_verification_email(app_state: &AppState, user: &User) -> Result<(), HttpError> {
+    let verification_token = token::create_scoped_token(
+        &user.id.to_string(),
+        token::VERIFY_EMAIL_SCOPE,
+        app_state.env.jwt_secret.as_bytes(),
+        app_state.env.email_verification_hours * 60,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    send_mail(
+        app_state,
+        &user.email,
+        templates::verify_email(
+            &user.username,
+            &verification_token,
+            app_state.env.email_verification_hours,
+            &app_state.env.app_url,
+        ),
+    );
+
+    Ok(())
+}
+
+pub async fn verify_email(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<VerifyEmailDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let claims = token::decode_scoped_token(
+        body.token,
+        token::VERIFY_EMAIL_SCOPE,
+        app_state.env.jwt_secret.as_bytes(),
+    )
+    .map_err(|_| HttpError::bad_request("The verification link is invalid or has expired"))?;
+
+    let user_id = uuid::Uuid::parse_str(&claims.sub)
+        .map_err(|_| HttpError::bad_request("The verification link is invalid or has expired"))?;
+
+    let verified = app_state
+        .db_client
+        .verify_user_email(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let message = if verified {
+        "Email verified"
+    } else {
+        "Email is already verified"
+    };
+
+    Ok(Json(Response {
+        status: "success",
+        message: message.to_string(),
+    }))
+}
+
+/// Answers the same whether or not the address belongs to an unverified account, so
+/// it cannot be used to find out who is registered.
+pub async fn resend_verification_email(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<ResendVerificationDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = app_state
+        .db_client
+        .get_user(None, None, Some(&body.email))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if let Some(user) = user.filter(|user| !user.is_verified()) {
+        send_verification_email(&app_state, &user)?;
+    }
+
+    Ok(Json(Response {
+        status: "success",
+        message: "If this address has an unverified account, a new verification link is on its way"
+            .to_string(),
+    }))
+}
+
+/// Mails a single-use reset link. Answers the same whether or not the address is
+/// registered.
+pub async fn forgot_password(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<ForgotPasswordDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = app_state
+        .db_client
+        .get_user(None, None, Some(&body.email))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if let Some(user) = user {
+        let reset_token = token::generate_password_reset_token();
+        let expires_at = Utc::now() + Duration::minutes(app_state.env.password_reset_minutes);
+
+        app_state
+            .db_client
+            .save_password_reset_token(
+                user.id,
+                token::hash_password_reset_token(&reset_token),
+                expires_at,
+            )
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        send_mail(
+            &app_state,
+            &user.email,
+            templates::password_reset(
+                &user.username,
+                &reset_token,
+                app_state.env.password_reset_minutes,
+                &app_state.env.app_url,
+            ),
+        );
+    }
+
+    Ok(Json(Response {
+        status: "success",
+        message: "If this address has an account, a password reset link is on its way".to_string(),
+    }))
+}
+
+/// Sets a new password from a reset link and signs the user out everywhere.
+///
+/// Private keys are generated and kept by the server and are not derived from or
+/// wrapped with the password, so there is nothing to rewrap: every file shared with
+/// the user stays readable after a reset.
+pub async fn reset_password(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<ResetPasswordDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let hash_password =
+        password::hash(&body.new_password).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let user = app_state
+        .db_client
+        .reset_password(
+            &token::hash_password_reset_token(&body.token),
+            hash_password,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request("The reset link is invalid or has expired"))?;
+
+    send_mail(
+        &app_state,
+        &user.email,
+        templates::password_changed(&user.username, &app_state.env.app_url),
+    );
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Password reset. You have been signed out everywhere and your access tokens were revoked; files shared with you are still readable"
+            .to_string(),
+    }))
+}
+
 pub async fn login(
     Extension(app_state): Extension<Arc<AppState>>,
+    client: ClientInfo,
     Json(body): Json<LoginUserDto>,
 ) -> Result<impl IntoResponse, HttpError> {
     body.validate()
@@ -76,43 +285,532 @@ pub async fn login(
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let user = result.ok_or(HttpError::bad_request(
-        ErrorMessage::InvalidCredentials.to_string(),
-    ))?;
+    let email = login_email(&body.email);
+    let locks = claim_login_attempt(&app_state, result.as_ref(), &email, &client).await?;
+
+    let user = match result {
+        Some(user) => user,
+        None => {
+            record_login_failure(
+                &app_state,
+                None,
+                &email,
+                LoginOutcome::UnknownEmail,
+                &client,
+                locks,
+            )
+            .await;
+            return Err(HttpError::bad_request(
+                ErrorMessage::InvalidCredentials.to_string(),
+            ));
+        }
+    };
 
     let password_matched = password::compare(&body.password, &user.password)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    if password_matched {
-        let token = token::create_token(
-            &user.id.to_string(),
-            &app_state.env.jwt_secret.as_bytes(),
-            app_state.env.jwt_maxage,
+    if !password_matched {
+        record_login_failure(
+            &app_state,
+            Some(&user),
+            &email,
+            LoginOutcome::WrongPassword,
+            &client,
+            locks,
+        )
+        .await;
+        return Err(HttpError::bad_request(
+            ErrorMessage::InvalidCredentials.to_string(),
+        ));
+    }
+
+    settle_login_attempt(&app_state, &email, &client).await?;
+
+    complete_login(&app_state, &user, client).await
+}
+
+/// Failures are counted per address as typed, so casing does not buy extra attempts.
+fn login_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Counts the attempt against the client's IP and the email before the password or
+/// code is checked, so parallel guesses cannot all pass a check that only sees
+/// finished ones. Refuses it when either has failed too often. Both answer the same,
+/// and unknown addresses lock like real ones, so the response does not reveal whether
+/// an account exists. True when failing this attempt locks the email. The IP key is
+/// the connecting address unless `TRUST_PROXY_HEADERS` is set, so behind the bundled
+/// frontend without it, LOGIN_IP_MAX_FAILED_ATTEMPTS failures lock out everyone.
+async fn claim_login_attempt(
+    app_state: &AppState,
+    user: Option<&User>,
+    email: &str,
+    client: &ClientInfo,
+) -> Result<bool, HttpError> {
+    if let Some(ip_address) = &client.ip_address {
+        let throttled = app_state
+            .db_client
+            .claim_login_ip_attempt(
+                ip_address,
+                app_state.env.login_ip_max_failed_attempts,
+                app_state.env.login_lockout_minutes,
+            )
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if let Some(retry_after) = throttled {
+            record_login_event(app_state, user, email, LoginOutcome::IpThrottled, client).await;
+
+            return Err(HttpError::too_many_requests(
+                ErrorMessage::TooManyLoginAttempts.to_string(),
+                retry_after,
+            ));
+        }
+    }
+
+    let claim = app_state
+        .db_client
+        .claim_login_attempt(
+            email,
+            app_state.env.login_max_failed_attempts,
+            app_state.env.login_lockout_minutes,
         )
+        .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-        let cookie_duration = time::Duration::minutes(app_state.env.jwt_maxage * 60);
-        let cookie = Cookie::build(("token", token.clone()))
-            .path("/")
-            .max_age(cookie_duration)
+    match claim {
+        LoginAttemptClaim::Claimed { locks } => Ok(locks),
+        LoginAttemptClaim::Locked(retry_after) => {
+            // Guesses at a locked email should not use up the IP's attempts too.
+            if let Some(ip_address) = &client.ip_address {
+                app_state
+                    .db_client
+                    .settle_login_ip_attempt(ip_address)
+                    .await
+                    .map_err(|e| HttpError::server_error(e.to_string()))?;
+            }
+
+            record_login_event(app_state, user, email, LoginOutcome::Locked, client).await;
+
+            Err(HttpError::too_many_requests(
+                ErrorMessage::TooManyLoginAttempts.to_string(),
+                retry_after,
+            ))
+        }
+    }
+}
+
+/// Hands back an attempt claimed by [`claim_login_attempt`] once its password or code
+/// was right.
+async fn settle_login_attempt(
+    app_state: &AppState,
+    email: &str,
+    client: &ClientInfo,
+) -> Result<(), HttpError> {
+    if let Some(ip_address) = &client.ip_address {
+        app_state
+            .db_client
+            .settle_login_ip_attempt(ip_address)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+    }
+
+    app_state
+        .db_client
+        .settle_login_attempt(email, app_state.env.login_max_failed_attempts)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// Records a failed attempt, which [`claim_login_attempt`] already counted. The owner
+/// is told by email when it was the attempt that locked their account.
+async fn record_login_failure(
+    app_state: &AppState,
+    user: Option<&User>,
+    email: &str,
+    outcome: LoginOutcome,
+    client: &ClientInfo,
+    locked: bool,
+) {
+    record_login_event(app_state, user, email, outcome, client).await;
+
+    if let Some(user) = user.filter(|_| locked) {
+        send_mail(
+            app_state,
+            &user.email,
+            templates::account_locked(
+                &user.username,
+                app_state.env.login_lockout_minutes,
+                &app_state.env.app_url,
+            ),
+        );
+    }
+}
+
+/// Gives the admin role to a verified user listed in `ADMIN_EMAILS` when they sign in
+/// and nobody holds `roles:manage` yet, which is how a fresh install gets its first
+/// admin. Once someone can manage roles the list is ignored, so a listed user who is
+/// later demoted stays demoted.
+async fn promote_configured_admin(
+    app_state: &AppState,
+    user: &User,
+    email: &str,
+) -> Result<(), HttpError> {
+    if user.role == Role::ADMIN
+        || !user.is_verified()
+        || !app_state
+            .env
+            .admin_emails
+            .iter()
+            .any(|admin| admin == email)
+    {
+        return Ok(());
+    }
+
+    app_state
+        .db_client
+        .promote_first_admin(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(())
+}
+
+async fn record_login_event(
+    app_state: &AppState,
+    user: Option<&User>,
+    email: &str,
+    outcome: LoginOutcome,
+    client: &ClientInfo,
+) {
+    if let Err(err) = app_state
+        .db_client
+        .save_login_event(
+            user.map(|user| user.id),
+            email,
+            outcome,
+            client.ip_address.clone(),
+            client.user_agent.clone(),
+        )
+        .await
+    {
+        eprintln!("Error recording login event: {:?}", err);
+    }
+}
+
+/// Lifts a lockout on the user's account before it runs out, for admins helping a
+/// user who was locked out by someone else's guesses.
+pub async fn unlock_login(
+    Path(user_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new("The requested user does not exist.", StatusCode::NOT_FOUND)
+        })?;
+
+    app_state
+        .db_client
+        .clear_login_failures(&login_email(&user.email))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(Response {
+        status: "success",
+        message: "The user can log in again".to_string(),
+    }))
+}
+
+/// Finishes a login whose first factor checked out: asks for the 2FA code when the
+/// user has it enabled, otherwise starts the session.
+pub async fn complete_login(
+    app_state: &AppState,
+    user: &User,
+    client: ClientInfo,
+) -> Result<axum::response::Response, HttpError> {
+    let two_factor = app_state
+        .db_client
+        .get_two_factor(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if two_factor.is_some_and(|two_factor| two_factor.is_enabled()) {
+        let challenge_token = token::generate_two_factor_challenge();
+
+        app_state
+            .db_client
+            .save_two_factor_challenge(
+                user.id,
+                token::hash_two_factor_challenge(&challenge_token),
+                Utc::now() + Duration::minutes(TWO_FACTOR_CHALLENGE_MINUTES),
+            )
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        return Ok(Json(TwoFactorChallengeResponseDto {
+            status: "two_factor_required".to_string(),
+            challenge_token,
+        })
+        .into_response());
+    }
+
+    start_session(app_state, user, client).await
+}
+
+/// Second login step for accounts with 2FA: trades the challenge token from `login`
+/// and a TOTP or backup code for a session. A challenge works once and takes at most
+/// [`TWO_FACTOR_CHALLENGE_ATTEMPTS`] codes.
+pub async fn login_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    client: ClientInfo,
+    Json(body): Json<TwoFactorLoginDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let challenge_hash = token::hash_two_factor_challenge(&body.challenge_token);
+
+    let user_id = app_state
+        .db_client
+        .claim_two_factor_challenge(&challenge_hash, TWO_FACTOR_CHALLENGE_ATTEMPTS)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    // Wrong codes count towards the same lockout as wrong passwords, so the challenge
+    // token cannot be used to guess codes freely.
+    let email = login_email(&user.email);
+    let locks = claim_login_attempt(&app_state, Some(&user), &email, &client).await?;
+
+    let two_factor = app_state
+        .db_client
+        .get_two_factor(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|two_factor| two_factor.is_enabled())
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    if !verify_second_factor(&app_state, &two_factor, &body.code).await? {
+        record_login_failure(
+            &app_state,
+            Some(&user),
+            &email,
+            LoginOutcome::InvalidTwoFactorCode,
+            &client,
+            locks,
+        )
+        .await;
+        return Err(HttpError::bad_request(
+            ErrorMessage::InvalidTwoFactorCode.to_string(),
+        ));
+    }
+
+    settle_login_attempt(&app_state, &email, &client).await?;
+
+    app_state
+        .db_client
+        .delete_two_factor_challenge(&challenge_hash)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    start_session(&app_state, &user, client).await
+}
+
+pub async fn refresh(
+    Extension(app_state): Extension<Arc<AppState>>,
+    cookie_jar: CookieJar,
+    body: Option<Json<RefreshTokenDto>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let presented = body
+        .and_then(|Json(body)| body.refresh_token)
+        .or_else(|| {
+            cookie_jar
+                .get(REFRESH_COOKIE)
+                .map(|cookie| cookie.value().to_string())
+        })
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string()))?;
+
+    let refresh_token = token::generate_refresh_token();
+
+    let rotation = app_state
+        .db_client
+        .rotate_refresh_token(
+            &token::hash_refresh_token(&presented),
+            token::hash_refresh_token(&refresh_token),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    match rotation {
+        RefreshRotation::Rotated(session) => session_response(&app_state, &session, refresh_token),
+        RefreshRotation::Reused => {
+            eprintln!("Refresh token reused; session revoked");
+            Err(HttpError::unauthorized(
+                ErrorMessage::SessionRevoked.to_string(),
+            ))
+        }
+        RefreshRotation::Invalid => Err(HttpError::unauthorized(
+            ErrorMessage::InvalidRefreshToken.to_string(),
+        )),
+    }
+}
+
+pub async fn logout(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    app_state
+        .db_client
+        .revoke_session(user.session_id()?, SessionRevokeReason::Logout)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let mut response = Json(Response {
+        status: "success",
+        message: "Logged out".to_string(),
+    })
+    .into_response();
+
+    for (name, path) in [("token", "/"), (REFRESH_COOKIE, REFRESH_COOKIE_PATH)] {
+        let cookie = Cookie::build((name, ""))
+            .path(path)
+            .max_age(time::Duration::ZERO)
             .http_only(true)
             .build();
+        response
+            .headers_mut()
+            .append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    }
 
-        let response = Json(UserLoginResponseDto {
-            status: "success".to_string(),
-            token,
-        });
+    Ok(response)
+}
 
-        let mut headers = HeaderMap::new();
-        headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+/// Signs the user in, warns them by email when the login comes from an IP address or
+/// device they have not used before, and clears their failed attempts.
+async fn start_session(
+    app_state: &AppState,
+    user: &User,
+    client: ClientInfo,
+) -> Result<axum::response::Response, HttpError> {
+    let refresh_token = token::generate_refresh_token();
+    let expires_at = Utc::now() + Duration::days(app_state.env.refresh_token_maxage_days);
 
-        let mut response = response.into_response();
-        response.headers_mut().extend(headers);
+    let known_login = app_state
+        .db_client
+        .get_known_login(
+            user.id,
+            client.ip_address.as_deref(),
+            client.user_agent.as_deref(),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-        Ok(response)
-    } else {
-        Err(HttpError::bad_request(
-            ErrorMessage::InvalidCredentials.to_string(),
-        ))
+    let session = app_state
+        .db_client
+        .save_session(
+            user.id,
+            client.user_agent.clone(),
+            client.ip_address.clone(),
+            expires_at,
+            token::hash_refresh_token(&refresh_token),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let email = login_email(&user.email);
+    record_login_event(
+        app_state,
+        Some(user),
+        &email,
+        LoginOutcome::Success,
+        &client,
+    )
+    .await;
+
+    app_state
+        .db_client
+        .clear_login_failures(&email)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    promote_configured_admin(app_state, user, &email).await?;
+
+    // The first login has nothing to compare against.
+    if known_login.has_previous_login
+        && !(known_login.ip_address_seen && known_login.user_agent_seen)
+    {
+        send_mail(
+            app_state,
+            &user.email,
+            templates::new_login(
+                &user.username,
+                client.ip_address.as_deref(),
+                client.user_agent.as_deref(),
+                &session.created_at.unwrap_or_else(Utc::now),
+                &app_state.env.app_url,
+            ),
+        );
     }
+
+    session_response(app_state, &session, refresh_token)
+}
+
+/// Issues an access token for `session` and hands out its new refresh token, both in
+/// the body and as cookies. The refresh cookie is only sent back to `/api/auth`.
+fn session_response(
+    app_state: &AppState,
+    session: &Session,
+    refresh_token: String,
+) -> Result<axum::response::Response, HttpError> {
+    let token = token::create_token(
+        &session.user_id.to_string(),
+        &session.id.to_string(),
+        app_state.env.jwt_secret.as_bytes(),
+        app_state.env.jwt_maxage,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let cookie_duration = time::Duration::minutes(app_state.env.jwt_maxage * 60);
+    let cookie = Cookie::build(("token", token.clone()))
+        .path("/")
+        .max_age(cookie_duration)
+        .http_only(true)
+        .build();
+
+    let refresh_cookie_duration =
+        time::Duration::seconds((session.expires_at - Utc::now()).num_seconds().max(0));
+    let refresh_cookie = Cookie::build((REFRESH_COOKIE, refresh_token.clone()))
+        .path(REFRESH_COOKIE_PATH)
+        .max_age(refresh_cookie_duration)
+        .http_only(true)
+        .build();
+
+    let response = Json(UserLoginResponseDto {
+        status: "success".to_string(),
+        token,
+        refresh_token,
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    headers.append(
+        header::SET_COOKIE,
+        refresh_cookie.to_string().parse().unwrap(),
+    );
+
+    let mut response = response.into_response();
+    response.headers_mut().extend(headers);
+
+    Ok(response)
 }
@@ -0,0 +1,107 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Extension, Router,
+};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::{
+    db::{AccessTokenExt, SessionExt},
+    error::HttpError,
+    middleware::{Credential, JWTAuthMiddleware},
+    models::AccessTokenScope,
+    AppState,
+};
+
+/// Sent when this connection fell too far behind the broadcast channel and
+/// missed events; clients should reload the inbox from `/api/list/receive`.
+const RESYNC_EVENT: &str = "inbox.resync";
+
+/// How often an open stream checks that its session or token is still active.
+const CREDENTIAL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn events_handler() -> Router {
+    Router::new().route("/", get(inbox_events))
+}
+
+/// The stream ends once the session is revoked or the token deleted or expired, so
+/// a revoked login stops receiving events within [`CREDENTIAL_CHECK_INTERVAL`].
+pub async fn inbox_events(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(forward_inbox_events(app_state, user, tx));
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+async fn forward_inbox_events(
+    app_state: Arc<AppState>,
+    user: JWTAuthMiddleware,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    let user_id = user.user.id;
+    let mut inbox = app_state.inbox.subscribe();
+    let mut credential_check = tokio::time::interval(CREDENTIAL_CHECK_INTERVAL);
+    // The first tick fires immediately; the middleware has only just checked.
+    credential_check.tick().await;
+
+    loop {
+        let event = tokio::select! {
+            message = inbox.recv() => match message {
+                Ok(notification) if notification.recipient_id == user_id => {
+                    match Event::default()
+                        .event(&notification.event.event_type)
+                        .id(notification.event.id.to_string())
+                        .json_data(&notification.event)
+                    {
+                        Ok(event) => event,
+                        Err(_) => continue,
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) => Event::default().event(RESYNC_EVENT).data("{}"),
+                Err(RecvError::Closed) => return,
+            },
+            _ = credential_check.tick() => {
+                if credential_active(&app_state, &user).await {
+                    continue;
+                }
+                return;
+            }
+            _ = tx.closed() => return,
+        };
+
+        if tx.send(Ok(event)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Database errors keep the stream open; only a credential known to be gone ends it.
+async fn credential_active(app_state: &AppState, user: &JWTAuthMiddleware) -> bool {
+    let result = match &user.credential {
+        Credential::Session(session_id) => app_state
+            .db_client
+            .get_active_session(*session_id, user.user.id)
+            .await
+            .map(|session| session.is_some()),
+        Credential::AccessToken { token_id, .. } => {
+            app_state.db_client.is_access_token_active(*token_id).await
+        }
+    };
+
+    match result {
+        Ok(active) => active,
+        Err(err) => {
+            eprintln!("Error checking event stream credential: {:?}", err);
+            true
+        }
+    }
+}
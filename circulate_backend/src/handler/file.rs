@@ -2,33 +2,57 @@ use std::{fs, path::PathBuf, sync::Arc};
 
 use axum::{
     body::Body,
-    extract::Multipart,
+    extract::{Multipart, Path, Query},
     http::{Response, StatusCode},
     response::IntoResponse,
-    routing::post,
+    routing::{get, post, put},
     Extension, Json, Router,
 };
-use base64::{engine::general_purpose::STANDARD, Engine};
-use chrono::{DateTime, Utc};
-use rsa::{
-    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
-    RsaPrivateKey, RsaPublicKey,
-};
+use chrono::Utc;
+use rsa::{pkcs1::DecodeRsaPrivateKey, RsaPrivateKey};
 use validator::Validate;
 
 use crate::{
-    db::UserExt,
-    dtos::{FileUploadDtos, Response as ResponseDto, RetriveFileDto},
+    db::{TwoFactorExt, UserExt},
+    dtos::{
+        FileUploadDtos, FileUploadResponseDto, RecipientStateUpdateDto, RequestQueryDto,
+        Response as ResponseDto, RestoreShareDto, RetriveFileDto, ShareAccessLogDto,
+        ShareAccessLogListResponseDto, ShareDetailDto, ShareDetailResponseDto,
+    },
     error::HttpError,
+    mail::{
+        notify::{notify_user, Notification},
+        templates,
+    },
     middleware::JWTAuthMiddleware,
-    utils::{decrypt::decrypt_file, encrypt::encrypt_file, password},
+    models::{AccessTokenScope, RecipientState, ShareAccessOutcome, ShareAttemptClaim},
+    ratelimit::{layer::RateLimitLayer, RateLimitGroup},
+    utils::{
+        client::ClientInfo,
+        decrypt::decrypt_file,
+        encrypt::{content_digest, encrypt_file, recipient_public_key},
+        password,
+    },
+    webhook::{emit_share_event_by_id, WebhookEvent},
     AppState,
 };
 
+const SHARE_LOCKED_MESSAGE: &str =
+    "This shared file is locked after too many failed password attempts. Ask the sender to unlock it.";
+
 pub fn file_handle() -> Router {
     Router::new()
         .route("/upload", post(upload_file))
-        .route("/retrive", post(retrive_file))
+        .route(
+            "/retrive",
+            post(retrive_file).layer(RateLimitLayer::new(RateLimitGroup::Retrieve)),
+        )
+        .route("/:share_id", get(get_share).delete(revoke_share))
+        .route("/:share_id/access-log", get(get_share_access_log))
+        .route("/:share_id/unlock", put(unlock_share))
+        .route("/:share_id/restore", put(restore_share))
+        .route("/:share_id/state", put(update_share_state))
+        .route("/:share_id/decline", post(decline_share))
 }
 
 pub async fn upload_file(
@@ -36,6 +60,8 @@ pub async fn upload_file(
     Extension(user): Extension<JWTAuthMiddleware>,
     mut multipart: Multipart,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
     let mut file_data = Vec::new();
     let mut file_name = String::new();
     let mut file_size: i64 = 0;
@@ -43,6 +69,7 @@ pub async fn upload_file(
         recipient_email: String::new(),
         password: String::new(),
         expiration_date: String::new(),
+        available_from: String::new(),
     };
 
     while let Some(field) = multipart.next_field().await.unwrap() {
@@ -63,6 +90,9 @@ pub async fn upload_file(
             "expiration_date" => {
                 form_data.expiration_date = field.text().await.unwrap();
             }
+            "available_from" => {
+                form_data.available_from = field.text().await.unwrap();
+            }
             _ => {}
         }
     }
@@ -71,6 +101,19 @@ pub async fn upload_file(
         .validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    let expiration_date = app_state
+        .env
+        .expiry_policy(&user.user.role)
+        .resolve(&form_data.expiration_date, Utc::now())
+        .map_err(HttpError::bad_request)?;
+
+    let available_from = form_data.available_from();
+    if available_from.is_some_and(|available_from| available_from >= expiration_date) {
+        return Err(HttpError::bad_request(
+            "Available from must be before the expiration date.",
+        ));
+    }
+
     let recipient_result = app_state
         .db_client
         .get_user(None, None, Some(&form_data.recipient_email))
@@ -80,20 +123,15 @@ pub async fn upload_file(
     let recipient_user =
         recipient_result.ok_or(HttpError::bad_request("Recipient user not found"))?;
 
-    let public_key_str = match &recipient_user.public_key {
-        Some(key) => key,
-        None => return Err(HttpError::bad_request("Recipient has no public key")),
-    };
-
-    let public_key_bytes = STANDARD
-        .decode(public_key_str)
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+    if !recipient_user.is_verified() {
+        return Err(HttpError::bad_request(
+            "Recipient has not verified their email address yet",
+        ));
+    }
 
-    let public_key =
-        String::from_utf8(public_key_bytes).map_err(|e| HttpError::server_error(e.to_string()))?;
+    let public_key_pem = recipient_public_key(&recipient_user)?;
 
-    let public_key_pem = RsaPublicKey::from_pkcs1_pem(&public_key)
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+    let sha256 = content_digest(&file_data);
 
     let (encrypted_aes_key, encrypted_data, iv) = encrypt_file(file_data, &public_key_pem).await?;
 
@@ -102,21 +140,19 @@ pub async fn upload_file(
     let hash_password =
         password::hash(&form_data.password).map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let expiration_date = DateTime::parse_from_rfc3339(&form_data.expiration_date)
-        .map_err(|e| HttpError::server_error(e.to_string()))?
-        .with_timezone(&Utc);
-
     let recipient_user_id = uuid::Uuid::parse_str(&recipient_user.id.to_string()).unwrap();
 
-    app_state
+    let share_id = app_state
         .db_client
         .save_encrypted_file(
-            user_id.clone(),
-            file_name,
+            Some(user_id),
+            file_name.clone(),
             file_size,
+            sha256,
             recipient_user_id,
             hash_password,
             expiration_date,
+            available_from,
             encrypted_aes_key,
             encrypted_data,
             iv,
@@ -124,46 +160,245 @@ pub async fn upload_file(
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let response = ResponseDto {
-        message: "File uploaded and encrypted successfully".to_string(),
+    notify_user(
+        &app_state,
+        recipient_user.id,
+        &recipient_user.email,
+        Notification::FileReceived,
+        templates::file_received(
+            &recipient_user.username,
+            &user.user.email,
+            &file_name,
+            &expiration_date,
+            &app_state.env.app_url,
+        ),
+    )
+    .await;
+
+    emit_share_event_by_id(&app_state, WebhookEvent::Uploaded, share_id).await;
+
+    let response = FileUploadResponseDto {
         status: "success",
+        message: "File uploaded and encrypted successfully".to_string(),
+        share_id: share_id.to_string(),
+        expiration_date,
+        available_from,
     };
 
     Ok(Json(response))
 }
 
+async fn record_share_access(
+    app_state: &AppState,
+    shared_link_id: Option<uuid::Uuid>,
+    requested_shared_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    outcome: ShareAccessOutcome,
+    client: &ClientInfo,
+) {
+    if let Err(err) = app_state
+        .db_client
+        .save_share_access_log(
+            shared_link_id,
+            requested_shared_id,
+            user_id,
+            outcome,
+            client.ip_address.clone(),
+            client.user_agent.clone(),
+        )
+        .await
+    {
+        eprintln!("Error recording share access: {:?}", err);
+    }
+}
+
 pub async fn retrive_file(
     Extension(app_state): Extension<Arc<AppState>>,
     Extension(user): Extension<JWTAuthMiddleware>,
+    client: ClientInfo,
     Json(body): Json<RetriveFileDto>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
     let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
     let shared_id = uuid::Uuid::parse_str(&body.shared_id.to_string()).unwrap();
 
+    let two_factor = app_state
+        .db_client
+        .get_two_factor(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if two_factor.is_some_and(|two_factor| two_factor.required && !two_factor.is_enabled()) {
+        return Err(HttpError::forbidden(
+            "Two-factor authentication is required to retrieve files. Enable it in your account settings.",
+        ));
+    }
+
     let shared_result = app_state
         .db_client
-        .get_shared(shared_id.clone(), user_id.clone())
+        .get_shared(shared_id, user_id)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let shared_data = shared_result.ok_or_else(|| {
-        HttpError::bad_request(
-            "The requested shared link either does not exist or has expired.".to_string(),
+    let shared_data = match shared_result {
+        Some(shared_data) => shared_data,
+        None => {
+            // Tell an expired share apart from an unknown one for the sender's log,
+            // without revealing the difference to the caller.
+            let existing = app_state
+                .db_client
+                .get_shared_by_id(shared_id)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?
+                .filter(|link| link.recipient_user_id == Some(user_id));
+
+            let now = Utc::now();
+            let locked_until = existing.as_ref().and_then(|link| {
+                link.available_from.filter(|available_from| {
+                    link.revoked_at.is_none()
+                        && *available_from > now
+                        && link
+                            .expiration_date
+                            .is_some_and(|expiration| expiration > now)
+                })
+            });
+
+            let (shared_link_id, outcome) = match existing {
+                Some(link) if link.revoked_at.is_some() => {
+                    (Some(link.id), ShareAccessOutcome::Revoked)
+                }
+                Some(link) if locked_until.is_some() => {
+                    (Some(link.id), ShareAccessOutcome::NotAvailableYet)
+                }
+                Some(link) => (Some(link.id), ShareAccessOutcome::Expired),
+                None => (None, ShareAccessOutcome::NotFound),
+            };
+
+            record_share_access(
+                &app_state,
+                shared_link_id,
+                shared_id,
+                user_id,
+                outcome,
+                &client,
+            )
+            .await;
+
+            // The recipient already sees the embargo in their receive list.
+            if let Some(available_from) = locked_until {
+                return Err(HttpError::forbidden(format!(
+                    "This shared file is locked until {}.",
+                    available_from.to_rfc3339()
+                )));
+            }
+
+            return Err(HttpError::bad_request(
+                "The requested shared link either does not exist or has expired.".to_string(),
+            ));
+        }
+    };
+
+    if shared_data.locked_at.is_some() {
+        record_share_access(
+            &app_state,
+            Some(shared_data.id),
+            shared_id,
+            user_id,
+            ShareAccessOutcome::Locked,
+            &client,
         )
-    })?;
+        .await;
+
+        return Err(HttpError::locked(SHARE_LOCKED_MESSAGE));
+    }
+
+    let claim = app_state
+        .db_client
+        .claim_share_password_attempt(
+            shared_data.id,
+            user_id,
+            app_state.env.share_max_failed_attempts,
+            app_state.env.share_backoff_free_attempts,
+            app_state.env.share_backoff_base_seconds,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    match claim {
+        ShareAttemptClaim::Claimed => {}
+        ShareAttemptClaim::Throttled(wait) => {
+            record_share_access(
+                &app_state,
+                Some(shared_data.id),
+                shared_id,
+                user_id,
+                ShareAccessOutcome::Throttled,
+                &client,
+            )
+            .await;
+
+            return Err(HttpError::too_many_requests(
+                format!(
+                    "Too many failed password attempts. Try again in {} seconds.",
+                    wait
+                ),
+                wait,
+            ));
+        }
+        ShareAttemptClaim::Locked => {
+            record_share_access(
+                &app_state,
+                Some(shared_data.id),
+                shared_id,
+                user_id,
+                ShareAccessOutcome::Locked,
+                &client,
+            )
+            .await;
+
+            return Err(HttpError::locked(SHARE_LOCKED_MESSAGE));
+        }
+    }
 
     let match_password = password::compare(&body.password, &shared_data.password)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     if !match_password {
+        let locked = app_state
+            .db_client
+            .lock_exhausted_shared(shared_data.id, app_state.env.share_max_failed_attempts)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        record_share_access(
+            &app_state,
+            Some(shared_data.id),
+            shared_id,
+            user_id,
+            ShareAccessOutcome::WrongPassword,
+            &client,
+        )
+        .await;
+
+        if locked {
+            return Err(HttpError::locked(SHARE_LOCKED_MESSAGE));
+        }
+
         return Err(HttpError::bad_request(
             "The provided password is incorect.".to_string(),
         ));
     }
 
+    app_state
+        .db_client
+        .settle_share_password_attempt(shared_data.id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
     let file_id = match shared_data.file_id {
         Some(id) => id,
         None => {
@@ -173,7 +408,7 @@ pub async fn retrive_file(
 
     let file_result = app_state
         .db_client
-        .get_file(file_id.clone())
+        .get_file(file_id)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -198,6 +433,49 @@ pub async fn retrive_file(
     )
     .await?;
 
+    app_state
+        .db_client
+        .mark_shared_downloaded(shared_data.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    record_share_access(
+        &app_state,
+        Some(shared_data.id),
+        shared_id,
+        user_id,
+        ShareAccessOutcome::Success,
+        &client,
+    )
+    .await;
+
+    emit_share_event_by_id(&app_state, WebhookEvent::Downloaded, shared_data.id).await;
+
+    if let Some(sender_id) = file_data.user_id {
+        let sender = app_state
+            .db_client
+            .get_user(Some(sender_id), None, None)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if let Some(sender) = sender {
+            notify_user(
+                &app_state,
+                sender.id,
+                &sender.email,
+                Notification::FileDownloaded,
+                templates::file_downloaded(
+                    &sender.username,
+                    &user.user.email,
+                    &file_data.file_name,
+                    &Utc::now(),
+                    &app_state.env.app_url,
+                ),
+            )
+            .await;
+        }
+    }
+
     let response = Response::builder()
         .status(StatusCode::OK)
         .header(
@@ -210,3 +488,383 @@ pub async fn retrive_file(
 
     Ok(response)
 }
+
+pub async fn get_share(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    // Shares belonging to someone else look exactly like missing ones.
+    let share = app_state
+        .db_client
+        .get_share_details(share_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new(
+                "The requested shared file does not exist.",
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    user.require_scope(if share.recipient_id == user_id {
+        AccessTokenScope::FilesReceive
+    } else {
+        AccessTokenScope::FilesSend
+    })?;
+
+    let response = ShareDetailResponseDto {
+        status: "success".to_string(),
+        share: ShareDetailDto::filter_share(&share, user_id),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn get_share_access_log(
+    Path(share_id): Path<uuid::Uuid>,
+    Query(query_params): Query<RequestQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let page = query_params.page.unwrap_or(1);
+    let limit = query_params.limit.unwrap_or(10);
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let shared_result = app_state
+        .db_client
+        .get_sent_shared(share_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if shared_result.is_none() {
+        return Err(HttpError::new(
+            "The requested shared file does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let (logs, total_count) = app_state
+        .db_client
+        .get_share_access_logs(share_id, page as u32, limit)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = ShareAccessLogListResponseDto {
+        status: "success".to_string(),
+        logs: ShareAccessLogDto::filter_access_logs(&logs),
+        results: total_count,
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn unlock_share(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let shared_result = app_state
+        .db_client
+        .get_sent_shared(share_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if shared_result.is_none() {
+        return Err(HttpError::new(
+            "The requested shared file does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    app_state
+        .db_client
+        .unlock_shared(share_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = ResponseDto {
+        message: "Shared file unlocked successfully".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
+
+/// Brings an expired share back with a new expiration date, as long as the
+/// cleanup job has not removed it yet (see `EXPIRED_GRACE_HOURS`).
+pub async fn restore_share(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<RestoreShareDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let shared_data = app_state
+        .db_client
+        .get_sent_shared(share_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|shared| shared.revoked_at.is_none())
+        .ok_or_else(|| {
+            HttpError::new(
+                "The requested shared file does not exist.",
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    let now = Utc::now();
+    let grace_cutoff = now - chrono::Duration::hours(app_state.env.expired_grace_hours);
+
+    match shared_data.expiration_date {
+        Some(expiration_date) if expiration_date > now => {
+            return Err(HttpError::bad_request(
+                "The shared file has not expired yet.",
+            ));
+        }
+        Some(expiration_date) if expiration_date < grace_cutoff => {
+            return Err(HttpError::new(
+                "The shared file can no longer be restored.",
+                StatusCode::GONE,
+            ));
+        }
+        _ => {}
+    }
+
+    let expiration_date = app_state
+        .env
+        .expiry_policy(&user.user.role)
+        .resolve(&body.expiration_date, now)
+        .map_err(HttpError::bad_request)?;
+
+    if shared_data
+        .available_from
+        .is_some_and(|available_from| available_from >= expiration_date)
+    {
+        return Err(HttpError::bad_request(
+            "Expiration date must be after the share becomes available.",
+        ));
+    }
+
+    app_state
+        .db_client
+        .restore_shared(share_id, expiration_date)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = ResponseDto {
+        message: "Shared file restored successfully".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn revoke_share(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let shared_data = app_state
+        .db_client
+        .get_sent_shared(share_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new(
+                "The requested shared file does not exist.",
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    if shared_data.revoked_at.is_some() {
+        return Err(HttpError::bad_request(
+            "The shared file has already been revoked.",
+        ));
+    }
+
+    app_state
+        .db_client
+        .revoke_shared(share_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    emit_share_event_by_id(&app_state, WebhookEvent::Revoked, share_id).await;
+
+    let file_result = match shared_data.file_id {
+        Some(file_id) => app_state
+            .db_client
+            .get_file(file_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+        None => None,
+    };
+
+    let recipient_result = match shared_data.recipient_user_id {
+        Some(recipient_id) => app_state
+            .db_client
+            .get_user(Some(recipient_id), None, None)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+        None => None,
+    };
+
+    if let (Some(file_data), Some(recipient)) = (file_result, recipient_result) {
+        notify_user(
+            &app_state,
+            recipient.id,
+            &recipient.email,
+            Notification::ShareRevoked,
+            templates::share_revoked(
+                &recipient.username,
+                &user.user.email,
+                &file_data.file_name,
+                &app_state.env.app_url,
+            ),
+        )
+        .await;
+    }
+
+    let response = ResponseDto {
+        message: "Shared file revoked successfully".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn update_share_state(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<RecipientStateUpdateDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let state = RecipientState::parse(&body.state)
+        .ok_or_else(|| HttpError::bad_request("Unknown share state"))?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let updated = app_state
+        .db_client
+        .update_recipient_state(share_id, user_id, state)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !updated {
+        return Err(HttpError::new(
+            "The requested shared file does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let response = ResponseDto {
+        message: "Shared file state updated successfully".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
+
+/// Lets the recipient refuse a share. The sender is told, and the share is deleted
+/// together with its encrypted copy rather than kept around in a declined state.
+pub async fn decline_share(
+    Path(share_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let shared_data = app_state
+        .db_client
+        .get_shared_by_id(share_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|shared| shared.recipient_user_id == Some(user_id) && shared.revoked_at.is_none())
+        .ok_or_else(|| {
+            HttpError::new(
+                "The requested shared file does not exist.",
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    let file_result = match shared_data.file_id {
+        Some(file_id) => app_state
+            .db_client
+            .get_file(file_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+        None => None,
+    };
+
+    // Queue the event while the share still exists to build its payload from.
+    emit_share_event_by_id(&app_state, WebhookEvent::Declined, share_id).await;
+
+    app_state
+        .db_client
+        .delete_shared_file(share_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if let Some(file_data) = file_result {
+        if let Some(sender_id) = file_data.user_id {
+            let sender = app_state
+                .db_client
+                .get_user(Some(sender_id), None, None)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+            if let Some(sender) = sender {
+                notify_user(
+                    &app_state,
+                    sender.id,
+                    &sender.email,
+                    Notification::ShareDeclined,
+                    templates::share_declined(
+                        &sender.username,
+                        &user.user.email,
+                        &file_data.file_name,
+                        &app_state.env.app_url,
+                    ),
+                )
+                .await;
+            }
+        }
+    }
+
+    let response = ResponseDto {
+        message: "Shared file declined and deleted".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
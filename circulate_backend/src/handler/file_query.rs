@@ -1,34 +1,60 @@
 use std::sync::Arc;
 
-use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Json, Router};
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use validator::Validate;
 
 use crate::{
     db::UserExt,
     dtos::{
-        RequestQueryDto, UserReceiveFileDto, UserReceiveFileListResponseDto, UserSendFileDto,
-        UserSendFileListResponseDto,
+        ExportQueryDto, ShareHistoryDto, ShareListQueryDto, UserReceiveFileDto,
+        UserReceiveFileListResponseDto, UserSendFileDto, UserSendFileListResponseDto,
     },
     error::HttpError,
     middleware::JWTAuthMiddleware,
+    models::{AccessTokenScope, ShareDirection, ShareStatus},
+    utils::cursor::paginate,
     AppState,
 };
 
+/// Rows buffered between the database and a slow export client.
+const EXPORT_BUFFER_ROWS: usize = 64;
+
 pub fn get_file_list_handler() -> Router {
     Router::new()
         .route("/send", get(get_user_shared_file))
+        .route("/send/export", get(export_send_history))
         .route("/receive", get(get_receive_shared_files))
+        .route("/receive/export", get(export_receive_history))
 }
 
 pub async fn get_user_shared_file(
-    Query(query_params): Query<RequestQueryDto>,
+    Query(query_params): Query<ShareListQueryDto>,
     Extension(app_state): Extension<Arc<AppState>>,
     Extension(user): Extension<JWTAuthMiddleware>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesSend)?;
+
     query_params
         .validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    if query_params.state.is_some() {
+        return Err(HttpError::bad_request(
+            "state only applies to received files",
+        ));
+    }
+
+    let filter = query_params.to_filter();
+
     let user = &user.user;
 
     let page = query_params.page.unwrap_or(1);
@@ -38,30 +64,53 @@ pub async fn get_user_shared_file(
 
     let (shared_files, total_count) = app_state
         .db_client
-        .get_send_files(user_id.clone(), page as u32, limit)
+        .get_send_files(user_id, &filter, page as u32, limit)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
+    let (shared_files, next_cursor, prev_cursor) =
+        paginate(shared_files, limit, filter.cursor.as_ref(), page, |file| {
+            file.created_at
+                .map(|created_at| (created_at, file.share_id))
+        });
+
     let filter_send_files = UserSendFileDto::filter_send_user_files(&shared_files);
 
     let response = UserSendFileListResponseDto {
         status: "success".to_string(),
         files: filter_send_files,
         results: total_count,
+        next_cursor,
+        prev_cursor,
     };
 
     Ok(Json(response))
 }
 
 pub async fn get_receive_shared_files(
-    Query(query_params): Query<RequestQueryDto>,
+    Query(query_params): Query<ShareListQueryDto>,
     Extension(app_state): Extension<Arc<AppState>>,
     Extension(user): Extension<JWTAuthMiddleware>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
     query_params
         .validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    let filter = query_params.to_filter();
+
+    // The inbox only ever holds live shares, so these statuses would always be empty.
+    if filter
+        .statuses
+        .iter()
+        .any(|status| matches!(status, ShareStatus::Expired | ShareStatus::Revoked))
+    {
+        return Err(HttpError::bad_request(
+            "Received files can only be filtered by active or downloaded status",
+        ));
+    }
+
     let user = &user.user;
 
     let page = query_params.page.unwrap_or(1);
@@ -71,17 +120,131 @@ pub async fn get_receive_shared_files(
 
     let (receive_files, total_count) = app_state
         .db_client
-        .get_receive_files(user_id.clone(), page as u32, limit)
+        .get_receive_files(user_id, &query_params.states(), &filter, page as u32, limit)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
+    let (receive_files, next_cursor, prev_cursor) =
+        paginate(receive_files, limit, filter.cursor.as_ref(), page, |file| {
+            file.created_at
+                .map(|created_at| (created_at, file.share_id))
+        });
+
     let filter_receive_files = UserReceiveFileDto::filter_receive_user_files(&receive_files);
 
     let response = UserReceiveFileListResponseDto {
         status: "success".to_string(),
         files: filter_receive_files,
         results: total_count,
+        next_cursor,
+        prev_cursor,
     };
 
     Ok(Json(response))
 }
+
+pub async fn export_send_history(
+    Query(query_params): Query<ShareListQueryDto>,
+    Query(export_params): Query<ExportQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    if query_params.state.is_some() {
+        return Err(HttpError::bad_request(
+            "state only applies to received files",
+        ));
+    }
+
+    export_history(
+        app_state,
+        user,
+        ShareDirection::Sent,
+        query_params,
+        export_params,
+    )
+    .await
+}
+
+pub async fn export_receive_history(
+    Query(query_params): Query<ShareListQueryDto>,
+    Query(export_params): Query<ExportQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    export_history(
+        app_state,
+        user,
+        ShareDirection::Received,
+        query_params,
+        export_params,
+    )
+    .await
+}
+
+/// Streams a history export. Unlike the lists it covers revoked and expired shares,
+/// and every recipient state unless `state` is given; `page`, `limit` and `cursor`
+/// are ignored. A database error part way through aborts the response, so a
+/// truncated export never looks complete.
+async fn export_history(
+    app_state: Arc<AppState>,
+    user: JWTAuthMiddleware,
+    direction: ShareDirection,
+    query_params: ShareListQueryDto,
+    export_params: ExportQueryDto,
+) -> Result<Response<Body>, HttpError> {
+    user.require_scope(match direction {
+        ShareDirection::Sent => AccessTokenScope::FilesSend,
+        ShareDirection::Received => AccessTokenScope::FilesReceive,
+    })?;
+
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    export_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let filter = query_params.to_filter();
+    let states = query_params.state.as_ref().map(|_| query_params.states());
+    let format = export_params.format();
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let (tx, rx) = mpsc::channel(EXPORT_BUFFER_ROWS);
+
+    let db_client = app_state.db_client.clone();
+    let stream_direction = direction.clone();
+    tokio::spawn(async move {
+        let result = db_client
+            .stream_share_history(user_id, &stream_direction, states.as_deref(), &filter, &tx)
+            .await;
+
+        // Ending the stream quietly would look like a complete export; an error
+        // item makes the body fail and the connection drop instead.
+        if let Err(e) = result {
+            eprintln!("Failed to export share history: {}", e);
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    let file_name = format!(
+        "circulate-{}-history.{}",
+        direction.to_str(),
+        format.extension()
+    );
+    let content_type = format.content_type();
+    let header = tokio_stream::iter(format.header().map(Ok));
+
+    let rows = ReceiverStream::new(rx)
+        .map(move |row| row.map(|row| format.row(&ShareHistoryDto::filter_row(&row, &direction))));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        )
+        .header("Content-type", content_type)
+        .body(Body::from_stream(header.chain(rows)))
+        .map_err(|e| HttpError::server_error(e.to_string()))
+}
@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Multipart, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use crate::{
+    db::{FileRequestExt, UserExt},
+    dtos::{
+        CreateFileRequestDto, FileRequestDto, FileRequestListResponseDto, FileRequestResponseDto,
+        FileUploadResponseDto, PublicFileRequestDto, PublicFileRequestResponseDto, RequestQueryDto,
+        RequestedFileUploadDto,
+    },
+    error::HttpError,
+    mail::{
+        notify::{notify_user, Notification},
+        templates,
+    },
+    middleware::JWTAuthMiddleware,
+    models::{AccessTokenScope, FileRequest},
+    ratelimit::{layer::RateLimitLayer, RateLimitGroup},
+    utils::{
+        encrypt::{content_digest, encrypt_file, recipient_public_key},
+        password,
+    },
+    webhook::{emit_share_event_by_id, WebhookEvent},
+    AppState,
+};
+
+const FILE_REQUEST_NOT_FOUND_MESSAGE: &str = "The requested file request does not exist.";
+const FILE_REQUEST_CLOSED_MESSAGE: &str = "This file request is no longer accepting uploads.";
+
+pub fn file_request_handler() -> Router {
+    Router::new()
+        .route("/", get(get_file_requests).post(create_file_request))
+        .route("/:request_id", delete(close_file_request))
+}
+
+/// Routes used by whoever was sent the link; mounted outside the `auth` middleware.
+pub fn public_file_request_handler() -> Router {
+    Router::new()
+        .route("/:request_id", get(get_public_file_request))
+        .route(
+            "/:request_id/upload",
+            post(upload_requested_file).layer(RateLimitLayer::new(RateLimitGroup::RequestUpload)),
+        )
+}
+
+pub async fn create_file_request(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<CreateFileRequestDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if user.user.public_key.is_none() {
+        return Err(HttpError::bad_request(
+            "Generate your keys before requesting files",
+        ));
+    }
+
+    if !user.user.is_verified() {
+        return Err(HttpError::bad_request(
+            "Verify your email address before requesting files",
+        ));
+    }
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let hash_password = match &body.password {
+        Some(request_password) => Some(
+            password::hash(request_password).map_err(|e| HttpError::server_error(e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let deadline = DateTime::parse_from_rfc3339(&body.deadline)
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .with_timezone(&Utc);
+
+    let file_request = app_state
+        .db_client
+        .save_file_request(
+            user_id,
+            body.title,
+            body.description
+                .filter(|description| !description.is_empty()),
+            hash_password,
+            deadline,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = FileRequestResponseDto {
+        status: "success".to_string(),
+        file_request: FileRequestDto::filter_file_request(&file_request),
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn get_file_requests(
+    Query(query_params): Query<RequestQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let page = query_params.page.unwrap_or(1);
+    let limit = query_params.limit.unwrap_or(10);
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let (file_requests, total_count) = app_state
+        .db_client
+        .get_file_requests(user_id, page as u32, limit)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = FileRequestListResponseDto {
+        status: "success".to_string(),
+        file_requests: FileRequestDto::filter_file_requests(&file_requests),
+        results: total_count,
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn close_file_request(
+    Path(request_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::FilesReceive)?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let file_request = app_state
+        .db_client
+        .close_file_request(request_id, user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(FILE_REQUEST_NOT_FOUND_MESSAGE, StatusCode::NOT_FOUND))?;
+
+    let response = FileRequestResponseDto {
+        status: "success".to_string(),
+        file_request: FileRequestDto::filter_file_request(&file_request),
+    };
+
+    Ok(Json(response))
+}
+
+/// Loads a request that can still take uploads. Closed and past-deadline requests
+/// answer 410 so the upload page can tell them apart from a mistyped link.
+async fn get_open_file_request(
+    app_state: &AppState,
+    request_id: uuid::Uuid,
+) -> Result<FileRequest, HttpError> {
+    let file_request = app_state
+        .db_client
+        .get_file_request(request_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(FILE_REQUEST_NOT_FOUND_MESSAGE, StatusCode::NOT_FOUND))?;
+
+    if !file_request.is_open() {
+        return Err(HttpError::new(
+            FILE_REQUEST_CLOSED_MESSAGE,
+            StatusCode::GONE,
+        ));
+    }
+
+    Ok(file_request)
+}
+
+pub async fn get_public_file_request(
+    Path(request_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let file_request = get_open_file_request(&app_state, request_id).await?;
+
+    let requester = app_state
+        .db_client
+        .get_user(Some(file_request.user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(FILE_REQUEST_NOT_FOUND_MESSAGE, StatusCode::NOT_FOUND))?;
+
+    let response = PublicFileRequestResponseDto {
+        status: "success".to_string(),
+        file_request: PublicFileRequestDto::filter_file_request(&file_request, &requester),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn upload_requested_file(
+    Path(request_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, HttpError> {
+    let file_request = get_open_file_request(&app_state, request_id).await?;
+
+    let mut file_data = Vec::new();
+    let mut file_name = String::new();
+    let mut file_size: i64 = 0;
+    let mut form_data = RequestedFileUploadDto::default();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| HttpError::bad_request(e.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+
+        match name.as_str() {
+            "fileUpload" => {
+                file_name = field.file_name().unwrap_or("unknown_file").to_string();
+                file_data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| HttpError::bad_request(e.to_string()))?
+                    .to_vec();
+                file_size = file_data.len() as i64;
+            }
+            "uploader_email" => {
+                form_data.uploader_email = field.text().await.unwrap_or_default();
+            }
+            "password" => {
+                form_data.password = field.text().await.unwrap_or_default();
+            }
+            "expiration_date" => {
+                form_data.expiration_date = field.text().await.unwrap_or_default();
+            }
+            "request_password" => {
+                form_data.request_password = field.text().await.ok();
+            }
+            _ => {}
+        }
+    }
+
+    form_data
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if file_name.is_empty() {
+        return Err(HttpError::bad_request("File is required"));
+    }
+
+    if let Some(hashed_request_password) = &file_request.password {
+        // Counted as a failure up front, so parallel guesses cannot slip past the
+        // backoff between the check and the compare.
+        let wait = app_state
+            .db_client
+            .claim_request_password_attempt(
+                file_request.id,
+                app_state.env.share_backoff_free_attempts,
+                app_state.env.share_backoff_base_seconds,
+            )
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if let Some(wait) = wait {
+            return Err(HttpError::too_many_requests(
+                format!(
+                    "Too many failed password attempts. Try again in {} seconds.",
+                    wait
+                ),
+                wait,
+            ));
+        }
+
+        let request_password = form_data.request_password.as_deref().unwrap_or_default();
+        let password_match = password::compare(request_password, hashed_request_password)
+            .map_err(|_| HttpError::bad_request("Incorrect request password"))?;
+
+        if !password_match {
+            return Err(HttpError::unauthorized("Incorrect request password"));
+        }
+
+        app_state
+            .db_client
+            .settle_request_password_attempt(file_request.id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+    }
+
+    let requester = app_state
+        .db_client
+        .get_user(Some(file_request.user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(FILE_REQUEST_NOT_FOUND_MESSAGE, StatusCode::NOT_FOUND))?;
+
+    // Uploaders have no account, so the requester's policy decides how long the
+    // share may live.
+    let expiration_date = app_state
+        .env
+        .expiry_policy(&requester.role)
+        .resolve(&form_data.expiration_date, Utc::now())
+        .map_err(HttpError::bad_request)?;
+
+    let public_key_pem = recipient_public_key(&requester)?;
+
+    let sha256 = content_digest(&file_data);
+
+    let (encrypted_aes_key, encrypted_data, iv) = encrypt_file(file_data, &public_key_pem).await?;
+
+    let hash_password =
+        password::hash(&form_data.password).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let share_id = app_state
+        .db_client
+        .save_requested_file(
+            file_request.id,
+            form_data.uploader_email.clone(),
+            file_name.clone(),
+            file_size,
+            sha256,
+            requester.id,
+            hash_password,
+            expiration_date,
+            encrypted_aes_key,
+            encrypted_data,
+            iv,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(FILE_REQUEST_CLOSED_MESSAGE, StatusCode::GONE))?;
+
+    notify_user(
+        &app_state,
+        requester.id,
+        &requester.email,
+        Notification::FileReceived,
+        templates::file_received(
+            &requester.username,
+            &format!("{} (unverified)", form_data.uploader_email),
+            &file_name,
+            &expiration_date,
+            &app_state.env.app_url,
+        ),
+    )
+    .await;
+
+    emit_share_event_by_id(&app_state, WebhookEvent::Uploaded, share_id).await;
+
+    let response = FileUploadResponseDto {
+        status: "success",
+        message: "File uploaded and encrypted successfully".to_string(),
+        share_id: share_id.to_string(),
+        expiration_date,
+        available_from: None,
+    };
+
+    Ok(Json(response))
+}
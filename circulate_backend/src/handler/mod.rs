@@ -1,4 +1,14 @@
+pub mod access_token;
+pub mod admin;
 pub mod auth;
+pub mod events;
 pub mod file;
 pub mod file_query;
+pub mod file_request;
+pub mod oidc;
+pub mod role;
+pub mod session;
+pub mod stats;
+pub mod two_factor;
 pub mod user;
+pub mod webhook;
@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use validator::Validate;
+
+use crate::{
+    db::{OidcExt, RoleExt, UserExt},
+    dtos::{
+        OidcAuthorizeResponseDto, OidcCallbackDto, OidcProviderDto, OidcProviderListResponseDto,
+    },
+    error::{ErrorMessage, HttpError},
+    handler::auth::complete_login,
+    models::User,
+    oidc::{
+        client::{self, IdTokenClaims},
+        OidcProvider,
+    },
+    utils::{client::ClientInfo, keys::generate_key, password},
+    AppState,
+};
+
+/// How long the user has to finish signing in at the provider.
+const LOGIN_STATE_MINUTES: i64 = 10;
+
+pub fn oidc_handler() -> Router {
+    Router::new()
+        .route("/", get(get_oidc_providers))
+        .route("/:provider/authorize", get(authorize))
+        .route("/:provider/callback", post(callback))
+}
+
+fn get_provider<'a>(app_state: &'a AppState, name: &str) -> Result<&'a OidcProvider, HttpError> {
+    app_state.env.oidc_provider(name).ok_or_else(|| {
+        HttpError::new(
+            "The requested identity provider does not exist.",
+            StatusCode::NOT_FOUND,
+        )
+    })
+}
+
+pub async fn get_oidc_providers(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let response = OidcProviderListResponseDto {
+        status: "success".to_string(),
+        providers: OidcProviderDto::filter_providers(&app_state.env.oidc_providers),
+    };
+
+    Ok(Json(response))
+}
+
+/// Starts an authorization code login with PKCE. The caller sends the browser to
+/// `authorization_url`; the provider later redirects to the provider's redirect URI.
+pub async fn authorize(
+    Path(name): Path<String>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let provider = get_provider(&app_state, &name)?;
+
+    let metadata = client::discover(&app_state.oidc_cache, provider)
+        .await
+        .map_err(|e| {
+            eprintln!("OIDC discovery for {} failed: {}", provider.name, e);
+            HttpError::new(
+                format!("{} is not reachable right now", provider.display_name),
+                StatusCode::BAD_GATEWAY,
+            )
+        })?;
+
+    let state = client::generate_state();
+    let nonce = client::generate_state();
+    let code_verifier = client::generate_code_verifier();
+
+    let authorization_url =
+        client::authorization_url(&metadata, provider, &state, &nonce, &code_verifier)
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state
+        .db_client
+        .save_oidc_login_state(
+            state,
+            &provider.name,
+            nonce,
+            code_verifier,
+            Utc::now() + Duration::minutes(LOGIN_STATE_MINUTES),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = OidcAuthorizeResponseDto {
+        status: "success".to_string(),
+        authorization_url,
+    };
+
+    Ok(Json(response))
+}
+
+/// Completes the login with the code the provider returned. The user is found by
+/// their provider account; failing that, a verified local account with the same
+/// verified email is linked, or a new one is created. A mapped group sets the
+/// user's role; without a match the role is left alone.
+pub async fn callback(
+    Path(name): Path<String>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    client: ClientInfo,
+    Json(body): Json<OidcCallbackDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let provider = get_provider(&app_state, &name)?;
+
+    let login_state = app_state
+        .db_client
+        .take_oidc_login_state(&body.state, &provider.name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::bad_request(
+                "This sign-in attempt is invalid or has expired, please try again",
+            )
+        })?;
+
+    let claims = async {
+        let metadata = client::discover(&app_state.oidc_cache, provider).await?;
+        client::exchange_code(
+            &app_state.oidc_cache,
+            &metadata,
+            provider,
+            &body.code,
+            &login_state.code_verifier,
+            &login_state.nonce,
+        )
+        .await
+    }
+    .await
+    .map_err(|e| {
+        eprintln!("OIDC login with {} failed: {}", provider.name, e);
+        HttpError::unauthorized(format!("Could not sign in with {}", provider.display_name))
+    })?;
+
+    let mut user = find_or_provision_user(&app_state, provider, &claims).await?;
+
+    app_state
+        .db_client
+        .save_user_identity(&provider.name, &claims.sub, user.id, claims.email.clone())
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let groups = claims.groups(&provider.groups_claim);
+    if let Some(role) = provider.role_for_groups(&groups) {
+        if role != user.role {
+            let known_role = app_state
+                .db_client
+                .get_role(role)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?
+                .is_some();
+
+            if known_role {
+                match app_state
+                    .db_client
+                    .update_user_role(user.id, role)
+                    .await
+                    .map_err(|e| HttpError::server_error(e.to_string()))?
+                {
+                    Some(updated_user) => user = updated_user,
+                    None => eprintln!(
+                        "OIDC provider {} maps {} to {}, which would leave nobody able to \
+                         manage roles; keeping {}",
+                        provider.name, user.email, role, user.role
+                    ),
+                }
+            } else {
+                eprintln!(
+                    "OIDC provider {} maps to unknown role {}; create it first",
+                    provider.name, role
+                );
+            }
+        }
+    }
+
+    complete_login(&app_state, &user, client).await
+}
+
+async fn find_or_provision_user(
+    app_state: &Arc<AppState>,
+    provider: &OidcProvider,
+    claims: &IdTokenClaims,
+) -> Result<User, HttpError> {
+    let linked_user = app_state
+        .db_client
+        .get_identity_user(&provider.name, &claims.sub)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if let Some(user) = linked_user {
+        return Ok(user);
+    }
+
+    // Accounts are only matched or created by an address the provider vouches for;
+    // otherwise anyone able to set an email at the provider could take over accounts.
+    let email = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified())
+        .ok_or_else(|| {
+            HttpError::forbidden(format!(
+                "{} did not provide a verified email address",
+                provider.display_name
+            ))
+        })?;
+
+    let existing_user = app_state
+        .db_client
+        .get_user(None, None, Some(email))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    match existing_user {
+        // Anyone can register an address they do not own; its password and sessions
+        // would carry over if such an account were linked. Resetting the password
+        // proves the address and makes the account linkable.
+        Some(user) if !user.is_verified() => Err(HttpError::forbidden(format!(
+            "An unverified account already uses this email. Verify it or reset its password, then sign in with {} again",
+            provider.display_name
+        ))),
+        Some(user) => Ok(user),
+        None => {
+            let user = provision_user(app_state, claims, email).await?;
+
+            app_state
+                .db_client
+                .verify_user_email(user.id)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+            Ok(user)
+        }
+    }
+}
+
+/// Creates an account for a first-time SSO user. Its password is random and never
+/// shown; the user can set one through the password reset flow if they want it.
+async fn provision_user(
+    app_state: &Arc<AppState>,
+    claims: &IdTokenClaims,
+    email: &str,
+) -> Result<User, HttpError> {
+    let username = claims
+        .name
+        .as_deref()
+        .or(claims.preferred_username.as_deref())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email));
+
+    let unusable_password = hex::encode(rand::thread_rng().gen::<[u8; 32]>());
+    let hash_password =
+        password::hash(&unusable_password).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let user = app_state
+        .db_client
+        .save_user(username, email, &hash_password)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation(ErrorMessage::EmailExist.to_string())
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    generate_key(app_state.clone(), user.clone()).await?;
+
+    Ok(user)
+}
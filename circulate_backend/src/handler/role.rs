@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use validator::Validate;
+
+use crate::{
+    db::{RoleExt, UserExt},
+    dtos::{
+        AssignRoleDto, CreateRoleDto, FilterUserDto, Response, RoleDto, RoleListResponseDto,
+        RoleResponseDto, UpdateRoleDto, UserData, UserResponseDto,
+    },
+    error::HttpError,
+    middleware::JWTAuthMiddleware,
+    AppState,
+};
+
+const LAST_ROLES_MANAGER_MESSAGE: &str = "This would leave nobody able to manage roles.";
+
+pub fn role_handler() -> Router {
+    Router::new()
+        .route("/", get(get_roles).post(create_role))
+        .route("/:name", put(update_role).delete(delete_role))
+}
+
+pub async fn get_roles(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let roles = app_state
+        .db_client
+        .get_roles()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = RoleListResponseDto {
+        status: "success".to_string(),
+        roles: RoleDto::filter_roles(&roles),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn create_role(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<CreateRoleDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    require_held_permissions(&user, &body.permissions, "grant")?;
+
+    let result = app_state
+        .db_client
+        .save_role(
+            &body.name,
+            body.description,
+            permission_set(body.permissions),
+        )
+        .await;
+
+    match result {
+        Ok(role) => {
+            let response = RoleResponseDto {
+                status: "success".to_string(),
+                role: RoleDto::filter_role(&role),
+            };
+
+            Ok((StatusCode::CREATED, Json(response)))
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => Err(
+            HttpError::unique_constraint_violation("A role with this name already exists"),
+        ),
+        Err(e) => Err(HttpError::server_error(e.to_string())),
+    }
+}
+
+/// Built-in roles are fixed, so only custom roles can be changed, and only by
+/// someone holding every permission the role has before and after.
+pub async fn update_role(
+    Path(name): Path<String>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<UpdateRoleDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    require_held_permissions(&user, &body.permissions, "grant")?;
+
+    let current = app_state
+        .db_client
+        .get_role(&name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|role| !role.built_in)
+        .ok_or_else(|| {
+            HttpError::new(
+                "The requested custom role does not exist.",
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    require_held_permissions(&user, &current.permissions, "take away")?;
+
+    let role = app_state
+        .db_client
+        .update_role(&name, body.description, permission_set(body.permissions))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(LAST_ROLES_MANAGER_MESSAGE, StatusCode::CONFLICT))?;
+
+    let response = RoleResponseDto {
+        status: "success".to_string(),
+        role: RoleDto::filter_role(&role),
+    };
+
+    Ok(Json(response))
+}
+
+/// Refused while users still have the role; move them to another role first.
+pub async fn delete_role(
+    Path(name): Path<String>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    if let Some(role) = app_state
+        .db_client
+        .get_role(&name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+    {
+        require_held_permissions(&user, &role.permissions, "take away")?;
+    }
+
+    let deleted = match app_state.db_client.delete_role(&name).await {
+        Ok(deleted) => deleted,
+        Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+            return Err(HttpError::new(
+                "This role is still assigned to users.",
+                StatusCode::CONFLICT,
+            ));
+        }
+        Err(e) => return Err(HttpError::server_error(e.to_string())),
+    };
+
+    if !deleted {
+        return Err(HttpError::new(
+            "The requested custom role does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let response = Response {
+        status: "success",
+        message: "Role deleted".to_string(),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn set_user_role(
+    Path(user_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<AssignRoleDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    // Someone else has to change an admin's role, so nobody drops their own access
+    // by mistake.
+    if user_id == user.user.id {
+        return Err(HttpError::forbidden("You cannot change your own role"));
+    }
+
+    let role = app_state
+        .db_client
+        .get_role(&body.role)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request(format!("Unknown role {}", body.role)))?;
+
+    require_held_permissions(&user, &role.permissions, "grant")?;
+
+    let target = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new("The requested user does not exist.", StatusCode::NOT_FOUND)
+        })?;
+
+    // Moving someone off a role takes its permissions away from them, which is as
+    // much a change to what they can do as granting new ones.
+    if let Some(current) = app_state
+        .db_client
+        .get_role(&target.role)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+    {
+        require_held_permissions(&user, &current.permissions, "take away")?;
+    }
+
+    let updated_user = app_state
+        .db_client
+        .update_user_role(user_id, &role.name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new(LAST_ROLES_MANAGER_MESSAGE, StatusCode::CONFLICT))?;
+
+    let response = UserResponseDto {
+        status: "success".to_string(),
+        data: UserData {
+            user: FilterUserDto::filter_user(&updated_user),
+            permissions: role.permissions,
+        },
+    };
+
+    Ok(Json(response))
+}
+
+/// Roles only hand out or take away what the caller can already do, so
+/// `roles:manage` does not lead to every other permission, nor to demoting those
+/// who hold more.
+fn require_held_permissions(
+    user: &JWTAuthMiddleware,
+    permissions: &[String],
+    action: &str,
+) -> Result<(), HttpError> {
+    match permissions
+        .iter()
+        .find(|permission| !user.permissions.contains(permission))
+    {
+        Some(permission) => Err(HttpError::forbidden(format!(
+            "You cannot {} the {} permission without holding it",
+            action, permission
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn permission_set(mut permissions: Vec<String>) -> Vec<String> {
+    permissions.sort();
+    permissions.dedup();
+    permissions
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path, http::StatusCode, response::IntoResponse, routing::delete, Extension, Json,
+    Router,
+};
+
+use crate::{
+    db::SessionExt,
+    dtos::{Response, SessionDto, SessionListResponseDto},
+    error::HttpError,
+    middleware::JWTAuthMiddleware,
+    models::SessionRevokeReason,
+    AppState,
+};
+
+pub fn session_handler() -> Router {
+    Router::new()
+        .route("/", delete(revoke_other_sessions).get(get_sessions))
+        .route("/:session_id", delete(revoke_session))
+}
+
+pub async fn get_sessions(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let sessions = app_state
+        .db_client
+        .get_user_sessions(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = SessionListResponseDto {
+        status: "success".to_string(),
+        sessions: SessionDto::filter_sessions(&sessions, user.session_id()?),
+    };
+
+    Ok(Json(response))
+}
+
+/// Signs the user out everywhere except the device making the request.
+pub async fn revoke_other_sessions(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let revoked = app_state
+        .db_client
+        .revoke_other_sessions(
+            user.user.id,
+            Some(user.session_id()?),
+            SessionRevokeReason::RevokedByUser,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = Response {
+        status: "success",
+        message: format!("Signed out of {} other session(s)", revoked),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn revoke_session(
+    Path(session_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let revoked = app_state
+        .db_client
+        .revoke_user_session(session_id, user.user.id, SessionRevokeReason::RevokedByUser)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !revoked {
+        return Err(HttpError::new(
+            "The requested session does not exist.",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let response = Response {
+        status: "success",
+        message: "Session revoked".to_string(),
+    };
+
+    Ok(Json(response))
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Json, Router};
+use chrono::Utc;
+use validator::Validate;
+
+use crate::{
+    db::StatsExt,
+    dtos::{StatsQueryDto, TransferStatsBucketDto, TransferStatsDto, TransferStatsResponseDto},
+    error::HttpError,
+    middleware::JWTAuthMiddleware,
+    models::AccessTokenScope,
+    AppState,
+};
+
+/// Most buckets a single request may ask for.
+const MAX_STATS_BUCKETS: i64 = 400;
+
+/// Whose transfers a router reports on: the caller's own, or the whole
+/// organisation's (needs `stats:read`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsScope {
+    User,
+    Organization,
+}
+
+impl StatsScope {
+    fn user_id(&self, user: &JWTAuthMiddleware) -> Option<uuid::Uuid> {
+        match self {
+            StatsScope::User => Some(user.user.id),
+            StatsScope::Organization => None,
+        }
+    }
+
+    fn to_str(self) -> &'static str {
+        match self {
+            StatsScope::User => "user",
+            StatsScope::Organization => "organization",
+        }
+    }
+}
+
+pub fn stats_handler(scope: StatsScope) -> Router {
+    Router::new()
+        .route("/", get(get_transfer_stats))
+        .layer(Extension(scope))
+}
+
+pub async fn get_transfer_stats(
+    Query(query_params): Query<StatsQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(scope): Extension<StatsScope>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::UsersRead)?;
+
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let (from, to) = query_params.range(Utc::now());
+    let bucket = query_params.bucket();
+
+    if from >= to {
+        return Err(HttpError::bad_request("from must be before to"));
+    }
+
+    if (to - from).num_days() / bucket.approx_days() > MAX_STATS_BUCKETS {
+        return Err(HttpError::bad_request(format!(
+            "Range too long for {} buckets. Use a shorter range or a wider bucket.",
+            bucket.to_str()
+        )));
+    }
+
+    let user_id = scope.user_id(&user);
+
+    let totals = app_state
+        .db_client
+        .get_transfer_stats(user_id, from, to)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let buckets = app_state
+        .db_client
+        .get_transfer_stats_buckets(user_id, from, to, &bucket)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = TransferStatsResponseDto {
+        status: "success".to_string(),
+        scope: scope.to_str().to_string(),
+        from,
+        to,
+        bucket: bucket.to_str().to_string(),
+        totals: TransferStatsDto::filter_stats(&totals),
+        buckets: TransferStatsBucketDto::filter_buckets(&buckets),
+    };
+
+    Ok(Json(response))
+}
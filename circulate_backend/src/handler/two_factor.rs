@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use chrono::Utc;
+use validator::Validate;
+
+use crate::{
+    db::{SessionExt, TwoFactorExt, UserExt},
+    dtos::{
+        Response, TwoFactorCodeDto, TwoFactorDisableDto, TwoFactorEnabledResponseDto,
+        TwoFactorRequirementDto, TwoFactorSetupResponseDto, TwoFactorStatusDto,
+        TwoFactorStatusResponseDto,
+    },
+    error::{ErrorMessage, HttpError},
+    middleware::JWTAuthMiddleware,
+    models::{SessionRevokeReason, TwoFactor},
+    utils::{password, totp},
+    AppState,
+};
+
+pub fn two_factor_handler() -> Router {
+    Router::new()
+        .route("/", get(get_two_factor_status))
+        .route("/setup", post(setup_two_factor))
+        .route("/confirm", post(confirm_two_factor))
+        .route("/disable", post(disable_two_factor))
+}
+
+/// Checks a TOTP code or, failing that, a backup code, and uses it up. A TOTP code
+/// that was already accepted once is refused.
+pub async fn verify_second_factor(
+    app_state: &AppState,
+    two_factor: &TwoFactor,
+    code: &str,
+) -> Result<bool, HttpError> {
+    let Some(secret) = two_factor.totp_secret.as_deref() else {
+        return Ok(false);
+    };
+
+    if let Some(step) = totp::verify(
+        secret,
+        code,
+        Utc::now().timestamp(),
+        two_factor.last_used_step,
+    ) {
+        return app_state
+            .db_client
+            .record_totp_step(two_factor.user_id, step)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()));
+    }
+
+    app_state
+        .db_client
+        .use_backup_code(
+            two_factor.user_id,
+            &totp::hash_backup_code(&two_factor.user_id, code),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+async fn get_enabled_two_factor(
+    app_state: &AppState,
+    user_id: uuid::Uuid,
+) -> Result<TwoFactor, HttpError> {
+    app_state
+        .db_client
+        .get_two_factor(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|two_factor| two_factor.is_enabled())
+        .ok_or_else(|| HttpError::bad_request("Two-factor authentication is not enabled"))
+}
+
+pub async fn get_two_factor_status(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    let two_factor = app_state
+        .db_client
+        .get_two_factor(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let backup_codes_remaining = app_state
+        .db_client
+        .count_backup_codes(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = TwoFactorStatusResponseDto {
+        status: "success".to_string(),
+        two_factor: TwoFactorStatusDto::filter_two_factor(
+            two_factor.as_ref(),
+            backup_codes_remaining,
+        ),
+    };
+
+    Ok(Json(response))
+}
+
+/// Starts enrollment with a fresh secret. 2FA stays off until a code from the
+/// authenticator app is confirmed; calling this again replaces the pending secret.
+pub async fn setup_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    if !user.user.is_verified() {
+        return Err(HttpError::forbidden(
+            "Verify your email address before enabling two-factor authentication",
+        ));
+    }
+
+    let secret = totp::generate_secret();
+
+    let saved = app_state
+        .db_client
+        .save_pending_totp_secret(user.user.id, secret.clone())
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !saved {
+        return Err(HttpError::bad_request(
+            "Two-factor authentication is already enabled",
+        ));
+    }
+
+    let otpauth_url = totp::provisioning_uri(&secret, &user.user.email, &app_state.env.totp_issuer);
+
+    let response = TwoFactorSetupResponseDto {
+        status: "success".to_string(),
+        secret,
+        otpauth_url,
+    };
+
+    Ok(Json(response))
+}
+
+/// Enables 2FA once the user proves their app produces the right codes, and signs
+/// out every other session since they were started without a second factor.
+pub async fn confirm_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<TwoFactorCodeDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let two_factor = app_state
+        .db_client
+        .get_two_factor(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let secret = match &two_factor {
+        Some(two_factor) if two_factor.is_enabled() => {
+            return Err(HttpError::bad_request(
+                "Two-factor authentication is already enabled",
+            ))
+        }
+        Some(TwoFactor {
+            totp_secret: Some(secret),
+            ..
+        }) => secret,
+        _ => {
+            return Err(HttpError::bad_request(
+                "Start two-factor setup before confirming it",
+            ))
+        }
+    };
+
+    let step = totp::verify(secret, &body.code, Utc::now().timestamp(), None)
+        .ok_or_else(|| HttpError::bad_request(ErrorMessage::InvalidTwoFactorCode.to_string()))?;
+
+    let backup_codes = totp::generate_backup_codes();
+    let backup_code_hashes = backup_codes
+        .iter()
+        .map(|code| totp::hash_backup_code(&user.user.id, code))
+        .collect();
+
+    let enabled = app_state
+        .db_client
+        .enable_totp(user.user.id, step, backup_code_hashes)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !enabled {
+        return Err(HttpError::bad_request(
+            "Two-factor authentication is already enabled",
+        ));
+    }
+
+    app_state
+        .db_client
+        .revoke_other_sessions(
+            user.user.id,
+            Some(user.session_id()?),
+            SessionRevokeReason::TwoFactorEnabled,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = TwoFactorEnabledResponseDto {
+        status: "success".to_string(),
+        backup_codes,
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn disable_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<TwoFactorDisableDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let password_matched = password::compare(&body.password, &user.user.password)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !password_matched {
+        return Err(HttpError::bad_request(
+            ErrorMessage::InvalidCredentials.to_string(),
+        ));
+    }
+
+    let two_factor = get_enabled_two_factor(&app_state, user.user.id).await?;
+
+    if !verify_second_factor(&app_state, &two_factor, &body.code).await? {
+        return Err(HttpError::bad_request(
+            ErrorMessage::InvalidTwoFactorCode.to_string(),
+        ));
+    }
+
+    app_state
+        .db_client
+        .disable_totp(user.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = Response {
+        status: "success",
+        message: "Two-factor authentication disabled".to_string(),
+    };
+
+    Ok(Json(response))
+}
+
+/// Admin switch; a user who is required to use 2FA cannot retrieve files until
+/// they enable it.
+pub async fn set_two_factor_requirement(
+    Path(user_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorRequirementDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new("The requested user does not exist.", StatusCode::NOT_FOUND)
+        })?;
+
+    app_state
+        .db_client
+        .set_two_factor_required(user_id, body.required)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = Response {
+        status: "success",
+        message: if body.required {
+            "Two-factor authentication is now required for this user".to_string()
+        } else {
+            "Two-factor authentication is no longer required for this user".to_string()
+        },
+    };
+
+    Ok(Json(response))
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     extract::Query,
+    middleware,
     response::IntoResponse,
     routing::{get, put},
     Extension, Json, Router,
@@ -9,13 +10,23 @@ use axum::{
 use validator::Validate;
 
 use crate::{
-    db::UserExt,
+    db::{LoginSecurityExt, UserExt},
     dtos::{
-        searchQueryByEmailDto, EmailListResponseDto, FilterEmailDto, FilterUserDto, NameUpdateDto,
-        Response, UserData, UserPasswordUpdateDto, UserResponseDto,
+        EmailListResponseDto, FilterEmailDto, FilterUserDto, LoginEventDto,
+        LoginEventListResponseDto, NameUpdateDto, NotificationPreferencesDto,
+        NotificationPreferencesResponseDto, NotificationPreferencesUpdateDto, RequestQueryDto,
+        Response, SearchQueryByEmailDto, UserData, UserPasswordUpdateDto, UserResponseDto,
     },
     error::{ErrorMessage, HttpError},
-    middleware::JWTAuthMiddleware,
+    handler::{
+        access_token::access_token_handler,
+        session::session_handler,
+        stats::{stats_handler, StatsScope},
+        two_factor::two_factor_handler,
+    },
+    middleware::{require_session, JWTAuthMiddleware},
+    models::AccessTokenScope,
+    ratelimit::{layer::RateLimitLayer, RateLimitGroup},
     utils::password,
     AppState,
 };
@@ -25,19 +36,43 @@ pub fn users_handler() -> Router {
         .route("/me", get(get_me))
         .route("/name", put(update_user_name))
         .route("/password", put(update_user_password))
-        .route("/search-emails", get(search_by_email))
+        .route(
+            "/search-emails",
+            get(search_by_email).layer(RateLimitLayer::new(RateLimitGroup::Search)),
+        )
+        .route(
+            "/notifications",
+            get(get_notification_preferences).put(update_notification_preferences),
+        )
+        .route("/login-events", get(get_login_events))
+        .nest("/stats", stats_handler(StatsScope::User))
+        .nest(
+            "/sessions",
+            session_handler().layer(middleware::from_fn(require_session)),
+        )
+        .nest(
+            "/2fa",
+            two_factor_handler().layer(middleware::from_fn(require_session)),
+        )
+        .nest(
+            "/tokens",
+            access_token_handler().layer(middleware::from_fn(require_session)),
+        )
 }
 
 pub async fn get_me(
     Extension(_app_state): Extension<Arc<AppState>>,
     Extension(user): Extension<JWTAuthMiddleware>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::UsersRead)?;
+
     let filtered_user = FilterUserDto::filter_user(&user.user);
 
     let response_data = UserResponseDto {
         status: "success".to_string(),
         data: UserData {
             user: filtered_user,
+            permissions: user.permissions.clone(),
         },
     };
 
@@ -49,15 +84,16 @@ pub async fn update_user_name(
     Extension(user): Extension<JWTAuthMiddleware>,
     Json(body): Json<NameUpdateDto>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.session_id()?;
+
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
-    let user = &user.user;
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
 
     let result = app_state
         .db_client
-        .update_user_name(user_id.clone(), &body.username)
+        .update_user_name(user_id, &body.username)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -67,6 +103,7 @@ pub async fn update_user_name(
         status: "success".to_string(),
         data: UserData {
             user: filtered_user,
+            permissions: user.permissions.clone(),
         },
     };
 
@@ -81,13 +118,14 @@ pub async fn update_user_password(
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    let session_id = user.session_id()?;
     let user = &user.user;
 
     let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
 
     let result = app_state
         .db_client
-        .get_user(Some(user_id.clone()), None, None)
+        .get_user(Some(user_id), None, None)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -105,14 +143,17 @@ pub async fn update_user_password(
     let hashed_password =
         password::hash(&body.new_password).map_err(|e| HttpError::server_error(e.to_string()))?;
 
+    // Whoever knew the old password should not stay signed in elsewhere or keep
+    // using tokens they created with it.
     app_state
         .db_client
-        .update_user_password(user_id.clone(), hashed_password)
+        .update_user_password(user_id, hashed_password, session_id)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     let response = Response {
-        message: "Password updated successfully".to_string(),
+        message: "Password updated. Your other sessions were signed out and your access tokens were revoked"
+            .to_string(),
         status: "success",
     };
 
@@ -120,10 +161,12 @@ pub async fn update_user_password(
 }
 
 pub async fn search_by_email(
-    Query(params): Query<searchQueryByEmailDto>,
+    Query(params): Query<SearchQueryByEmailDto>,
     Extension(app_state): Extension<Arc<AppState>>,
     Extension(user): Extension<JWTAuthMiddleware>,
 ) -> Result<impl IntoResponse, HttpError> {
+    user.require_scope(AccessTokenScope::UsersRead)?;
+
     params
         .validate()
         .map_err(|e| HttpError::server_error(e.to_string()))?;
@@ -133,7 +176,7 @@ pub async fn search_by_email(
 
     let users = app_state
         .db_client
-        .search_by_email(user_id.clone(), query_pattern.clone())
+        .search_by_email(user_id, query_pattern.clone())
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -146,3 +189,90 @@ pub async fn search_by_email(
 
     Ok(Json(response_data))
 }
+
+pub async fn get_notification_preferences(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.session_id()?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let preferences = app_state
+        .db_client
+        .get_notification_preferences(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = NotificationPreferencesResponseDto {
+        status: "success".to_string(),
+        preferences: NotificationPreferencesDto::filter_preferences(preferences.as_ref()),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn update_notification_preferences(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Json(body): Json<NotificationPreferencesUpdateDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.session_id()?;
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let preferences = app_state
+        .db_client
+        .update_notification_preferences(
+            user_id,
+            body.file_received,
+            body.file_downloaded,
+            body.share_expiring,
+            body.share_revoked,
+            body.share_declined,
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = NotificationPreferencesResponseDto {
+        status: "success".to_string(),
+        preferences: NotificationPreferencesDto::filter_preferences(Some(&preferences)),
+    };
+
+    Ok(Json(response))
+}
+
+/// Recent login attempts on the user's account, successful or not, newest first.
+pub async fn get_login_events(
+    Query(query_params): Query<RequestQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+) -> Result<impl IntoResponse, HttpError> {
+    user.session_id()?;
+
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let page = query_params.page.unwrap_or(1);
+    let limit = query_params.limit.unwrap_or(10);
+
+    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+
+    let (events, total_count) = app_state
+        .db_client
+        .get_login_events(user_id, page as u32, limit)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = LoginEventListResponseDto {
+        status: "success".to_string(),
+        events: LoginEventDto::filter_login_events(&events),
+        results: total_count,
+    };
+
+    Ok(Json(response))
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use rand::Rng;
+use validator::Validate;
+
+use crate::{
+    db::WebhookExt,
+    dtos::{
+        CreateWebhookDto, RequestQueryDto, Response, WebhookAttemptDto,
+        WebhookAttemptListResponseDto, WebhookCreatedResponseDto, WebhookDto,
+        WebhookListResponseDto,
+    },
+    error::HttpError,
+    middleware::JWTAuthMiddleware,
+    webhook::target::resolve_target,
+    AppState,
+};
+
+/// Whose webhooks a router manages: the caller's own, or the organisation-wide
+/// ones that receive every event (needs `webhooks:manage`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebhookScope {
+    User,
+    Organization,
+}
+
+impl WebhookScope {
+    fn owner(&self, user: &JWTAuthMiddleware) -> Option<uuid::Uuid> {
+        match self {
+            WebhookScope::User => Some(user.user.id),
+            WebhookScope::Organization => None,
+        }
+    }
+}
+
+pub fn webhook_handler(scope: WebhookScope) -> Router {
+    Router::new()
+        .route("/", get(get_webhooks).post(create_webhook))
+        .route("/:webhook_id", delete(delete_webhook))
+        .route("/:webhook_id/attempts", get(get_webhook_attempts))
+        .layer(Extension(scope))
+}
+
+pub async fn create_webhook(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(scope): Extension<WebhookScope>,
+    Json(body): Json<CreateWebhookDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    if !user.user.is_verified() {
+        return Err(HttpError::forbidden(
+            "Verify your email address before adding webhooks",
+        ));
+    }
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    resolve_target(&body.url, app_state.env.webhook_allow_private_targets)
+        .await
+        .map_err(|e| HttpError::bad_request(e.to_str()))?;
+
+    let secret = format!(
+        "whsec_{}",
+        hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+    );
+
+    let mut events = body.events.clone();
+    events.sort();
+    events.dedup();
+
+    let endpoint = app_state
+        .db_client
+        .save_webhook_endpoint(scope.owner(&user), body.url, secret.clone(), events)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = WebhookCreatedResponseDto {
+        status: "success".to_string(),
+        webhook: WebhookDto::filter_webhook(&endpoint),
+        secret,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn get_webhooks(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(scope): Extension<WebhookScope>,
+) -> Result<impl IntoResponse, HttpError> {
+    let endpoints = app_state
+        .db_client
+        .get_webhook_endpoints(scope.owner(&user))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = WebhookListResponseDto {
+        status: "success".to_string(),
+        webhooks: WebhookDto::filter_webhooks(&endpoints),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn delete_webhook(
+    Path(webhook_id): Path<uuid::Uuid>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(scope): Extension<WebhookScope>,
+) -> Result<impl IntoResponse, HttpError> {
+    let endpoint = app_state
+        .db_client
+        .get_webhook_endpoint(webhook_id, scope.owner(&user))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new("Webhook not found", StatusCode::NOT_FOUND))?;
+
+    app_state
+        .db_client
+        .delete_webhook_endpoint(endpoint.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = Response {
+        message: "Webhook deleted successfully".to_string(),
+        status: "success",
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn get_webhook_attempts(
+    Path(webhook_id): Path<uuid::Uuid>,
+    Query(query_params): Query<RequestQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(scope): Extension<WebhookScope>,
+) -> Result<impl IntoResponse, HttpError> {
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let page = query_params.page.unwrap_or(1);
+    let limit = query_params.limit.unwrap_or(10);
+
+    let endpoint = app_state
+        .db_client
+        .get_webhook_endpoint(webhook_id, scope.owner(&user))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new("Webhook not found", StatusCode::NOT_FOUND))?;
+
+    let (attempts, total_count) = app_state
+        .db_client
+        .get_webhook_attempts(endpoint.id, page as u32, limit)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let response = WebhookAttemptListResponseDto {
+        status: "success".to_string(),
+        attempts: WebhookAttemptDto::filter_attempts(&attempts),
+        results: total_count,
+    };
+
+    Ok(Json(response))
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use super::{MailError, MailMessage, Mailer};
+
+/// Development transport: writes every message to a spool directory as a
+/// plain-text `.eml` file.
+#[derive(Debug)]
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: &str, dir: &str) -> Self {
+        FileMailer {
+            from: from.to_string(),
+            dir: PathBuf::from(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+
+        let now = Utc::now();
+        let mut path = self.dir.clone();
+        path.push(format!(
+            "{}-{}.eml",
+            now.format("%Y%m%d%H%M%S"),
+            uuid::Uuid::new_v4()
+        ));
+
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\r\n{}\r\n",
+            self.from,
+            message.to,
+            message.subject,
+            now.to_rfc2822(),
+            message.body
+        );
+
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+
+use super::{MailError, MailMessage, Mailer};
+
+/// Development transport that sends nothing and only notes each message on stderr,
+/// alongside the rest of the server's logging.
+#[derive(Debug)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        eprintln!("Mail to {}: {}", message.to, message.subject);
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+pub mod file;
+pub mod log;
+pub mod notify;
+pub mod smtp;
+pub mod templates;
+
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+use file::FileMailer;
+use log::LogMailer;
+use smtp::SmtpMailer;
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct MailError(pub String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MailError: {}", self.0)
+    }
+}
+
+impl std::error::Error for MailError {}
+
+#[async_trait]
+pub trait Mailer: Send + Sync + fmt::Debug {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError>;
+}
+
+pub fn build_mailer(config: &Config) -> Result<Arc<dyn Mailer>, MailError> {
+    match config.mail_transport.as_str() {
+        "smtp" => Ok(Arc::new(SmtpMailer::new(config)?)),
+        "file" => Ok(Arc::new(FileMailer::new(
+            &config.mail_from,
+            &config.mail_dir,
+        ))),
+        "log" => Ok(Arc::new(LogMailer)),
+        other => Err(MailError(format!("Unknown MAIL_TRANSPORT: {}", other))),
+    }
+}
@@ -0,0 +1,108 @@
+use uuid::Uuid;
+
+use super::{templates, templates::MailTemplate, MailMessage};
+use crate::{db::UserExt, models::NotificationPreferences, AppState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    FileReceived,
+    FileDownloaded,
+    ShareExpiring,
+    ShareRevoked,
+    ShareDeclined,
+}
+
+impl Notification {
+    fn is_enabled(&self, preferences: &NotificationPreferences) -> bool {
+        match self {
+            Notification::FileReceived => preferences.file_received,
+            Notification::FileDownloaded => preferences.file_downloaded,
+            Notification::ShareExpiring => preferences.share_expiring,
+            Notification::ShareRevoked => preferences.share_revoked,
+            Notification::ShareDeclined => preferences.share_declined,
+        }
+    }
+}
+
+/// Sends `template` to the user unless they opted out of this kind of notification.
+/// Delivery happens in the background so a slow mail server never blocks a request.
+pub async fn notify_user(
+    app_state: &AppState,
+    user_id: Uuid,
+    email: &str,
+    notification: Notification,
+    template: MailTemplate,
+) {
+    let preferences = match app_state
+        .db_client
+        .get_notification_preferences(user_id)
+        .await
+    {
+        Ok(preferences) => preferences,
+        Err(err) => {
+            eprintln!("Error loading notification preferences: {:?}", err);
+            return;
+        }
+    };
+
+    if let Some(preferences) = preferences {
+        if !notification.is_enabled(&preferences) {
+            return;
+        }
+    }
+
+    send_mail(app_state, email, template);
+}
+
+/// Sends `template` regardless of notification preferences, for account mail such
+/// as address verification. Delivery happens in the background.
+pub fn send_mail(app_state: &AppState, email: &str, template: MailTemplate) {
+    let mailer = app_state.mailer.clone();
+    let message = MailMessage {
+        to: email.to_string(),
+        subject: template.subject,
+        body: template.body,
+    };
+
+    tokio::spawn(async move {
+        if let Err(err) = mailer.send(message).await {
+            eprintln!("Error sending email: {}", err);
+        }
+    });
+}
+
+pub async fn notify_expiring_shares(app_state: &AppState) -> Result<(), sqlx::Error> {
+    let shares = app_state
+        .db_client
+        .get_shares_expiring_within(app_state.env.expiry_reminder_hours)
+        .await?;
+
+    if shares.is_empty() {
+        return Ok(());
+    }
+
+    for share in &shares {
+        let template = templates::share_expiring(
+            &share.recipient_name,
+            &share.sender_email,
+            &share.file_name,
+            &share.expiration_date,
+            &app_state.env.app_url,
+        );
+
+        notify_user(
+            app_state,
+            share.recipient_user_id,
+            &share.recipient_email,
+            Notification::ShareExpiring,
+            template,
+        )
+        .await;
+    }
+
+    let share_ids: Vec<Uuid> = shares.iter().map(|share| share.share_id).collect();
+    app_state.db_client.mark_expiry_notified(&share_ids).await?;
+
+    println!("Sent {} expiry notifications.", shares.len());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{MailError, MailMessage, Mailer};
+use crate::config::Config;
+
+#[derive(Debug)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &Config) -> Result<Self, MailError> {
+        let builder = match config.smtp_tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+                .map_err(|e| MailError(e.to_string()))?,
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+                .map_err(|e| MailError(e.to_string()))?,
+            // Plain SMTP, e.g. a local MailHog or smtp4dev instance.
+            _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+        };
+
+        let builder = match (&config.smtp_username, &config.smtp_password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.to_owned(), password.to_owned()))
+            }
+            _ => builder,
+        };
+
+        let from = config
+            .mail_from
+            .parse::<Mailbox>()
+            .map_err(|e| MailError(e.to_string()))?;
+
+        Ok(SmtpMailer {
+            transport: builder.port(config.smtp_port).build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        let to = message
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| MailError(e.to_string()))?;
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body)
+            .map_err(|e| MailError(e.to_string()))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+
+pub struct MailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+pub fn file_received(
+    recipient_name: &str,
+    sender_email: &str,
+    file_name: &str,
+    expiration_date: &DateTime<Utc>,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: format!("{} sent you \"{}\"", sender_email, file_name),
+        body: format!(
+            "Hi {},\n\n{} shared the file \"{}\" with you on Circulate.\n\
+             It is available until {}.\n\nOpen your inbox: {}/receive\n",
+            recipient_name,
+            sender_email,
+            file_name,
+            format_date(expiration_date),
+            app_url
+        ),
+    }
+}
+
+pub fn file_downloaded(
+    sender_name: &str,
+    recipient_email: &str,
+    file_name: &str,
+    downloaded_at: &DateTime<Utc>,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: format!("{} downloaded \"{}\"", recipient_email, file_name),
+        body: format!(
+            "Hi {},\n\n{} downloaded the file \"{}\" on {}.\n\nSee your sent files: {}/upload\n",
+            sender_name,
+            recipient_email,
+            file_name,
+            format_date(downloaded_at),
+            app_url
+        ),
+    }
+}
+
+pub fn share_expiring(
+    recipient_name: &str,
+    sender_email: &str,
+    file_name: &str,
+    expiration_date: &DateTime<Utc>,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: format!("\"{}\" expires soon", file_name),
+        body: format!(
+            "Hi {},\n\nThe file \"{}\" from {} expires on {}.\n\
+             Download it before then: {}/receive\n",
+            recipient_name,
+            file_name,
+            sender_email,
+            format_date(expiration_date),
+            app_url
+        ),
+    }
+}
+
+pub fn share_revoked(
+    recipient_name: &str,
+    sender_email: &str,
+    file_name: &str,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: format!("\"{}\" is no longer available", file_name),
+        body: format!(
+            "Hi {},\n\n{} revoked the file \"{}\" they shared with you.\n\
+             It can no longer be downloaded.\n\nOpen your inbox: {}/receive\n",
+            recipient_name, sender_email, file_name, app_url
+        ),
+    }
+}
+
+pub fn share_declined(
+    sender_name: &str,
+    recipient_email: &str,
+    file_name: &str,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: format!("{} declined \"{}\"", recipient_email, file_name),
+        body: format!(
+            "Hi {},\n\n{} declined the file \"{}\" you shared with them.\n\
+             The share and its encrypted copy have been deleted.\n\nSee your sent files: {}/upload\n",
+            sender_name, recipient_email, file_name, app_url
+        ),
+    }
+}
+
+pub fn verify_email(user_name: &str, token: &str, valid_hours: i64, app_url: &str) -> MailTemplate {
+    MailTemplate {
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Hi {},\n\nConfirm this is your email address to start receiving files on Circulate:\n\
+             {}/verify-email?token={}\n\nThe link is valid for {} hours. If you did not create \
+             an account, you can ignore this email.\n",
+            user_name, app_url, token, valid_hours
+        ),
+    }
+}
+
+pub fn password_reset(
+    user_name: &str,
+    token: &str,
+    valid_minutes: i64,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: "Reset your Circulate password".to_string(),
+        body: format!(
+            "Hi {},\n\nSomeone asked to reset the password of your Circulate account. \
+             Choose a new password here:\n{}/reset-password?token={}\n\n\
+             The link works once and is valid for {} minutes. Resetting signs you out \
+             everywhere; files shared with you stay readable.\n\n\
+             If you did not ask for this, you can ignore this email and your password \
+             stays the same.\n",
+            user_name, app_url, token, valid_minutes
+        ),
+    }
+}
+
+pub fn password_changed(user_name: &str, app_url: &str) -> MailTemplate {
+    MailTemplate {
+        subject: "Your Circulate password was changed".to_string(),
+        body: format!(
+            "Hi {},\n\nThe password of your Circulate account was just reset and all your \
+             sessions were signed out.\n\nIf this was not you, reset it again right away: \
+             {}/forgot-password\n",
+            user_name, app_url
+        ),
+    }
+}
+
+pub fn account_locked(user_name: &str, lockout_minutes: i32, app_url: &str) -> MailTemplate {
+    MailTemplate {
+        subject: "Sign-ins to your Circulate account are paused".to_string(),
+        body: format!(
+            "Hi {},\n\nThere were too many failed attempts to sign in to your Circulate \
+             account, so sign-ins are paused for {} minutes.\n\nIf this was not you, \
+             consider changing your password: {}/forgot-password\n",
+            user_name, lockout_minutes, app_url
+        ),
+    }
+}
+
+pub fn new_login(
+    user_name: &str,
+    ip_address: Option<&str>,
+    user_agent: Option<&str>,
+    time: &DateTime<Utc>,
+    app_url: &str,
+) -> MailTemplate {
+    MailTemplate {
+        subject: "New sign-in to your Circulate account".to_string(),
+        body: format!(
+            "Hi {},\n\nYour Circulate account was just signed in to from a new device or \
+             location.\n\nIP address: {}\nDevice: {}\nTime: {}\n\nIf this was not you, sign \
+             out your sessions and reset your password: {}/forgot-password\n",
+            user_name,
+            ip_address.unwrap_or("unknown"),
+            user_agent.unwrap_or("unknown"),
+            format_date(time),
+            app_url
+        ),
+    }
+}
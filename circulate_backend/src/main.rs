@@ -3,34 +3,56 @@ mod db;
 mod dtos;
 mod error;
 mod handler;
+mod mail;
 mod middleware;
 mod models;
+mod oidc;
+mod ratelimit;
+mod realtime;
 mod router;
 mod utils;
+mod webhook;
 
-use axum::{
-    http::{
-        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-        HeaderValue, Method,
-    },
-    Router,
+use axum::http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    HeaderValue, Method,
 };
 use config::Config;
-use db::{DBClient, UserExt};
+use db::{
+    AccessTokenExt, DBClient, LoginSecurityExt, NotifyExt, OidcExt, PasswordResetExt, RateLimitExt,
+    SessionExt, TwoFactorExt, UserExt,
+};
 use dotenv::dotenv;
+use mail::{build_mailer, notify::notify_expiring_shares, Mailer};
+use oidc::cache::OidcCache;
+use ratelimit::{build_rate_limiter, RateLimiter};
+use realtime::{inbox_channel, spawn_inbox_listener, InboxNotification};
 //use router::create_router;
 use sqlx::postgres::PgPoolOptions;
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::sync::broadcast;
 use tokio_cron_scheduler::{Job, JobScheduler};
 use tower_http::cors::CorsLayer;
 use tracing_subscriber::filter::LevelFilter;
+use webhook::{delivery::spawn_delivery_worker, emit_share_event, WebhookEvent};
 
 use crate::router::create_router;
 
+/// How long ended sessions are kept before the cleanup job removes them.
+const SESSION_RETENTION_DAYS: i64 = 30;
+/// How long login events are kept for the user's login history.
+const LOGIN_EVENT_RETENTION_DAYS: i32 = 90;
+/// How long inbox events stay readable after they were announced.
+const INBOX_EVENT_RETENTION_HOURS: i32 = 1;
+
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub env: Config,
     pub db_client: DBClient,
+    pub mailer: Arc<dyn Mailer>,
+    pub inbox: broadcast::Sender<InboxNotification>,
+    pub rate_limiter: Arc<dyn RateLimiter>,
+    pub oidc_cache: Arc<OidcCache>,
 }
 
 #[tokio::main]
@@ -61,25 +83,121 @@ async fn main() {
         .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
         .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE])
         .allow_credentials(true)
-        .allow_methods([Method::GET, Method::POST, Method::PUT]);
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE]);
+
+    let mailer = match build_mailer(&config) {
+        Ok(mailer) => mailer,
+        Err(err) => {
+            println!("Failed to set up the mail transport: {}", err);
+            std::process::exit(1);
+        }
+    };
 
     let db_client = DBClient::new(pool);
-    let app_state = AppState {
-        env: config.clone(),
-        db_client: db_client.clone(),
+
+    let rate_limiter = match build_rate_limiter(&config, &db_client) {
+        Ok(rate_limiter) => rate_limiter,
+        Err(err) => {
+            println!("Failed to set up rate limiting: {}", err);
+            std::process::exit(1);
+        }
     };
 
+    let app_state = Arc::new(AppState {
+        env: config.clone(),
+        db_client,
+        mailer,
+        inbox: inbox_channel(),
+        rate_limiter,
+        oidc_cache: Arc::new(OidcCache::default()),
+    });
+
     let scheduler = JobScheduler::new().await.unwrap();
 
-    let job = Job::new_async("0 0 * * * *", {
+    let job = Job::new_async(config.cleanup_schedule.as_str(), {
+        let app_state = app_state.clone();
         move |_, _| {
-            let db_client = db_client.clone();
+            let app_state = app_state.clone();
             Box::pin(async move {
                 println!("Running scheduled task to delete expired files.. ");
-                if let Err(err) = db_client.delete_expired_files().await {
-                    eprintln!("Error deleting expired files: {:?}", err);
-                } else {
-                    println!("Successfully deleted expired files.");
+                // Announced when the share expires; the file itself may stay for
+                // the grace period before it is purged below.
+                match app_state.db_client.claim_newly_expired_shares().await {
+                    Ok(expired_shares) => {
+                        for share in &expired_shares {
+                            emit_share_event(&app_state, WebhookEvent::Expired, share).await;
+                        }
+                    }
+                    Err(err) => eprintln!("Error loading newly expired shares: {:?}", err),
+                }
+
+                match app_state
+                    .db_client
+                    .delete_expired_files(app_state.env.expired_grace_hours)
+                    .await
+                {
+                    Ok(_) => println!("Successfully deleted expired files."),
+                    Err(err) => eprintln!("Error deleting expired files: {:?}", err),
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_stale_sessions(SESSION_RETENTION_DAYS)
+                    .await
+                {
+                    eprintln!("Error deleting stale sessions: {:?}", err);
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_stale_password_reset_tokens()
+                    .await
+                {
+                    eprintln!("Error deleting stale password reset tokens: {:?}", err);
+                }
+
+                if let Err(err) = app_state.db_client.delete_expired_oidc_login_states().await {
+                    eprintln!("Error deleting expired OIDC login states: {:?}", err);
+                }
+
+                if let Err(err) = app_state.db_client.delete_expired_access_tokens().await {
+                    eprintln!("Error deleting expired access tokens: {:?}", err);
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_expired_two_factor_challenges()
+                    .await
+                {
+                    eprintln!("Error deleting expired 2FA challenges: {:?}", err);
+                }
+
+                if let Err(err) = app_state.db_client.delete_idle_rate_limit_buckets().await {
+                    eprintln!("Error deleting idle rate limit buckets: {:?}", err);
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_old_login_events(LOGIN_EVENT_RETENTION_DAYS)
+                    .await
+                {
+                    eprintln!("Error deleting old login events: {:?}", err);
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_stale_login_failures(app_state.env.login_lockout_minutes)
+                    .await
+                {
+                    eprintln!("Error deleting stale login failures: {:?}", err);
+                }
+
+                if let Err(err) = app_state
+                    .db_client
+                    .delete_old_inbox_events(INBOX_EVENT_RETENTION_HOURS)
+                    .await
+                {
+                    eprintln!("Error deleting old inbox events: {:?}", err);
                 }
             })
         }
@@ -88,20 +206,48 @@ async fn main() {
 
     scheduler.add(job).await.unwrap();
 
+    let expiry_job = Job::new_async(config.expiry_reminder_schedule.as_str(), {
+        let app_state = app_state.clone();
+        move |_, _| {
+            let app_state = app_state.clone();
+            Box::pin(async move {
+                println!("Running scheduled task to notify about expiring files.. ");
+                if let Err(err) = notify_expiring_shares(&app_state).await {
+                    eprintln!("Error sending expiry notifications: {:?}", err);
+                }
+            })
+        }
+    })
+    .unwrap();
+
+    scheduler.add(expiry_job).await.unwrap();
+
     tokio::spawn(async move {
         scheduler.start().await.unwrap();
     });
 
-    let app = create_router(Arc::new(app_state.clone())).layer(cors.clone());
+    spawn_delivery_worker(app_state.clone());
+    spawn_inbox_listener(app_state.clone());
+
+    let app = create_router(app_state.clone()).layer(cors.clone());
 
-    println!(
-        "{}",
-        format!("Server is running on http://localhost:{}", config.port)
-    );
+    println!("Server is running on http://localhost:{}", config.port);
+
+    if !config.trust_proxy_headers {
+        println!(
+            "TRUST_PROXY_HEADERS is off: clients are told apart by the connecting address \
+             only, so behind the bundled frontend every user shares one IP rate limit bucket."
+        );
+    }
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", &config.port))
         .await
         .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
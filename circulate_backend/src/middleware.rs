@@ -1,20 +1,85 @@
 use std::sync::Arc;
 
-use axum::{extract::Request, http::header, middleware::Next, response::IntoResponse, Extension};
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::IntoResponse,
+    Extension,
+};
 use axum_extra::extract::cookie::CookieJar;
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::UserExt,
+    db::{AccessTokenExt, SessionExt, UserExt},
     error::{ErrorMessage, HttpError},
-    models::User,
+    models::{AccessTokenScope, Permission, User},
     utils::token,
     AppState,
 };
 
+const SESSION_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// How a request was authenticated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Credential {
+    Session(uuid::Uuid),
+    AccessToken {
+        token_id: uuid::Uuid,
+        scopes: Vec<String>,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JWTAuthMiddleware {
     pub user: User,
+    pub credential: Credential,
+    /// What the user's role grants.
+    pub permissions: Vec<String>,
+}
+
+impl JWTAuthMiddleware {
+    /// The login session behind the request. Access tokens are refused, which keeps
+    /// account settings (password, sessions, 2FA, tokens) out of their reach.
+    pub fn session_id(&self) -> Result<uuid::Uuid, HttpError> {
+        match &self.credential {
+            Credential::Session(session_id) => Ok(*session_id),
+            Credential::AccessToken { .. } => Err(HttpError::forbidden(
+                ErrorMessage::SessionRequired.to_string(),
+            )),
+        }
+    }
+
+    /// Sessions can do everything the user can; access tokens only what their scopes
+    /// grant.
+    pub fn require_scope(&self, scope: AccessTokenScope) -> Result<(), HttpError> {
+        match &self.credential {
+            Credential::Session(_) => Ok(()),
+            Credential::AccessToken { scopes, .. }
+                if scopes.iter().any(|s| s == scope.to_str()) =>
+            {
+                Ok(())
+            }
+            Credential::AccessToken { .. } => Err(HttpError::forbidden(
+                ErrorMessage::MissingScope(scope.to_str()).to_string(),
+            )),
+        }
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.iter().any(|p| p == permission.to_str())
+    }
+
+    pub fn require_permission(&self, permission: Permission) -> Result<(), HttpError> {
+        if !self.has_permission(permission) {
+            return Err(HttpError::forbidden(
+                ErrorMessage::MissingPermission(permission.to_str()).to_string(),
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 pub async fn auth(
@@ -30,17 +95,38 @@ pub async fn auth(
             req.headers()
                 .get(header::AUTHORIZATION)
                 .and_then(|auth_header| auth_header.to_str().ok())
-                .and_then(|auth_value| {
-                    if auth_value.starts_with("Bearer ") {
-                        Some(auth_value[7..].to_owned())
-                    } else {
-                        None
-                    }
-                })
+                .and_then(|auth_value| auth_value.strip_prefix("Bearer "))
+                .map(|token| token.to_owned())
         });
     let token = cookies
         .ok_or_else(|| HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string()))?;
 
+    let (user_id, credential) = if token.starts_with(token::ACCESS_TOKEN_PREFIX) {
+        access_token_credential(&app_state, &token).await?
+    } else {
+        session_credential(&app_state, token).await?
+    };
+
+    let (user, permissions) = app_state
+        .db_client
+        .get_user_with_permissions(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    req.extensions_mut().insert(JWTAuthMiddleware {
+        user,
+        credential,
+        permissions,
+    });
+
+    Ok(next.run(req).await)
+}
+
+async fn session_credential(
+    app_state: &AppState,
+    token: String,
+) -> Result<(uuid::Uuid, Credential), HttpError> {
     let token_details = match token::decode_token(token, app_state.env.jwt_secret.as_bytes()) {
         Ok(token_details) => token_details,
         Err(_) => {
@@ -50,19 +136,84 @@ pub async fn auth(
         }
     };
 
-    let user_id = uuid::Uuid::parse_str(&token_details.to_string()).unwrap();
+    let user_id = uuid::Uuid::parse_str(&token_details.sub).unwrap();
+    let session_id = uuid::Uuid::parse_str(&token_details.sid)
+        .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
 
-    let user = app_state
+    let session = app_state
         .db_client
-        .get_user(Some(user_id), None, None)
+        .get_active_session(session_id, user_id)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::SessionRevoked.to_string()))?;
 
-    let user =
-        user.ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+    // "Last seen" only needs to be roughly right, so skip the write on most requests.
+    if session
+        .last_used_at
+        .is_none_or(|last_used_at| Utc::now() - last_used_at > SESSION_TOUCH_INTERVAL)
+    {
+        if let Err(e) = app_state.db_client.touch_session(session_id).await {
+            eprintln!("Failed to update session last use: {}", e);
+        }
+    }
+
+    Ok((user_id, Credential::Session(session_id)))
+}
+
+async fn access_token_credential(
+    app_state: &AppState,
+    token: &str,
+) -> Result<(uuid::Uuid, Credential), HttpError> {
+    let access_token = app_state
+        .db_client
+        .get_active_access_token(&token::hash_access_token(token))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    if access_token
+        .last_used_at
+        .is_none_or(|last_used_at| Utc::now() - last_used_at > SESSION_TOUCH_INTERVAL)
+    {
+        if let Err(e) = app_state
+            .db_client
+            .touch_access_token(access_token.id)
+            .await
+        {
+            eprintln!("Failed to update access token last use: {}", e);
+        }
+    }
 
-    req.extensions_mut()
-        .insert(JWTAuthMiddleware { user: user.clone() });
+    Ok((
+        access_token.user_id,
+        Credential::AccessToken {
+            token_id: access_token.id,
+            scopes: access_token.scopes,
+        },
+    ))
+}
+
+/// For routers that only make sense for someone logged in, such as account settings
+/// and administration.
+pub async fn require_session(
+    Extension(user): Extension<JWTAuthMiddleware>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, HttpError> {
+    user.session_id()?;
+
+    Ok(next.run(req).await)
+}
+
+/// Guards a router or route with a permission, e.g.
+/// `middleware::from_fn_with_state(Permission::StatsRead, require_permission)`.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    Extension(user): Extension<JWTAuthMiddleware>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, HttpError> {
+    user.require_permission(permission)?;
 
     Ok(next.run(req).await)
 }
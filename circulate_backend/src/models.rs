@@ -8,10 +8,19 @@ pub struct User {
     pub email: String,
     pub password: String,
     pub public_key: Option<String>,
+    pub role: String,
+    pub verified_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl User {
+    /// Whether the user has confirmed they own their email address.
+    pub fn is_verified(&self) -> bool {
+        self.verified_at.is_some()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, sqlx::Type)]
 pub struct File {
     pub id: uuid::Uuid,
@@ -32,22 +41,617 @@ pub struct SharedLink {
     pub password: String,
     pub expiration_date: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
+    pub failed_attempts: i32,
+    pub last_failed_at: Option<DateTime<Utc>>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub available_from: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct FileRequest {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub password: Option<String>,
+    pub deadline: DateTime<Utc>,
+    pub upload_count: i32,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FileRequest {
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none() && self.deadline > Utc::now()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SharePasswordFailure {
+    pub user_id: uuid::Uuid,
+    pub failed_attempts: i32,
+    pub last_failed_at: Option<DateTime<Utc>>,
+}
+
+/// Whether a password attempt on a share may go ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareAttemptClaim {
+    /// The attempt was counted as a failure up front; a correct password settles it.
+    Claimed,
+    /// Seconds until the backoff allows another attempt.
+    Throttled(u64),
+    Locked,
 }
 
 #[derive(sqlx::FromRow)]
 pub struct SendFileDetails {
     pub file_id: uuid::Uuid,
+    pub share_id: uuid::Uuid,
     pub file_name: String,
     pub recipient_email: String,
     pub expiration_date: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
 }
 
 #[derive(sqlx::FromRow)]
 pub struct ReceiveFileDetails {
+    pub share_id: uuid::Uuid,
+    pub file_id: uuid::Uuid,
+    pub file_name: String,
+    pub sender_email: String,
+    pub file_request_id: Option<uuid::Uuid>,
+    pub recipient_state: String,
+    pub available_from: Option<DateTime<Utc>>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Everything about one share that either party may see. Key material and the
+/// share password are deliberately left out.
+#[derive(sqlx::FromRow)]
+pub struct ShareDetails {
+    pub share_id: uuid::Uuid,
     pub file_id: uuid::Uuid,
     pub file_name: String,
+    pub file_size: i64,
+    pub sha256: Option<String>,
+    pub sender_email: String,
+    pub recipient_id: uuid::Uuid,
+    pub recipient_email: String,
+    pub file_request_id: Option<uuid::Uuid>,
+    pub recipient_state: String,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub available_from: Option<DateTime<Utc>>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ShareDetails {
+    /// Revoked wins over expired; downloads are reported separately.
+    pub fn status(&self) -> ShareStatus {
+        if self.revoked_at.is_some() {
+            ShareStatus::Revoked
+        } else if self
+            .expiration_date
+            .is_some_and(|expiration_date| expiration_date <= Utc::now())
+        {
+            ShareStatus::Expired
+        } else {
+            ShareStatus::Active
+        }
+    }
+}
+
+/// Where a share sits in the recipient's inbox. Declining is not a state: a
+/// declined share is deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecipientState {
+    New,
+    Opened,
+    Archived,
+}
+
+impl RecipientState {
+    pub const ALL: [&'static str; 3] = ["new", "opened", "archived"];
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            RecipientState::New => "new",
+            RecipientState::Opened => "opened",
+            RecipientState::Archived => "archived",
+        }
+    }
+
+    pub fn parse(state: &str) -> Option<Self> {
+        match state {
+            "new" => Some(RecipientState::New),
+            "opened" => Some(RecipientState::Opened),
+            "archived" => Some(RecipientState::Archived),
+            _ => None,
+        }
+    }
+}
+
+/// Share lifecycle status used to filter the send and receive lists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareStatus {
+    Active,
+    Expired,
+    Downloaded,
+    Revoked,
+}
+
+impl ShareStatus {
+    pub const ALL: [&'static str; 4] = ["active", "expired", "downloaded", "revoked"];
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            ShareStatus::Active => "active",
+            ShareStatus::Expired => "expired",
+            ShareStatus::Downloaded => "downloaded",
+            ShareStatus::Revoked => "revoked",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "active" => Some(ShareStatus::Active),
+            "expired" => Some(ShareStatus::Expired),
+            "downloaded" => Some(ShareStatus::Downloaded),
+            "revoked" => Some(ShareStatus::Revoked),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareSortField {
+    CreatedAt,
+    ExpirationDate,
+    FileName,
+    FileSize,
+    Email,
+}
+
+impl ShareSortField {
+    pub const ALL: [&'static str; 5] = [
+        "created_at",
+        "expiration_date",
+        "file_name",
+        "file_size",
+        "email",
+    ];
+
+    pub fn parse(field: &str) -> Option<Self> {
+        match field {
+            "created_at" => Some(ShareSortField::CreatedAt),
+            "expiration_date" => Some(ShareSortField::ExpirationDate),
+            "file_name" => Some(ShareSortField::FileName),
+            "file_size" => Some(ShareSortField::FileSize),
+            "email" => Some(ShareSortField::Email),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareAccessOutcome {
+    Success,
+    WrongPassword,
+    Expired,
+    NotFound,
+    NotAvailableYet,
+    Throttled,
+    Locked,
+    Revoked,
+}
+
+impl ShareAccessOutcome {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ShareAccessOutcome::Success => "success",
+            ShareAccessOutcome::WrongPassword => "wrong_password",
+            ShareAccessOutcome::Expired => "expired",
+            ShareAccessOutcome::NotFound => "not_found",
+            ShareAccessOutcome::NotAvailableYet => "not_available_yet",
+            ShareAccessOutcome::Throttled => "throttled",
+            ShareAccessOutcome::Locked => "locked",
+            ShareAccessOutcome::Revoked => "revoked",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ShareAccessLog {
+    pub id: uuid::Uuid,
+    pub shared_link_id: Option<uuid::Uuid>,
+    pub user_id: Option<uuid::Uuid>,
+    pub outcome: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct NotificationPreferences {
+    pub user_id: uuid::Uuid,
+    pub file_received: bool,
+    pub file_downloaded: bool,
+    pub share_expiring: bool,
+    pub share_revoked: bool,
+    pub share_declined: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct ExpiringShareDetails {
+    pub share_id: uuid::Uuid,
+    pub file_name: String,
+    pub expiration_date: DateTime<Utc>,
+    pub recipient_user_id: uuid::Uuid,
+    pub recipient_name: String,
+    pub recipient_email: String,
     pub sender_email: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct ShareEventDetails {
+    pub share_id: uuid::Uuid,
+    pub file_id: uuid::Uuid,
+    pub file_name: String,
+    pub file_size: i64,
+    pub sender_id: Option<uuid::Uuid>,
+    pub sender_email: Option<String>,
+    pub recipient_id: uuid::Uuid,
+    pub recipient_email: String,
+    pub expiration_date: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct WebhookEndpoint {
+    pub id: uuid::Uuid,
+    pub user_id: Option<uuid::Uuid>,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    pub id: uuid::Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct WebhookDeliveryAttempt {
+    pub id: uuid::Uuid,
+    pub delivery_id: uuid::Uuid,
+    pub event_id: uuid::Uuid,
+    pub event_type: String,
+    pub delivery_status: String,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Width of the buckets in a statistics time series.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl StatsBucket {
+    pub const ALL: [&'static str; 3] = ["day", "week", "month"];
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            StatsBucket::Day => "day",
+            StatsBucket::Week => "week",
+            StatsBucket::Month => "month",
+        }
+    }
+
+    pub fn parse(bucket: &str) -> Option<Self> {
+        match bucket {
+            "day" => Some(StatsBucket::Day),
+            "week" => Some(StatsBucket::Week),
+            "month" => Some(StatsBucket::Month),
+            _ => None,
+        }
+    }
+
+    /// Rough length, used to cap how many buckets one request can ask for.
+    pub fn approx_days(&self) -> i64 {
+        match self {
+            StatsBucket::Day => 1,
+            StatsBucket::Week => 7,
+            StatsBucket::Month => 30,
+        }
+    }
+}
+
+/// Totals over a time range. Lifetime and download rate cover sent shares only.
+#[derive(sqlx::FromRow)]
+pub struct TransferStats {
+    pub files_sent: i64,
+    pub bytes_sent: i64,
+    pub files_received: i64,
+    pub bytes_received: i64,
+    pub unique_counterparts: i64,
+    pub avg_lifetime_seconds: Option<f64>,
+    pub download_rate: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct TransferStatsBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub files_sent: i64,
+    pub bytes_sent: i64,
+    pub files_received: i64,
+    pub bytes_received: i64,
+}
+
+/// Which side of a share the user is on, for history exports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareDirection {
+    Sent,
+    Received,
+}
+
+impl ShareDirection {
+    pub fn to_str(&self) -> &str {
+        match self {
+            ShareDirection::Sent => "sent",
+            ShareDirection::Received => "received",
+        }
+    }
+}
+
+/// One row of a send or receive history export.
+#[derive(sqlx::FromRow)]
+pub struct ShareHistoryRow {
+    pub share_id: uuid::Uuid,
+    pub file_id: uuid::Uuid,
+    pub file_name: String,
+    pub file_size: i64,
+    pub sha256: Option<String>,
+    pub counterpart_email: String,
+    pub status: String,
+    pub recipient_state: String,
+    pub download_count: i32,
+    pub downloaded_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub available_from: Option<DateTime<Utc>>,
     pub expiration_date: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Result of presenting a refresh token.
+pub enum RefreshRotation {
+    /// The token was current; a new one replaces it.
+    Rotated(Session),
+    /// The token had already been used, so it has leaked. Its session is revoked.
+    Reused,
+    /// Unknown token, or its session is revoked or expired.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionRevokeReason {
+    Logout,
+    RefreshTokenReused,
+    RevokedByUser,
+    PasswordChanged,
+    TwoFactorEnabled,
+    PasswordReset,
+}
+
+impl SessionRevokeReason {
+    pub fn to_str(&self) -> &str {
+        match self {
+            SessionRevokeReason::Logout => "logout",
+            SessionRevokeReason::RefreshTokenReused => "refresh_token_reused",
+            SessionRevokeReason::RevokedByUser => "revoked_by_user",
+            SessionRevokeReason::PasswordChanged => "password_changed",
+            SessionRevokeReason::TwoFactorEnabled => "two_factor_enabled",
+            SessionRevokeReason::PasswordReset => "password_reset",
+        }
+    }
+}
+
+/// 2FA settings of a user. A secret without `enabled_at` is an enrollment that has
+/// not been confirmed yet.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TwoFactor {
+    pub user_id: uuid::Uuid,
+    pub totp_secret: Option<String>,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub required: bool,
+}
+
+impl TwoFactor {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some() && self.totp_secret.is_some()
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OidcLoginState {
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// What a personal access token may be used for. Tokens never reach account
+/// settings such as sessions, 2FA or other tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AccessTokenScope {
+    FilesSend,
+    FilesReceive,
+    UsersRead,
+}
+
+impl AccessTokenScope {
+    pub const ALL: [&'static str; 3] = ["files:send", "files:receive", "users:read"];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AccessTokenScope::FilesSend => "files:send",
+            AccessTokenScope::FilesReceive => "files:receive",
+            AccessTokenScope::UsersRead => "users:read",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "files:send" => Some(AccessTokenScope::FilesSend),
+            "files:receive" => Some(AccessTokenScope::FilesReceive),
+            "users:read" => Some(AccessTokenScope::UsersRead),
+            _ => None,
+        }
+    }
+}
+
+/// What a role allows beyond what every user can do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Require 2FA for users and lift login lockouts.
+    UsersManage,
+    /// Create, change and assign roles.
+    RolesManage,
+    /// Organisation-wide webhooks.
+    WebhooksManage,
+    /// Organisation-wide transfer statistics.
+    StatsRead,
+}
+
+impl Permission {
+    pub const ALL: [&'static str; 4] = [
+        "users:manage",
+        "roles:manage",
+        "webhooks:manage",
+        "stats:read",
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Permission::UsersManage => "users:manage",
+            Permission::RolesManage => "roles:manage",
+            Permission::WebhooksManage => "webhooks:manage",
+            Permission::StatsRead => "stats:read",
+        }
+    }
+
+    pub fn parse(permission: &str) -> Option<Self> {
+        match permission {
+            "users:manage" => Some(Permission::UsersManage),
+            "roles:manage" => Some(Permission::RolesManage),
+            "webhooks:manage" => Some(Permission::WebhooksManage),
+            "stats:read" => Some(Permission::StatsRead),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Role {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    /// `user` and `admin`, which ship with Circulate and cannot be changed.
+    pub built_in: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Role {
+    /// The built-in role with every permission.
+    pub const ADMIN: &'static str = "admin";
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccessToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginOutcome {
+    Success,
+    WrongPassword,
+    UnknownEmail,
+    InvalidTwoFactorCode,
+    /// Refused without checking the password because the email is locked.
+    Locked,
+    /// Refused because the client's IP failed too often recently.
+    IpThrottled,
+}
+
+impl LoginOutcome {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            LoginOutcome::Success => "success",
+            LoginOutcome::WrongPassword => "wrong_password",
+            LoginOutcome::UnknownEmail => "unknown_email",
+            LoginOutcome::InvalidTwoFactorCode => "invalid_two_factor_code",
+            LoginOutcome::Locked => "locked",
+            LoginOutcome::IpThrottled => "ip_throttled",
+        }
+    }
+}
+
+/// Whether a login attempt for an email may go ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginAttemptClaim {
+    /// The attempt was counted as a failure up front; a correct password or code
+    /// settles it. `locks` when it took the last attempt, so failing it locks the email.
+    Claimed { locks: bool },
+    /// Seconds until the email's lock runs out.
+    Locked(u64),
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LoginEvent {
+    pub id: uuid::Uuid,
+    pub outcome: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Whether a successful login comes from somewhere the user has signed in from before.
+#[derive(Debug, Clone)]
+pub struct KnownLogin {
+    pub has_previous_login: bool,
+    pub ip_address_seen: bool,
+    pub user_agent_seen: bool,
+}
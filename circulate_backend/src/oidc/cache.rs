@@ -0,0 +1,77 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::jwk::JwkSet;
+
+use super::client::ProviderMetadata;
+
+/// How long a discovery document or key set is reused before it is fetched again.
+const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug)]
+struct Entry<T> {
+    value: Arc<T>,
+    fetched_at: Instant,
+}
+
+/// Discovery documents by issuer and signing keys by JWKS URL, so logins do not
+/// fetch both from the provider every time. Keys are also refetched early when an
+/// ID token names a key the cached set does not have, which covers key rotation.
+#[derive(Debug, Default)]
+pub struct OidcCache {
+    metadata: Mutex<HashMap<String, Entry<ProviderMetadata>>>,
+    jwks: Mutex<HashMap<String, Entry<JwkSet>>>,
+}
+
+impl OidcCache {
+    pub fn metadata(&self, issuer: &str) -> Option<Arc<ProviderMetadata>> {
+        fresh(&self.metadata, issuer)
+    }
+
+    pub fn store_metadata(
+        &self,
+        issuer: &str,
+        metadata: ProviderMetadata,
+    ) -> Arc<ProviderMetadata> {
+        store(&self.metadata, issuer, metadata)
+    }
+
+    pub fn jwks(&self, jwks_uri: &str) -> Option<Arc<JwkSet>> {
+        fresh(&self.jwks, jwks_uri)
+    }
+
+    pub fn store_jwks(&self, jwks_uri: &str, jwks: JwkSet) -> Arc<JwkSet> {
+        store(&self.jwks, jwks_uri, jwks)
+    }
+}
+
+fn fresh<T>(entries: &Mutex<HashMap<String, Entry<T>>>, key: &str) -> Option<Arc<T>> {
+    let entries = entries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    entries
+        .get(key)
+        .filter(|entry| entry.fetched_at.elapsed() < CACHE_TTL)
+        .map(|entry| entry.value.clone())
+}
+
+fn store<T>(entries: &Mutex<HashMap<String, Entry<T>>>, key: &str, value: T) -> Arc<T> {
+    let value = Arc::new(value);
+    let mut entries = entries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    entries.insert(
+        key.to_string(),
+        Entry {
+            value: value.clone(),
+            fetched_at: Instant::now(),
+        },
+    );
+
+    value
+}
@@ -0,0 +1,513 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{cache::OidcCache, OidcError, OidcProvider};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The parts of the discovery document the login flow needs.
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+/// Verified identity from the provider's ID token.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    /// Some providers send this as the string `"true"`.
+    #[serde(default)]
+    email_verified: Option<Value>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    nonce: Option<String>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+impl IdTokenClaims {
+    pub fn email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(Value::Bool(verified)) => *verified,
+            Some(Value::String(verified)) => verified == "true",
+            _ => false,
+        }
+    }
+
+    /// Group names from `claim`, which may be a list or a single string.
+    pub fn groups(&self, claim: &str) -> Vec<String> {
+        match self.extra.get(claim) {
+            Some(Value::Array(groups)) => groups
+                .iter()
+                .filter_map(|group| group.as_str().map(|group| group.to_string()))
+                .collect(),
+            Some(Value::String(group)) => vec![group.to_string()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, OidcError> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| OidcError(e.to_string()))
+}
+
+/// Random value for `state` and `nonce`.
+pub fn generate_state() -> String {
+    URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// PKCE verifier (RFC 7636): 43 characters from the unreserved set.
+pub fn generate_code_verifier() -> String {
+    URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+pub async fn discover(
+    cache: &OidcCache,
+    provider: &OidcProvider,
+) -> Result<Arc<ProviderMetadata>, OidcError> {
+    if let Some(metadata) = cache.metadata(&provider.issuer) {
+        return Ok(metadata);
+    }
+
+    let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+
+    let metadata: ProviderMetadata = http_client()?
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| OidcError(format!("discovery failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| OidcError(format!("invalid discovery document: {}", e)))?;
+
+    if metadata.issuer.trim_end_matches('/') != provider.issuer {
+        return Err(OidcError(format!(
+            "discovery document is for issuer {}",
+            metadata.issuer
+        )));
+    }
+
+    Ok(cache.store_metadata(&provider.issuer, metadata))
+}
+
+pub fn authorization_url(
+    metadata: &ProviderMetadata,
+    provider: &OidcProvider,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+) -> Result<String, OidcError> {
+    let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| OidcError(format!("invalid authorization endpoint: {}", e)))?;
+
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &provider.client_id)
+        .append_pair("redirect_uri", &provider.redirect_uri)
+        .append_pair("scope", &provider.scopes.join(" "))
+        .append_pair("state", state)
+        .append_pair("nonce", nonce)
+        .append_pair("code_challenge", &code_challenge(code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    Ok(url.to_string())
+}
+
+/// Redeems the authorization code and returns the verified ID token claims.
+pub async fn exchange_code(
+    cache: &OidcCache,
+    metadata: &ProviderMetadata,
+    provider: &OidcProvider,
+    code: &str,
+    code_verifier: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let client = http_client()?;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", provider.redirect_uri.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if let Some(client_secret) = &provider.client_secret {
+        form.push(("client_secret", client_secret.as_str()));
+    }
+
+    let response = client
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| OidcError(format!("token request failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(OidcError(format!(
+            "token endpoint answered {}: {}",
+            status, body
+        )));
+    }
+
+    let tokens: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| OidcError(format!("invalid token response: {}", e)))?;
+
+    let id_token = tokens
+        .id_token
+        .ok_or_else(|| OidcError("token response has no id_token".to_string()))?;
+
+    verify_id_token(cache, &client, metadata, provider, &id_token, nonce).await
+}
+
+async fn fetch_jwks(
+    cache: &OidcCache,
+    client: &reqwest::Client,
+    jwks_uri: &str,
+) -> Result<Arc<JwkSet>, OidcError> {
+    let jwks: JwkSet = client
+        .get(jwks_uri)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| OidcError(format!("JWKS request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| OidcError(format!("invalid JWKS: {}", e)))?;
+
+    Ok(cache.store_jwks(jwks_uri, jwks))
+}
+
+fn find_jwk<'a>(jwks: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+}
+
+async fn verify_id_token(
+    cache: &OidcCache,
+    client: &reqwest::Client,
+    metadata: &ProviderMetadata,
+    provider: &OidcProvider,
+    id_token: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let header = decode_header(id_token).map_err(|e| OidcError(e.to_string()))?;
+
+    // ID tokens are checked against published keys, which only makes sense for
+    // asymmetric signatures.
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(OidcError(
+            "ID tokens signed with a shared secret are not supported".to_string(),
+        ));
+    }
+
+    let kid = header.kid.as_deref();
+
+    // A key missing from the cached set may have been rotated in since; fetch once more.
+    let jwks = match cache.jwks(&metadata.jwks_uri) {
+        Some(jwks) if find_jwk(&jwks, kid).is_some() => jwks,
+        _ => fetch_jwks(cache, client, &metadata.jwks_uri).await?,
+    };
+
+    let jwk = find_jwk(&jwks, kid)
+        .ok_or_else(|| OidcError("no signing key matches the ID token".to_string()))?;
+
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| OidcError(e.to_string()))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&metadata.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| OidcError(format!("invalid ID token: {}", e)))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(OidcError("ID token nonce does not match".to_string()));
+    }
+
+    Ok(claims)
+}
+
+/// Runs the login flow against a small in-process identity provider: discovery, the
+/// token endpoint and the key set of a throwaway RSA key.
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    };
+
+    use axum::{extract::State, routing::get, routing::post, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use rsa::{pkcs1::EncodeRsaPrivateKey, traits::PublicKeyParts, RsaPrivateKey};
+    use serde_json::json;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "circulate";
+
+    /// PEM of the signing key and its public half as a JWK without a `kid`.
+    fn signing_key() -> &'static (String, Value) {
+        static KEY: OnceLock<(String, Value)> = OnceLock::new();
+
+        KEY.get_or_init(|| {
+            let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+            let pem = key
+                .to_pkcs1_pem(rsa::pkcs1::LineEnding::LF)
+                .unwrap()
+                .to_string();
+            let jwk = json!({
+                "kty": "RSA",
+                "alg": "RS256",
+                "use": "sig",
+                "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            });
+            (pem, jwk)
+        })
+    }
+
+    #[derive(Default)]
+    struct MockIdp {
+        issuer: Mutex<String>,
+        /// `kid` the key set publishes the signing key under.
+        published_kid: Mutex<String>,
+        /// What the token endpoint hands out next.
+        id_token: Mutex<String>,
+        discovery_requests: AtomicUsize,
+        jwks_requests: AtomicUsize,
+    }
+
+    impl MockIdp {
+        async fn start() -> (Arc<MockIdp>, OidcProvider) {
+            let idp = Arc::new(MockIdp::default());
+            *idp.published_kid.lock().unwrap() = "key-1".to_string();
+
+            let router = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/keys", get(keys))
+                .route("/token", post(token))
+                .with_state(idp.clone());
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            *idp.issuer.lock().unwrap() = issuer.clone();
+            tokio::spawn(async move { axum::serve(listener, router).await });
+
+            let provider = OidcProvider {
+                name: "mock".to_string(),
+                display_name: "Mock".to_string(),
+                issuer,
+                client_id: CLIENT_ID.to_string(),
+                client_secret: Some("secret".to_string()),
+                redirect_uri: "http://localhost:3000/oidc/callback/mock".to_string(),
+                scopes: vec!["openid".to_string()],
+                groups_claim: "groups".to_string(),
+                role_mappings: Vec::new(),
+            };
+
+            (idp, provider)
+        }
+
+        /// Signs `claims` over the standard ones and serves them from the token endpoint.
+        fn issue(&self, kid: &str, claims: Value) {
+            let now = chrono::Utc::now().timestamp();
+            let mut token_claims = json!({
+                "iss": *self.issuer.lock().unwrap(),
+                "aud": CLIENT_ID,
+                "sub": "user-1",
+                "iat": now,
+                "exp": now + 300,
+            });
+            for (name, value) in claims.as_object().unwrap() {
+                token_claims[name] = value.clone();
+            }
+
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some(kid.to_string());
+            let key = EncodingKey::from_rsa_pem(signing_key().0.as_bytes()).unwrap();
+
+            *self.id_token.lock().unwrap() = encode(&header, &token_claims, &key).unwrap();
+        }
+    }
+
+    async fn discovery(State(idp): State<Arc<MockIdp>>) -> Json<Value> {
+        idp.discovery_requests.fetch_add(1, Ordering::SeqCst);
+        let issuer = idp.issuer.lock().unwrap().clone();
+
+        Json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/auth", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/keys", issuer),
+        }))
+    }
+
+    async fn keys(State(idp): State<Arc<MockIdp>>) -> Json<Value> {
+        idp.jwks_requests.fetch_add(1, Ordering::SeqCst);
+        let mut jwk = signing_key().1.clone();
+        jwk["kid"] = json!(*idp.published_kid.lock().unwrap());
+
+        Json(json!({ "keys": [jwk] }))
+    }
+
+    async fn token(State(idp): State<Arc<MockIdp>>) -> Json<Value> {
+        Json(json!({
+            "access_token": "access",
+            "token_type": "Bearer",
+            "id_token": *idp.id_token.lock().unwrap(),
+        }))
+    }
+
+    async fn login(
+        cache: &OidcCache,
+        provider: &OidcProvider,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let metadata = discover(cache, provider).await?;
+        exchange_code(cache, &metadata, provider, "code", "verifier", nonce).await
+    }
+
+    #[tokio::test]
+    async fn login_returns_verified_claims() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        idp.issue(
+            "key-1",
+            json!({
+                "nonce": "n-1",
+                "email": "alice@example.com",
+                "email_verified": "true",
+                "groups": ["eng", "admins"],
+            }),
+        );
+
+        let claims = login(&cache, &provider, "n-1").await.unwrap();
+
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+        assert!(claims.email_verified());
+        assert_eq!(claims.groups("groups"), vec!["eng", "admins"]);
+    }
+
+    #[tokio::test]
+    async fn discovery_and_keys_are_cached() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+
+        for nonce in ["n-1", "n-2", "n-3"] {
+            idp.issue("key-1", json!({ "nonce": nonce }));
+            login(&cache, &provider, nonce).await.unwrap();
+        }
+
+        assert_eq!(idp.discovery_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(idp.jwks_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn rotated_keys_are_refetched() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        idp.issue("key-1", json!({ "nonce": "n-1" }));
+        login(&cache, &provider, "n-1").await.unwrap();
+
+        *idp.published_kid.lock().unwrap() = "key-2".to_string();
+        idp.issue("key-2", json!({ "nonce": "n-2" }));
+        login(&cache, &provider, "n-2").await.unwrap();
+
+        assert_eq!(idp.jwks_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_token_for_another_login() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        idp.issue("key-1", json!({ "nonce": "someone-else" }));
+
+        assert!(login(&cache, &provider, "n-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_token_for_another_client() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        idp.issue("key-1", json!({ "nonce": "n-1", "aud": "other-client" }));
+
+        assert!(login(&cache, &provider, "n-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_an_unknown_signing_key() {
+        let (idp, provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        idp.issue("key-9", json!({ "nonce": "n-1" }));
+
+        assert!(login(&cache, &provider, "n-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_discovery_document_for_another_issuer() {
+        let (_idp, mut provider) = MockIdp::start().await;
+        let cache = OidcCache::default();
+        provider.issuer = provider.issuer.replace("127.0.0.1", "localhost");
+
+        assert!(discover(&cache, &provider).await.is_err());
+    }
+
+    #[test]
+    fn unverified_emails_are_not_trusted() {
+        for (email_verified, verified) in [
+            (json!(true), true),
+            (json!("true"), true),
+            (json!(false), false),
+            (json!("yes"), false),
+            (Value::Null, false),
+        ] {
+            let claims: IdTokenClaims = serde_json::from_value(json!({
+                "sub": "user-1",
+                "email": "alice@example.com",
+                "email_verified": email_verified,
+            }))
+            .unwrap();
+
+            assert_eq!(claims.email_verified(), verified);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+pub mod cache;
+pub mod client;
+
+use std::fmt;
+
+/// An OpenID Connect identity provider users can sign in with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcProvider {
+    /// Short name used in URLs, e.g. `/api/auth/oidc/dex/authorize`.
+    pub name: String,
+    pub display_name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Where the provider sends the browser back to; normally a frontend page that
+    /// hands `code` and `state` to the callback endpoint.
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// ID token claim holding the user's groups.
+    pub groups_claim: String,
+    /// `(group, role)` pairs, checked in order. Users in none of the groups keep their
+    /// role; roles that do not exist in the `roles` table are skipped.
+    pub role_mappings: Vec<(String, String)>,
+}
+
+impl OidcProvider {
+    /// Reads the providers listed in `OIDC_PROVIDERS` (comma separated). Each one is
+    /// configured with `OIDC_<NAME>_ISSUER` and `OIDC_<NAME>_CLIENT_ID`, and optionally
+    /// `_CLIENT_SECRET`, `_DISPLAY_NAME`, `_SCOPES`, `_REDIRECT_URI`, `_GROUPS_CLAIM` and
+    /// `_ROLE_MAP` (`group=role,group=role`). Incomplete providers are skipped.
+    pub fn from_env(app_url: &str) -> Vec<OidcProvider> {
+        let names = std::env::var("OIDC_PROVIDERS").unwrap_or_default();
+
+        names
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let env = |key: &str| {
+                    std::env::var(format!("OIDC_{}_{}", name.to_uppercase(), key))
+                        .ok()
+                        .filter(|value| !value.trim().is_empty())
+                };
+
+                let (Some(issuer), Some(client_id)) = (env("ISSUER"), env("CLIENT_ID")) else {
+                    eprintln!(
+                        "OIDC provider {} needs an issuer and a client id; skipping it",
+                        name
+                    );
+                    return None;
+                };
+
+                let role_mappings = env("ROLE_MAP")
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|mapping| mapping.split_once('='))
+                    .map(|(group, role)| (group.trim().to_string(), role.trim().to_lowercase()))
+                    .filter(|(group, role)| !group.is_empty() && !role.is_empty())
+                    .collect();
+
+                Some(OidcProvider {
+                    display_name: env("DISPLAY_NAME").unwrap_or_else(|| name.clone()),
+                    issuer: issuer.trim_end_matches('/').to_string(),
+                    client_id,
+                    client_secret: env("CLIENT_SECRET"),
+                    redirect_uri: env("REDIRECT_URI").unwrap_or_else(|| {
+                        format!("{}/oidc/callback/{}", app_url.trim_end_matches('/'), name)
+                    }),
+                    scopes: env("SCOPES")
+                        .unwrap_or_else(|| "openid email profile".to_string())
+                        .split_whitespace()
+                        .map(|scope| scope.to_string())
+                        .collect(),
+                    groups_claim: env("GROUPS_CLAIM").unwrap_or_else(|| "groups".to_string()),
+                    role_mappings,
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    /// Role for a user in `groups`, by the first mapping that matches. `None` when
+    /// nothing matches, so roles set in Circulate are kept.
+    pub fn role_for_groups(&self, groups: &[String]) -> Option<&str> {
+        self.role_mappings
+            .iter()
+            .find(|(group, _)| groups.contains(group))
+            .map(|(_, role)| role.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct OidcError(pub String);
+
+impl fmt::Display for OidcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OidcError: {}", self.0)
+    }
+}
+
+impl std::error::Error for OidcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(role_mappings: &[(&str, &str)]) -> OidcProvider {
+        OidcProvider {
+            name: "mock".to_string(),
+            display_name: "Mock".to_string(),
+            issuer: "https://idp.example.com".to_string(),
+            client_id: "circulate".to_string(),
+            client_secret: None,
+            redirect_uri: "https://circulate.example.com/oidc/callback/mock".to_string(),
+            scopes: vec!["openid".to_string()],
+            groups_claim: "groups".to_string(),
+            role_mappings: role_mappings
+                .iter()
+                .map(|(group, role)| (group.to_string(), role.to_string()))
+                .collect(),
+        }
+    }
+
+    fn groups(groups: &[&str]) -> Vec<String> {
+        groups.iter().map(|group| group.to_string()).collect()
+    }
+
+    #[test]
+    fn first_matching_mapping_wins() {
+        let provider = provider(&[("admins", "admin"), ("eng", "engineer")]);
+
+        assert_eq!(
+            provider.role_for_groups(&groups(&["eng", "admins"])),
+            Some("admin")
+        );
+        assert_eq!(
+            provider.role_for_groups(&groups(&["eng"])),
+            Some("engineer")
+        );
+    }
+
+    #[test]
+    fn no_match_keeps_the_role() {
+        let provider = provider(&[("admins", "admin")]);
+
+        assert_eq!(provider.role_for_groups(&groups(&["eng"])), None);
+        assert_eq!(provider.role_for_groups(&[]), None);
+    }
+
+    #[test]
+    fn no_mappings_keep_the_role() {
+        assert_eq!(provider(&[]).role_for_groups(&groups(&["admins"])), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use super::{RateLimitGroup, RateLimitKey};
+use crate::{error::HttpError, middleware::JWTAuthMiddleware, utils::client::ClientInfo, AppState};
+
+const RATE_LIMITED_MESSAGE: &str = "Too many requests, please try again later";
+
+/// Throttles the wrapped routes with the policy configured for `group`. Keys on the
+/// user need the layer to sit inside `auth`, e.g. on a route of an authenticated
+/// router.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitLayer {
+    group: RateLimitGroup,
+}
+
+impl RateLimitLayer {
+    pub fn new(group: RateLimitGroup) -> Self {
+        RateLimitLayer { group }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            group: self.group,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    group: RateLimitGroup,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // The clone may not be ready, so keep the one that was polled.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        // Work out the buckets up front; the request body cannot be shared across
+        // the await.
+        let app_state = req.extensions().get::<Arc<AppState>>().cloned();
+        let keys = app_state
+            .as_ref()
+            .and_then(|app_state| app_state.env.rate_limit_policy(self.group))
+            .map(|policy| bucket_keys(self.group, policy.key, &req))
+            .unwrap_or_default();
+        let group = self.group;
+
+        Box::pin(async move {
+            if let Some(app_state) = app_state {
+                if let Err(error) = check(&app_state, group, keys).await {
+                    return Ok(error.into_response());
+                }
+            }
+
+            inner.call(req).await
+        })
+    }
+}
+
+/// Buckets the request draws from under the group's policy.
+fn bucket_keys(group: RateLimitGroup, key: RateLimitKey, req: &Request) -> Vec<String> {
+    let trusted_proxies = req
+        .extensions()
+        .get::<Arc<AppState>>()
+        .map_or(0, |app_state| app_state.env.trusted_proxies());
+
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+
+    // Requests with no known address share one bucket rather than going unlimited.
+    let ip = ClientInfo::from_parts(req.headers(), peer, trusted_proxies)
+        .ip_address
+        .unwrap_or_else(|| "unknown".to_string());
+    let ip_key = format!("{}:ip:{}", group.to_str(), ip);
+    let user_key = req
+        .extensions()
+        .get::<JWTAuthMiddleware>()
+        .map(|user| format!("{}:user:{}", group.to_str(), user.user.id));
+
+    match key {
+        RateLimitKey::Ip => vec![ip_key],
+        RateLimitKey::User => vec![user_key.unwrap_or(ip_key)],
+        RateLimitKey::IpAndUser => std::iter::once(ip_key).chain(user_key).collect(),
+    }
+}
+
+async fn check(
+    app_state: &AppState,
+    group: RateLimitGroup,
+    keys: Vec<String>,
+) -> Result<(), HttpError> {
+    let Some(policy) = app_state.env.rate_limit_policy(group) else {
+        return Ok(());
+    };
+
+    for key in keys {
+        match app_state.rate_limiter.acquire(&key, policy).await {
+            Ok(None) => {}
+            Ok(Some(retry_after)) => {
+                return Err(HttpError::too_many_requests(
+                    RATE_LIMITED_MESSAGE,
+                    retry_after,
+                ))
+            }
+            // A broken limiter should not take the API down with it.
+            Err(e) => eprintln!("Rate limiter failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{RateLimitError, RateLimitPolicy, RateLimiter};
+
+/// Buckets kept before idle ones are swept out.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// A bucket left alone this long is full again, the same as having none.
+    idle_after: Duration,
+}
+
+/// Buckets in this process only. Each instance of a multi-instance deployment
+/// would allow the full rate on its own.
+#[derive(Debug, Default)]
+pub struct MemoryRateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimiter for MemoryRateLimiter {
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<Option<u64>, RateLimitError> {
+        let now = Instant::now();
+        let capacity = policy.capacity as f64;
+        let refill_per_second = policy.refill_per_second();
+
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|e| RateLimitError(e.to_string()))?;
+
+        if buckets.len() >= SWEEP_THRESHOLD {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated_at) < bucket.idle_after);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            idle_after: Duration::from_secs(policy.period_seconds as u64),
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(None);
+        }
+
+        Ok(Some(
+            ((1.0 - bucket.tokens) / refill_per_second).ceil().max(1.0) as u64,
+        ))
+    }
+}
@@ -0,0 +1,185 @@
+pub mod layer;
+pub mod memory;
+pub mod postgres;
+
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{config::Config, db::DBClient, utils::expiry::parse_duration};
+use memory::MemoryRateLimiter;
+use postgres::PostgresRateLimiter;
+
+/// Routes that share a policy. Each group has its own buckets, so being throttled on
+/// login does not block downloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitGroup {
+    /// Login, registration and the other unauthenticated account endpoints.
+    Auth,
+    /// Recipient email search.
+    Search,
+    /// Downloading shared files.
+    Retrieve,
+    /// Anonymous uploads through a file request link.
+    RequestUpload,
+}
+
+impl RateLimitGroup {
+    pub const ALL: [RateLimitGroup; 4] = [
+        RateLimitGroup::Auth,
+        RateLimitGroup::Search,
+        RateLimitGroup::Retrieve,
+        RateLimitGroup::RequestUpload,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            RateLimitGroup::Auth => "auth",
+            RateLimitGroup::Search => "search",
+            RateLimitGroup::Retrieve => "retrieve",
+            RateLimitGroup::RequestUpload => "request_upload",
+        }
+    }
+
+    /// `(requests, per, key)` used when the group is not configured. IP keys are only
+    /// per client when `TRUST_PROXY_HEADERS` lets the frontend pass the browser's
+    /// address on; otherwise they are one bucket for everyone behind it.
+    fn default_policy(self) -> (&'static str, RateLimitKey) {
+        match self {
+            RateLimitGroup::Auth => ("10/1m", RateLimitKey::Ip),
+            RateLimitGroup::Search => ("30/1m", RateLimitKey::User),
+            RateLimitGroup::Retrieve => ("20/1m", RateLimitKey::IpAndUser),
+            RateLimitGroup::RequestUpload => ("20/1h", RateLimitKey::Ip),
+        }
+    }
+}
+
+/// What a bucket belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    Ip,
+    /// Falls back to the IP on routes without a logged-in user.
+    User,
+    /// A bucket for each; both must have room.
+    IpAndUser,
+}
+
+impl RateLimitKey {
+    fn parse(key: &str) -> Option<Self> {
+        match key {
+            "ip" => Some(RateLimitKey::Ip),
+            "user" => Some(RateLimitKey::User),
+            "ip_and_user" => Some(RateLimitKey::IpAndUser),
+            _ => None,
+        }
+    }
+}
+
+/// Token bucket: holds up to `capacity` requests and refills `capacity` every
+/// `period_seconds`, so short bursts are fine but the long-run rate is capped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub period_seconds: i64,
+    pub key: RateLimitKey,
+}
+
+impl RateLimitPolicy {
+    pub fn refill_per_second(&self) -> f64 {
+        self.capacity as f64 / self.period_seconds as f64
+    }
+
+    /// Parses `<requests>/<duration>`, e.g. `10/1m` or `100/1h`.
+    fn parse(value: &str, key: RateLimitKey) -> Option<Self> {
+        let (capacity, period) = value.split_once('/')?;
+        let capacity: u32 = capacity
+            .trim()
+            .parse()
+            .ok()
+            .filter(|capacity| *capacity > 0)?;
+        let period_seconds = parse_duration(period)?.num_seconds();
+
+        Some(RateLimitPolicy {
+            capacity,
+            period_seconds,
+            key,
+        })
+    }
+
+    /// Reads `RATE_LIMIT_<GROUP>` (`<requests>/<duration>`, or `off`) and
+    /// `RATE_LIMIT_<GROUP>_KEY` (`ip`, `user` or `ip_and_user`) for each group.
+    /// Groups that are switched off are left out.
+    pub fn from_env() -> Vec<(RateLimitGroup, RateLimitPolicy)> {
+        RateLimitGroup::ALL
+            .into_iter()
+            .filter_map(|group| {
+                let name = group.to_str().to_uppercase();
+                let (default_limit, default_key) = group.default_policy();
+
+                let limit = std::env::var(format!("RATE_LIMIT_{}", name))
+                    .unwrap_or_else(|_| default_limit.to_string());
+                if limit.trim() == "off" {
+                    return None;
+                }
+
+                let key = std::env::var(format!("RATE_LIMIT_{}_KEY", name))
+                    .ok()
+                    .map(|key| {
+                        RateLimitKey::parse(key.trim()).unwrap_or_else(|| {
+                            eprintln!("Invalid RATE_LIMIT_{}_KEY {}; using the default", name, key);
+                            default_key
+                        })
+                    })
+                    .unwrap_or(default_key);
+
+                let policy = RateLimitPolicy::parse(&limit, key).unwrap_or_else(|| {
+                    eprintln!(
+                        "Invalid RATE_LIMIT_{} {}; using {}",
+                        name, limit, default_limit
+                    );
+                    RateLimitPolicy::parse(default_limit, key).expect("default policy is valid")
+                });
+
+                Some((group, policy))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimitError(pub String);
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RateLimitError: {}", self.0)
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+#[async_trait]
+pub trait RateLimiter: Send + Sync + fmt::Debug {
+    /// Takes one request from `key`'s bucket. `Some(seconds)` means the bucket is
+    /// empty and the caller should wait that long.
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<Option<u64>, RateLimitError>;
+}
+
+/// `memory` keeps buckets in this process; `postgres` shares them between all
+/// instances behind a load balancer.
+pub fn build_rate_limiter(
+    config: &Config,
+    db_client: &DBClient,
+) -> Result<Arc<dyn RateLimiter>, RateLimitError> {
+    match config.rate_limit_backend.as_str() {
+        "memory" => Ok(Arc::new(MemoryRateLimiter::default())),
+        "postgres" => Ok(Arc::new(PostgresRateLimiter::new(db_client.clone()))),
+        other => Err(RateLimitError(format!(
+            "Unknown RATE_LIMIT_BACKEND: {}",
+            other
+        ))),
+    }
+}
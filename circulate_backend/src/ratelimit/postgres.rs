@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+
+use super::{RateLimitError, RateLimitPolicy, RateLimiter};
+use crate::db::{DBClient, RateLimitExt};
+
+/// Buckets in the `rate_limit_buckets` table, shared by every instance using the
+/// same database.
+#[derive(Debug)]
+pub struct PostgresRateLimiter {
+    db_client: DBClient,
+}
+
+impl PostgresRateLimiter {
+    pub fn new(db_client: DBClient) -> Self {
+        PostgresRateLimiter { db_client }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for PostgresRateLimiter {
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<Option<u64>, RateLimitError> {
+        let refill_per_second = policy.refill_per_second();
+
+        let tokens_available = self
+            .db_client
+            .take_rate_limit_token(
+                key,
+                policy.capacity as f64,
+                refill_per_second,
+                policy.period_seconds as f64,
+            )
+            .await
+            .map_err(|e| RateLimitError(e.to_string()))?;
+
+        Ok(tokens_available
+            .map(|tokens| ((1.0 - tokens) / refill_per_second).ceil().max(1.0) as u64))
+    }
+}
@@ -0,0 +1,176 @@
+//! Live inbox updates.
+//!
+//! Share events are stored in `inbox_events` and their ids published with
+//! `pg_notify` on [`INBOX_CHANNEL`]; notify payloads are size-capped, so the event
+//! itself never travels that way. Every backend instance `LISTEN`s on that channel,
+//! reads each announced event back and forwards it into a local broadcast channel,
+//! which the `/api/events` stream filters by recipient. This way an upload handled by
+//! one instance reaches a browser connected to another.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{db::NotifyExt, models::ShareEventDetails, webhook::WebhookEvent, AppState};
+
+pub const INBOX_CHANNEL: &str = "inbox_events";
+
+const BROADCAST_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub share_id: Uuid,
+    pub file_id: Uuid,
+    pub file_name: String,
+    pub file_size: i64,
+    pub sender_email: Option<String>,
+    pub expiration_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What travels over the broadcast channel: the event plus whose inbox it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxNotification {
+    pub recipient_id: Uuid,
+    pub event: InboxEvent,
+}
+
+impl InboxNotification {
+    /// The inbox counterpart of a share event, if the recipient's inbox changes.
+    pub fn from_share_event(event: &WebhookEvent, share: &ShareEventDetails) -> Option<Self> {
+        let event_type = match event {
+            WebhookEvent::Uploaded => "share.received",
+            WebhookEvent::Revoked => "share.revoked",
+            WebhookEvent::Expired => "share.expired",
+            WebhookEvent::Declined => "share.declined",
+            WebhookEvent::Downloaded => return None,
+        };
+
+        Some(InboxNotification {
+            recipient_id: share.recipient_id,
+            event: InboxEvent {
+                id: Uuid::new_v4(),
+                event_type: event_type.to_string(),
+                share_id: share.share_id,
+                file_id: share.file_id,
+                file_name: share.file_name.clone(),
+                file_size: share.file_size,
+                sender_email: share.sender_email.clone(),
+                expiration_date: share.expiration_date,
+                created_at: Utc::now(),
+            },
+        })
+    }
+}
+
+pub fn inbox_channel() -> broadcast::Sender<InboxNotification> {
+    broadcast::channel(BROADCAST_CAPACITY).0
+}
+
+pub async fn publish_share_event(
+    app_state: &AppState,
+    event: &WebhookEvent,
+    share: &ShareEventDetails,
+) {
+    let Some(notification) = InboxNotification::from_share_event(event, share) else {
+        return;
+    };
+
+    let payload = match serde_json::to_value(&notification.event) {
+        Ok(payload) => payload,
+        Err(err) => {
+            eprintln!("Error serializing inbox event: {:?}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = app_state
+        .db_client
+        .publish_inbox_event(
+            INBOX_CHANNEL,
+            notification.event.id,
+            notification.recipient_id,
+            payload,
+        )
+        .await
+    {
+        eprintln!("Error publishing inbox event: {:?}", err);
+    }
+}
+
+async fn load_inbox_notification(app_state: &AppState, payload: &str) -> Option<InboxNotification> {
+    let event_id = match Uuid::parse_str(payload) {
+        Ok(event_id) => event_id,
+        Err(err) => {
+            eprintln!("Error decoding inbox event id: {:?}", err);
+            return None;
+        }
+    };
+
+    let (recipient_id, payload) = match app_state.db_client.get_inbox_event(event_id).await {
+        Ok(Some(event)) => event,
+        Ok(None) => return None,
+        Err(err) => {
+            eprintln!("Error loading inbox event: {:?}", err);
+            return None;
+        }
+    };
+
+    match serde_json::from_value::<InboxEvent>(payload) {
+        Ok(event) => Some(InboxNotification {
+            recipient_id,
+            event,
+        }),
+        Err(err) => {
+            eprintln!("Error decoding inbox event: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Listens on [`INBOX_CHANNEL`] for the lifetime of the process and feeds the
+/// local broadcast channel.
+pub fn spawn_inbox_listener(app_state: std::sync::Arc<AppState>) {
+    tokio::spawn(async move {
+        loop {
+            let mut listener = match PgListener::connect_with(app_state.db_client.pool()).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("Error connecting inbox listener: {:?}", err);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            if let Err(err) = listener.listen(INBOX_CHANNEL).await {
+                eprintln!("Error listening for inbox events: {:?}", err);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => {
+                        if let Some(notification) =
+                            load_inbox_notification(&app_state, notification.payload()).await
+                        {
+                            // No subscribers is not an error worth reporting.
+                            let _ = app_state.inbox.send(notification);
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Inbox listener connection lost: {:?}", err);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
@@ -5,10 +5,16 @@ use tower_http::trace::TraceLayer;
 
 use crate::{
     handler::{
-        auth::auth_handler, file::file_handle, file_query::get_file_list_handler,
+        admin::admin_handler,
+        auth::auth_handler,
+        events::events_handler,
+        file::file_handle,
+        file_query::get_file_list_handler,
+        file_request::{file_request_handler, public_file_request_handler},
         user::users_handler,
+        webhook::{webhook_handler, WebhookScope},
     },
-    middleware::auth,
+    middleware::{auth, require_session},
     AppState,
 };
 
@@ -21,6 +27,24 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
             "/list",
             get_file_list_handler().layer(middleware::from_fn(auth)),
         )
+        .nest(
+            "/requests",
+            file_request_handler().layer(middleware::from_fn(auth)),
+        )
+        .nest("/public/requests", public_file_request_handler())
+        .nest("/events", events_handler().layer(middleware::from_fn(auth)))
+        .nest(
+            "/webhooks",
+            webhook_handler(WebhookScope::User)
+                .layer(middleware::from_fn(require_session))
+                .layer(middleware::from_fn(auth)),
+        )
+        .nest(
+            "/admin",
+            admin_handler()
+                .layer(middleware::from_fn(require_session))
+                .layer(middleware::from_fn(auth)),
+        )
         .layer(TraceLayer::new_for_http())
         .layer(Extension(app_state));
 
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Duration, Utc};
+
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// A user's failures across shares are forgotten after this long without another one,
+/// so an old streak does not keep them waiting forever.
+pub const FAILURE_DECAY_HOURS: i64 = 24;
+
+/// Seconds left before another attempt is allowed, or `None` when the caller may retry now.
+///
+/// The first `free_attempts` failures are not delayed; every failure after that doubles
+/// the wait, starting at `base_seconds` and capped at one hour.
+pub fn retry_after(
+    failed_attempts: i32,
+    last_failed_at: Option<DateTime<Utc>>,
+    free_attempts: i32,
+    base_seconds: i64,
+) -> Option<u64> {
+    let last_failed_at = last_failed_at?;
+
+    if failed_attempts < free_attempts {
+        return None;
+    }
+
+    let exponent = (failed_attempts - free_attempts).min(30) as u32;
+    let delay = base_seconds
+        .saturating_mul(2_i64.saturating_pow(exponent))
+        .min(MAX_BACKOFF_SECONDS);
+
+    let remaining = (last_failed_at + Duration::seconds(delay) - Utc::now()).num_seconds();
+
+    if remaining > 0 {
+        Some(remaining as u64)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+
+use crate::{error::HttpError, AppState};
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    /// `trusted_proxies` is how many proxies in front of the API append to
+    /// `x-forwarded-for`; with zero the header is ignored and the peer address used.
+    pub fn from_parts(
+        headers: &HeaderMap,
+        peer: Option<SocketAddr>,
+        trusted_proxies: usize,
+    ) -> Self {
+        let ip_address = forwarded_ip(headers, trusted_proxies)
+            .or_else(|| peer.map(|addr| addr.ip()))
+            .map(|ip| ip.to_string());
+
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+
+        ClientInfo {
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+/// Each proxy appends the address it got the request from, so the client is the
+/// entry the outermost trusted proxy added, `trusted_proxies` from the right.
+/// Anything left of it was sent by the client and can say anything.
+fn forwarded_ip(headers: &HeaderMap, trusted_proxies: usize) -> Option<IpAddr> {
+    if trusted_proxies == 0 {
+        return None;
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    // A shorter chain came through fewer proxies, so every entry in it is one a
+    // trusted proxy added and the leftmost is the client.
+    let hop = hops.get(hops.len().saturating_sub(trusted_proxies))?;
+
+    hop.parse::<IpAddr>()
+        .ok()
+        .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = HttpError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let trusted_proxies = parts
+            .extensions
+            .get::<Arc<AppState>>()
+            .map(|app_state| app_state.env.trusted_proxies())
+            .unwrap_or(0);
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+
+        Ok(ClientInfo::from_parts(
+            &parts.headers,
+            peer,
+            trusted_proxies,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn client_ip(headers: &HeaderMap, trusted_proxies: usize) -> Option<String> {
+        let peer = "10.0.0.2:40000".parse().ok();
+        ClientInfo::from_parts(headers, peer, trusted_proxies).ip_address
+    }
+
+    #[test]
+    fn ignores_the_header_without_trusted_proxies() {
+        let headers = forwarded_for(&["203.0.113.7"]);
+        assert_eq!(client_ip(&headers, 0).as_deref(), Some("10.0.0.2"));
+    }
+
+    #[test]
+    fn takes_the_entry_the_proxy_appended() {
+        let headers = forwarded_for(&["1.1.1.1, 203.0.113.7"]);
+        assert_eq!(client_ip(&headers, 1).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn counts_trusted_proxies_from_the_right() {
+        let headers = forwarded_for(&["1.1.1.1, 203.0.113.7", "10.0.0.1"]);
+        assert_eq!(client_ip(&headers, 2).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn takes_the_leftmost_entry_of_a_short_chain() {
+        let headers = forwarded_for(&["203.0.113.7"]);
+        assert_eq!(client_ip(&headers, 3).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn falls_back_to_the_peer() {
+        assert_eq!(client_ip(&HeaderMap::new(), 1).as_deref(), Some("10.0.0.2"));
+
+        let headers = forwarded_for(&["1.1.1.1, not-an-ip"]);
+        assert_eq!(client_ip(&headers, 1).as_deref(), Some("10.0.0.2"));
+    }
+
+    #[test]
+    fn strips_ports() {
+        let headers = forwarded_for(&["203.0.113.7:51000"]);
+        assert_eq!(client_ip(&headers, 1).as_deref(), Some("203.0.113.7"));
+
+        let headers = forwarded_for(&["[2001:db8::1]:51000"]);
+        assert_eq!(client_ip(&headers, 1).as_deref(), Some("2001:db8::1"));
+    }
+}
@@ -0,0 +1,95 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Position in a share list ordered by `(created_at, id)`. Clients only ever see the
+/// encoded form, so the layout can change without breaking them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+    /// Set on `prev_cursor`s: the page before this position is wanted.
+    pub backward: bool,
+}
+
+impl ShareCursor {
+    pub fn encode(&self) -> String {
+        let direction = if self.backward { "p" } else { "n" };
+        let raw = format!(
+            "{}:{}:{}",
+            direction,
+            self.created_at.timestamp_micros(),
+            self.id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(value).ok()?).ok()?;
+        let mut parts = raw.splitn(3, ':');
+
+        let backward = match parts.next()? {
+            "n" => false,
+            "p" => true,
+            _ => return None,
+        };
+        let created_at = DateTime::from_timestamp_micros(parts.next()?.parse().ok()?)?;
+        let id = Uuid::parse_str(parts.next()?).ok()?;
+
+        Some(ShareCursor {
+            created_at,
+            id,
+            backward,
+        })
+    }
+}
+
+/// Trims a page fetched with one extra row and works out the cursors around it.
+/// `rows` must be in scan order: reversed when `cursor` points backward.
+pub fn paginate<T>(
+    mut rows: Vec<T>,
+    limit: usize,
+    cursor: Option<&ShareCursor>,
+    page: usize,
+    key: impl Fn(&T) -> Option<(DateTime<Utc>, Uuid)>,
+) -> (Vec<T>, Option<String>, Option<String>) {
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+
+    let backward = cursor.is_some_and(|cursor| cursor.backward);
+    if backward {
+        rows.reverse();
+    }
+
+    // Going forward there is something behind us unless this is the first page;
+    // going backward we just came from the rows after this page.
+    let (has_next, has_prev) = if backward {
+        (true, has_more)
+    } else {
+        (has_more, cursor.is_some() || page > 1)
+    };
+
+    let cursor_at = |row: Option<&T>, backward: bool| {
+        row.and_then(&key).map(|(created_at, id)| {
+            ShareCursor {
+                created_at,
+                id,
+                backward,
+            }
+            .encode()
+        })
+    };
+
+    let next_cursor = if has_next {
+        cursor_at(rows.last(), false)
+    } else {
+        None
+    };
+    let prev_cursor = if has_prev {
+        cursor_at(rows.first(), true)
+    } else {
+        None
+    };
+
+    (rows, next_cursor, prev_cursor)
+}
@@ -19,10 +19,10 @@ pub async fn decrypt_file(
     let cipher = Cbc::<Aes256, Pkcs7>::new_from_slices(&aes_key, &iv)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let mut buffer = encrypted_file_data.clone();
+    let buffer = encrypted_file_data.clone();
 
     let decrypted_data = cipher
-        .decrypt_vec(&mut buffer)
+        .decrypt_vec(&buffer)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     Ok(decrypted_data)
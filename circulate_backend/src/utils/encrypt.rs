@@ -1,9 +1,32 @@
 use aes::Aes256;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use block_modes::{block_padding::Pkcs7, BlockMode, Cbc};
 use rand::Rng;
-use rsa::{Pkcs1v15Encrypt, RsaPublicKey};
+use rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+use sha2::{Digest, Sha256};
 
-use crate::error::HttpError;
+use crate::{error::HttpError, models::User};
+
+pub fn recipient_public_key(recipient: &User) -> Result<RsaPublicKey, HttpError> {
+    let public_key_str = match &recipient.public_key {
+        Some(key) => key,
+        None => return Err(HttpError::bad_request("Recipient has no public key")),
+    };
+
+    let public_key_bytes = STANDARD
+        .decode(public_key_str)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let public_key =
+        String::from_utf8(public_key_bytes).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    RsaPublicKey::from_pkcs1_pem(&public_key).map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// Hex SHA-256 of the plaintext, so recipients can check what they decrypted.
+pub fn content_digest(file_data: &[u8]) -> String {
+    hex::encode(Sha256::digest(file_data))
+}
 
 pub async fn encrypt_file(
     file_data: Vec<u8>,
@@ -17,8 +40,8 @@ pub async fn encrypt_file(
     let cipher = Cbc::<Aes256, Pkcs7>::new_from_slices(&aes_key, &iv)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let mut buffer = file_data.clone();
-    let encrypted_data = cipher.encrypt_vec(&mut buffer);
+    let buffer = file_data.clone();
+    let encrypted_data = cipher.encrypt_vec(&buffer);
 
     let encrypted_aes_key = user_public_key
         .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, &aes_key)
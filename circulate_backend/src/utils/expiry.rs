@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Longest lifetime any policy can allow, so durations never overflow a timestamp.
+const MAX_LIFETIME_DAYS: i64 = 3650;
+
+/// Parses a share lifetime. Accepts ISO 8601 durations made of weeks, days, hours,
+/// minutes and seconds (`PT24H`, `P1DT12H`, `P2W`) and the shorthand `7d`, `24h`,
+/// `90m`, `30s`, `2w`, which may be combined (`1d12h`). Years and months are
+/// rejected because their length depends on the calendar.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    let seconds = match value.strip_prefix(['P', 'p']) {
+        Some(rest) => parse_iso8601(rest)?,
+        None => parse_units(value, Units::Shorthand)?,
+    };
+
+    if seconds <= 0 || seconds > MAX_LIFETIME_DAYS * 24 * 60 * 60 {
+        return None;
+    }
+
+    Duration::try_seconds(seconds)
+}
+
+fn parse_iso8601(value: &str) -> Option<i64> {
+    let (date_part, time_part) = match value.split_once(['T', 't']) {
+        Some((date_part, time_part)) if !time_part.is_empty() => (date_part, Some(time_part)),
+        Some(_) => return None,
+        None => (value, None),
+    };
+
+    let mut seconds = if date_part.is_empty() {
+        0
+    } else {
+        parse_units(date_part, Units::IsoDate)?
+    };
+
+    if let Some(time_part) = time_part {
+        seconds = seconds.checked_add(parse_units(time_part, Units::IsoTime)?)?;
+    }
+
+    Some(seconds)
+}
+
+/// Which units a run of `<number><unit>` pairs may use. In ISO 8601 `M` means months
+/// before the `T` and minutes after it, so the two parts are kept apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Units {
+    /// `w`, `d`, `h`, `m` and `s`.
+    Shorthand,
+    /// Before the `T`: weeks and days only.
+    IsoDate,
+    /// After the `T`: hours, minutes and seconds.
+    IsoTime,
+}
+
+/// Sums `<number><unit>` pairs, refusing units `units` does not allow.
+fn parse_units(value: &str, units: Units) -> Option<i64> {
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut total: i64 = 0;
+    let mut number = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let amount: i64 = number.parse().ok()?;
+        number.clear();
+
+        let unit_seconds = match (c.to_ascii_lowercase(), units) {
+            ('w', Units::Shorthand | Units::IsoDate) => 7 * 24 * 60 * 60,
+            ('d', Units::Shorthand | Units::IsoDate) => 24 * 60 * 60,
+            ('h', Units::Shorthand | Units::IsoTime) => 60 * 60,
+            ('m', Units::Shorthand | Units::IsoTime) => 60,
+            ('s', Units::Shorthand | Units::IsoTime) => 1,
+            _ => return None,
+        };
+
+        total = total.checked_add(amount.checked_mul(unit_seconds)?)?;
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(total)
+}
+
+/// Bounds on how long a share may live. The default is used when the uploader does
+/// not ask for an expiration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpiryPolicy {
+    pub min: Duration,
+    pub default: Duration,
+    pub max: Duration,
+}
+
+impl ExpiryPolicy {
+    /// Reads `SHARE_EXPIRY_MIN`, `SHARE_EXPIRY_DEFAULT` and `SHARE_EXPIRY_MAX`, and any
+    /// `SHARE_EXPIRY_{MIN,DEFAULT,MAX}_<ROLE>` overrides. Unset role values fall back
+    /// to the base policy.
+    pub fn from_env() -> (ExpiryPolicy, HashMap<String, ExpiryPolicy>) {
+        let env_duration = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| parse_duration(&value))
+        };
+
+        let base = ExpiryPolicy::new(
+            env_duration("SHARE_EXPIRY_MIN").unwrap_or_else(|| Duration::minutes(5)),
+            env_duration("SHARE_EXPIRY_DEFAULT").unwrap_or_else(|| Duration::days(7)),
+            env_duration("SHARE_EXPIRY_MAX").unwrap_or_else(|| Duration::days(30)),
+        );
+
+        let mut roles: Vec<String> = std::env::vars()
+            .filter_map(|(key, _)| {
+                [
+                    "SHARE_EXPIRY_MIN_",
+                    "SHARE_EXPIRY_DEFAULT_",
+                    "SHARE_EXPIRY_MAX_",
+                ]
+                .iter()
+                .find_map(|prefix| key.strip_prefix(prefix))
+                .map(|role| role.to_string())
+            })
+            .collect();
+        roles.sort();
+        roles.dedup();
+
+        let role_policies = roles
+            .into_iter()
+            .map(|role| {
+                let policy = ExpiryPolicy::new(
+                    env_duration(&format!("SHARE_EXPIRY_MIN_{}", role)).unwrap_or(base.min),
+                    env_duration(&format!("SHARE_EXPIRY_DEFAULT_{}", role)).unwrap_or(base.default),
+                    env_duration(&format!("SHARE_EXPIRY_MAX_{}", role)).unwrap_or(base.max),
+                );
+                (role.to_lowercase(), policy)
+            })
+            .collect();
+
+        (base, role_policies)
+    }
+
+    fn new(min: Duration, default: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        ExpiryPolicy {
+            min,
+            default: default.max(min).min(max),
+            max,
+        }
+    }
+
+    /// Turns what the uploader sent (nothing, an RFC 3339 timestamp or a duration)
+    /// into an expiration date inside this policy.
+    pub fn resolve(&self, requested: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        let requested = requested.trim();
+
+        if requested.is_empty() {
+            return Ok(now + self.default);
+        }
+
+        let expiration_date = match DateTime::parse_from_rfc3339(requested) {
+            Ok(expiration_date) => expiration_date.with_timezone(&Utc),
+            Err(_) => match parse_duration(requested) {
+                Some(duration) => now + duration,
+                None => return Err(
+                    "Invalid expiration. Use an RFC3339 date or a duration such as PT24H or 7d."
+                        .to_string(),
+                ),
+            },
+        };
+
+        if expiration_date <= now {
+            return Err("Expiration date must be in the future.".to_string());
+        }
+
+        if expiration_date < now + self.min {
+            return Err(format!(
+                "Shares must stay available for at least {}.",
+                describe(self.min)
+            ));
+        }
+
+        if expiration_date > now + self.max {
+            return Err(format!(
+                "Shares can stay available for at most {}.",
+                describe(self.max)
+            ));
+        }
+
+        Ok(expiration_date)
+    }
+}
+
+fn describe(duration: Duration) -> String {
+    let plural = |n: i64, unit: &str| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+
+    if duration.num_seconds() % (24 * 60 * 60) == 0 {
+        plural(duration.num_days(), "day")
+    } else if duration.num_seconds() % (60 * 60) == 0 {
+        plural(duration.num_hours(), "hour")
+    } else if duration.num_seconds() % 60 == 0 {
+        plural(duration.num_minutes(), "minute")
+    } else {
+        plural(duration.num_seconds(), "second")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iso8601_time_units() {
+        assert_eq!(parse_duration("PT1M"), Some(Duration::minutes(1)));
+        assert_eq!(parse_duration("PT24H"), Some(Duration::hours(24)));
+        assert_eq!(parse_duration("PT30S"), Some(Duration::seconds(30)));
+    }
+
+    #[test]
+    fn iso8601_date_and_time_parts() {
+        assert_eq!(
+            parse_duration("P1DT2H"),
+            Some(Duration::days(1) + Duration::hours(2))
+        );
+        assert_eq!(parse_duration("P2W"), Some(Duration::weeks(2)));
+        assert_eq!(parse_duration("p1d"), Some(Duration::days(1)));
+    }
+
+    #[test]
+    fn iso8601_rejects_calendar_units() {
+        assert_eq!(parse_duration("P1M"), None);
+        assert_eq!(parse_duration("P1Y"), None);
+        assert_eq!(parse_duration("P1Y2M"), None);
+    }
+
+    #[test]
+    fn iso8601_rejects_units_in_the_wrong_part() {
+        assert_eq!(parse_duration("P2H"), None);
+        assert_eq!(parse_duration("P30S"), None);
+        assert_eq!(parse_duration("PT1D"), None);
+        assert_eq!(parse_duration("PT1W"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for value in [
+            "", "P", "PT", "P1DT", "P1", "PTH", "P-1D", "1x", "d", "P1D2", "P1.5D",
+        ] {
+            assert_eq!(parse_duration(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn shorthand() {
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        assert_eq!(
+            parse_duration("1d12h"),
+            Some(Duration::days(1) + Duration::hours(12))
+        );
+        assert_eq!(parse_duration("2w"), Some(Duration::weeks(2)));
+    }
+
+    #[test]
+    fn rejects_zero_and_overlong_durations() {
+        assert_eq!(parse_duration("0d"), None);
+        assert_eq!(parse_duration("PT0S"), None);
+        assert_eq!(parse_duration("3651d"), None);
+    }
+}
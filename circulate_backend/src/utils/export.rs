@@ -0,0 +1,79 @@
+use crate::dtos::ShareHistoryDto;
+
+/// Output format of a history export. Both are written one line per share so the
+/// export can be streamed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub const ALL: [&'static str; 2] = ["csv", "jsonl"];
+
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" => Some(ExportFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+
+    /// Line written before any rows, if the format has one.
+    pub fn header(&self) -> Option<String> {
+        match self {
+            ExportFormat::Csv => Some(csv_line(ShareHistoryDto::CSV_HEADER.iter())),
+            ExportFormat::JsonLines => None,
+        }
+    }
+
+    pub fn row(&self, row: &ShareHistoryDto) -> String {
+        match self {
+            ExportFormat::Csv => csv_line(row.csv_record().iter()),
+            ExportFormat::JsonLines => {
+                let mut line = serde_json::to_string(row).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+        }
+    }
+}
+
+fn csv_line<T: AsRef<str>>(fields: impl Iterator<Item = T>) -> String {
+    let mut line = fields
+        .map(|field| csv_field(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Quotes a field when needed. File names come from uploaders, so values that a
+/// spreadsheet would run as a formula get a leading `'`.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
@@ -39,12 +39,12 @@ pub async fn generate_key(
 
     app_state
         .db_client
-        .save_user_key(user_id.clone(), public_key_b64.clone())
+        .save_user_key(user_id, public_key_b64.clone())
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     let private_keys_dir = "assets/private_keys";
-    fs::create_dir_all(&private_keys_dir).map_err(|e| HttpError::server_error(e.to_string()))?;
+    fs::create_dir_all(private_keys_dir).map_err(|e| HttpError::server_error(e.to_string()))?;
 
     let pem_file_path = format!("{}/{}.pem", private_keys_dir, user_id.clone());
     let mut file =
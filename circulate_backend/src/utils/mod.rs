@@ -1,5 +1,11 @@
+pub mod backoff;
+pub mod client;
+pub mod cursor;
 pub mod decrypt;
 pub mod encrypt;
+pub mod expiry;
+pub mod export;
 pub mod keys;
 pub mod password;
 pub mod token;
+pub mod totp;
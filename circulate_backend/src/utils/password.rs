@@ -1,10 +1,7 @@
-use std::usize::MAX;
-
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
-use jsonwebtoken::errors::Error;
 
 use crate::error::ErrorMessage;
 
@@ -41,7 +38,7 @@ pub fn compare(password: &str, hashed_password: &str) -> Result<bool, ErrorMessa
 
     let password_matched = Argon2::default()
         .verify_password(password.as_bytes(), &parsed_hash)
-        .map_or(false, |_| true);
+        .is_ok();
 
     Ok(password_matched)
 }
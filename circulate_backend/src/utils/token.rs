@@ -1,20 +1,25 @@
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::error::{ErrorMessage, HttpError};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
     pub sub: String,
+    /// Session the token was issued for; the token dies with it.
+    pub sid: String,
     pub iat: usize,
     pub exp: usize,
 }
 
 pub fn create_token(
     user_id: &str,
+    session_id: &str,
     secret: &[u8],
-    expires_in_seconds: i64,
+    expires_in_minutes: i64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     if user_id.is_empty() {
         return Err(jsonwebtoken::errors::ErrorKind::InvalidSubject.into());
@@ -22,9 +27,10 @@ pub fn create_token(
 
     let now = Utc::now();
     let iat = now.timestamp() as usize;
-    let exp = (now + Duration::minutes(expires_in_seconds)).timestamp() as usize;
+    let exp = (now + Duration::minutes(expires_in_minutes)).timestamp() as usize;
     let claims = TokenClaims {
         sub: user_id.to_string(),
+        sid: session_id.to_string(),
         iat,
         exp,
     };
@@ -36,7 +42,7 @@ pub fn create_token(
     )
 }
 
-pub fn decode_token<T: Into<String>>(token: T, secret: &[u8]) -> Result<String, HttpError> {
+pub fn decode_token<T: Into<String>>(token: T, secret: &[u8]) -> Result<TokenClaims, HttpError> {
     let decode = decode::<TokenClaims>(
         &token.into(),
         &DecodingKey::from_secret(secret),
@@ -44,9 +50,106 @@ pub fn decode_token<T: Into<String>>(token: T, secret: &[u8]) -> Result<String,
     );
 
     match decode {
-        Ok(token) => Ok(token.claims.sub),
+        Ok(token) => Ok(token.claims),
         Err(_) => Err(HttpError::unauthorized(
             ErrorMessage::InvalidToken.to_string(),
         )),
     }
 }
+
+/// Claims of single-purpose tokens, such as the one in email verification links.
+/// They have no session, so `auth` refuses them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScopedClaims {
+    pub sub: String,
+    pub scope: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+pub const VERIFY_EMAIL_SCOPE: &str = "verify_email";
+
+pub fn create_scoped_token(
+    user_id: &str,
+    scope: &str,
+    secret: &[u8],
+    expires_in_minutes: i64,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = Utc::now();
+    let claims = ScopedClaims {
+        sub: user_id.to_string(),
+        scope: scope.to_string(),
+        iat: now.timestamp() as usize,
+        exp: (now + Duration::minutes(expires_in_minutes)).timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+}
+
+/// Decodes a token made by `create_scoped_token`, refusing tokens for any other scope.
+pub fn decode_scoped_token<T: Into<String>>(
+    token: T,
+    scope: &str,
+    secret: &[u8],
+) -> Result<ScopedClaims, HttpError> {
+    let decode = decode::<ScopedClaims>(
+        &token.into(),
+        &DecodingKey::from_secret(secret),
+        &Validation::new(jsonwebtoken::Algorithm::HS256),
+    );
+
+    match decode {
+        Ok(token) if token.claims.scope == scope => Ok(token.claims),
+        _ => Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        )),
+    }
+}
+
+/// New opaque refresh token. Only its hash is stored.
+pub fn generate_refresh_token() -> String {
+    format!("rt_{}", hex::encode(rand::thread_rng().gen::<[u8; 32]>()))
+}
+
+pub fn hash_refresh_token(refresh_token: &str) -> String {
+    hex::encode(Sha256::digest(refresh_token.as_bytes()))
+}
+
+/// New opaque 2FA challenge, handed out between the password and 2FA steps of
+/// login. Only its hash is stored.
+pub fn generate_two_factor_challenge() -> String {
+    format!("tf_{}", hex::encode(rand::thread_rng().gen::<[u8; 32]>()))
+}
+
+pub fn hash_two_factor_challenge(challenge: &str) -> String {
+    hex::encode(Sha256::digest(challenge.as_bytes()))
+}
+
+/// New opaque password reset token. Only its hash is stored.
+pub fn generate_password_reset_token() -> String {
+    format!("pr_{}", hex::encode(rand::thread_rng().gen::<[u8; 32]>()))
+}
+
+pub fn hash_password_reset_token(reset_token: &str) -> String {
+    hex::encode(Sha256::digest(reset_token.as_bytes()))
+}
+
+/// Marks personal access tokens so `auth` can tell them from session JWTs.
+pub const ACCESS_TOKEN_PREFIX: &str = "circ_pat_";
+
+/// New personal access token. Only its hash is stored.
+pub fn generate_access_token() -> String {
+    format!(
+        "{}{}",
+        ACCESS_TOKEN_PREFIX,
+        hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+    )
+}
+
+pub fn hash_access_token(access_token: &str) -> String {
+    hex::encode(Sha256::digest(access_token.as_bytes()))
+}
@@ -0,0 +1,191 @@
+//! Delivers queued webhook events.
+//!
+//! Every request is a `POST` of the JSON event with these headers:
+//!
+//! - `X-Circulate-Event`: the event type, e.g. `file.uploaded`
+//! - `X-Circulate-Delivery`: the delivery id, stable across retries
+//! - `X-Circulate-Timestamp`: unix seconds at send time
+//! - `X-Circulate-Signature`: `sha256=` followed by the hex HMAC-SHA256 of
+//!   `"{timestamp}.{body}"`, keyed with the endpoint secret
+//!
+//! Any 2xx response marks the delivery as delivered. Other responses, redirects
+//! included, and network errors are retried with exponential backoff until
+//! `MAX_ATTEMPTS` is reached. Each batch is delivered concurrently, so one slow
+//! endpoint holds up at most its own deliveries for `REQUEST_TIMEOUT`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use tokio::task::JoinSet;
+
+use crate::{
+    db::WebhookExt,
+    models::WebhookDelivery,
+    webhook::target::{resolve_target, WebhookTarget},
+    AppState,
+};
+
+const MAX_ATTEMPTS: i32 = 8;
+const BATCH_SIZE: i64 = 20;
+const LEASE_SECONDS: f64 = 60.0;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_BASE_SECONDS: i64 = 30;
+const RETRY_MAX_SECONDS: i64 = 6 * 60 * 60;
+
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    let seconds = RETRY_BASE_SECONDS
+        .saturating_mul(2_i64.pow(exponent))
+        .min(RETRY_MAX_SECONDS);
+    chrono::Duration::seconds(seconds)
+}
+
+/// A client that can only reach the checked addresses and does not follow redirects,
+/// so a redirect cannot lead the request somewhere the check would have refused.
+fn pinned_client(target: &WebhookTarget) -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&target.host, &target.addrs)
+        .build()
+}
+
+/// What the endpoint owner gets to see about a failure: enough to fix their endpoint,
+/// not the details of what the server ran into.
+fn failure_reason(err: &reqwest::Error) -> &'static str {
+    if err.is_timeout() {
+        "Timed out"
+    } else if err.is_connect() {
+        "Could not connect"
+    } else {
+        "Request failed"
+    }
+}
+
+async fn deliver(app_state: &AppState, delivery: WebhookDelivery) {
+    let body = delivery.payload.to_string();
+    let timestamp = Utc::now().timestamp();
+    let signature = sign(&delivery.secret, timestamp, &body);
+
+    let started = Instant::now();
+    let target = resolve_target(&delivery.url, app_state.env.webhook_allow_private_targets).await;
+    let (response_status, error) = match target {
+        Ok(target) => match pinned_client(&target) {
+            Ok(client) => {
+                let result = client
+                    .post(&delivery.url)
+                    .header("Content-Type", "application/json")
+                    .header("X-Circulate-Event", &delivery.event_type)
+                    .header("X-Circulate-Delivery", delivery.id.to_string())
+                    .header("X-Circulate-Timestamp", timestamp.to_string())
+                    .header("X-Circulate-Signature", format!("sha256={}", signature))
+                    .body(body)
+                    .send()
+                    .await;
+
+                match result {
+                    Ok(response) if response.status().is_success() => {
+                        (Some(response.status().as_u16() as i32), None)
+                    }
+                    Ok(response) => (
+                        Some(response.status().as_u16() as i32),
+                        Some("Endpoint did not respond with 2xx".to_string()),
+                    ),
+                    Err(err) => (None, Some(failure_reason(&err).to_string())),
+                }
+            }
+            Err(err) => {
+                eprintln!("Failed to build the webhook HTTP client: {:?}", err);
+                (None, Some("Request failed".to_string()))
+            }
+        },
+        Err(err) => (None, Some(err.to_str().to_string())),
+    };
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let delivered = error.is_none();
+
+    if let Err(err) = app_state
+        .db_client
+        .save_webhook_attempt(delivery.id, response_status, error, duration_ms)
+        .await
+    {
+        eprintln!("Error recording webhook attempt: {:?}", err);
+    }
+
+    let result = if delivered {
+        app_state
+            .db_client
+            .mark_webhook_delivered(delivery.id)
+            .await
+    } else {
+        let next_attempt_at = if delivery.attempts >= MAX_ATTEMPTS {
+            None
+        } else {
+            Some(Utc::now() + retry_delay(delivery.attempts))
+        };
+        app_state
+            .db_client
+            .reschedule_webhook_delivery(delivery.id, next_attempt_at)
+            .await
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error updating webhook delivery: {:?}", err);
+    }
+}
+
+pub async fn deliver_pending(app_state: &Arc<AppState>) -> Result<usize, sqlx::Error> {
+    let deliveries = app_state
+        .db_client
+        .claim_webhook_deliveries(BATCH_SIZE, LEASE_SECONDS)
+        .await?;
+    let count = deliveries.len();
+
+    let mut tasks = JoinSet::new();
+    for delivery in deliveries {
+        let app_state = app_state.clone();
+        tasks.spawn(async move { deliver(&app_state, delivery).await });
+    }
+    while let Some(result) = tasks.join_next().await {
+        if let Err(err) = result {
+            eprintln!("Webhook delivery task failed: {:?}", err);
+        }
+    }
+
+    Ok(count)
+}
+
+pub fn spawn_delivery_worker(app_state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            // Keep draining while full batches come back, then wait for the next tick.
+            loop {
+                match deliver_pending(&app_state).await {
+                    Ok(count) if count as i64 == BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(err) => {
+                        eprintln!("Error delivering webhooks: {:?}", err);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
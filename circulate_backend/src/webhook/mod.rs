@@ -0,0 +1,118 @@
+pub mod delivery;
+pub mod target;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    db::{UserExt, WebhookExt},
+    models::ShareEventDetails,
+    realtime::publish_share_event,
+    AppState,
+};
+
+pub const WEBHOOK_EVENTS: [&str; 5] = [
+    "file.uploaded",
+    "file.downloaded",
+    "file.revoked",
+    "file.expired",
+    "file.declined",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookEvent {
+    Uploaded,
+    Downloaded,
+    Revoked,
+    Expired,
+    Declined,
+}
+
+impl WebhookEvent {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Uploaded => "file.uploaded",
+            WebhookEvent::Downloaded => "file.downloaded",
+            WebhookEvent::Revoked => "file.revoked",
+            WebhookEvent::Expired => "file.expired",
+            WebhookEvent::Declined => "file.declined",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    id: Uuid,
+    #[serde(rename = "type")]
+    event_type: &'a str,
+    created_at: DateTime<Utc>,
+    data: ShareEventData<'a>,
+}
+
+#[derive(Serialize)]
+struct ShareEventData<'a> {
+    share_id: Uuid,
+    file_id: Uuid,
+    file_name: &'a str,
+    file_size: i64,
+    sender_email: Option<&'a str>,
+    recipient_email: &'a str,
+    expiration_date: DateTime<Utc>,
+}
+
+/// Queues `event` for every active endpoint of the sender, the recipient and the
+/// organisation that subscribed to it. Delivery itself happens in [`delivery`].
+/// The recipient's live inbox stream is notified as well.
+pub async fn emit_share_event(
+    app_state: &AppState,
+    event: WebhookEvent,
+    share: &ShareEventDetails,
+) {
+    publish_share_event(app_state, &event, share).await;
+
+    let event_id = Uuid::new_v4();
+    let payload = WebhookPayload {
+        id: event_id,
+        event_type: event.to_str(),
+        created_at: Utc::now(),
+        data: ShareEventData {
+            share_id: share.share_id,
+            file_id: share.file_id,
+            file_name: &share.file_name,
+            file_size: share.file_size,
+            sender_email: share.sender_email.as_deref(),
+            recipient_email: &share.recipient_email,
+            expiration_date: share.expiration_date,
+        },
+    };
+
+    let payload = match serde_json::to_value(&payload) {
+        Ok(payload) => payload,
+        Err(err) => {
+            eprintln!("Error serializing webhook payload: {:?}", err);
+            return;
+        }
+    };
+
+    let mut user_ids = vec![share.recipient_id];
+    if let Some(sender_id) = share.sender_id {
+        user_ids.push(sender_id);
+    }
+
+    if let Err(err) = app_state
+        .db_client
+        .enqueue_webhook_event(event_id, event.to_str(), payload, &user_ids)
+        .await
+    {
+        eprintln!("Error queueing webhook event: {:?}", err);
+    }
+}
+
+pub async fn emit_share_event_by_id(app_state: &AppState, event: WebhookEvent, share_id: Uuid) {
+    match app_state.db_client.get_share_event_details(share_id).await {
+        Ok(Some(share)) => emit_share_event(app_state, event, &share).await,
+        Ok(None) => {}
+        Err(err) => eprintln!("Error loading share for webhook event: {:?}", err),
+    }
+}
@@ -0,0 +1,103 @@
+//! Keeps webhook deliveries on the public internet.
+//!
+//! Endpoints are checked when they are registered and again before every delivery,
+//! since DNS can change in between. Deliveries connect to the addresses that passed
+//! the check, so the host cannot be re-resolved to somewhere else mid-request.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use reqwest::Url;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetError {
+    InvalidUrl,
+    Unresolvable,
+    /// The host resolves to a loopback, private, link-local or otherwise internal
+    /// address.
+    NonPublicAddress,
+}
+
+impl TargetError {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            TargetError::InvalidUrl => "Webhook url must be an http or https url with a host",
+            TargetError::Unresolvable => "Webhook host could not be resolved",
+            TargetError::NonPublicAddress => "Webhook host must resolve to a public address",
+        }
+    }
+}
+
+/// A checked endpoint: its host and the public addresses it resolved to.
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub host: String,
+    pub addrs: Vec<SocketAddr>,
+}
+
+/// `allow_private` lifts the address check, for development against local receivers.
+pub async fn resolve_target(url: &str, allow_private: bool) -> Result<WebhookTarget, TargetError> {
+    let url = Url::parse(url).map_err(|_| TargetError::InvalidUrl)?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(TargetError::InvalidUrl);
+    }
+
+    let host = url.host_str().ok_or(TargetError::InvalidUrl)?.to_string();
+    let port = url.port_or_known_default().ok_or(TargetError::InvalidUrl)?;
+
+    // IPv6 literals come back bracketed, which the resolver does not accept.
+    let lookup_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup_host, port))
+        .await
+        .map_err(|_| TargetError::Unresolvable)?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(TargetError::Unresolvable);
+    }
+
+    // Every address has to be public: the client may connect to any of them.
+    if !allow_private && addrs.iter().any(|addr| !is_public(addr.ip())) {
+        return Err(TargetError::NonPublicAddress);
+    }
+
+    Ok(WebhookTarget { host, addrs })
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, _, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // 0.0.0.0/8, carrier-grade NAT (100.64.0.0/10), benchmarking (198.18.0.0/15)
+        // and the reserved 240.0.0.0/4.
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // Documentation prefix 2001:db8::/32.
+        || (ip.segments()[0] == 0x2001 && ip.segments()[1] == 0x0db8))
+}
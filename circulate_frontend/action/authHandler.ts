@@ -1,30 +1,43 @@
 "use server";
 
-import { AuthError } from "next-auth";
+import { AuthError, CredentialsSignin } from "next-auth";
+import { getToken } from "next-auth/jwt";
+import { cookies, headers } from "next/headers";
+import { redirect } from "next/navigation";
 import { signIn, signOut } from "@/auth";
 import { DEFAULT_LOGIN_REDIRECT } from "@/routes";
 import { GlobalApiCall } from "@/components/utils/GlobalApiCall";
+import { clientHeaders } from "@/components/utils/clientHeaders";
 
 const API_BASE_URL = process.env.API_BASE_URL;
 
 export async function LoginApi({
     email,
     password,
+    code,
 }: {
     email: string,
     password: string,
+    code?: string,
 }) {
     try{
         await signIn("credentials", {
             email,
             password,
+            code,
             redirectTo: DEFAULT_LOGIN_REDIRECT,
         });
     }catch(error) {
         if (error instanceof AuthError) {
             switch (error.type) {
                 case "CredentialsSignin":
-                    return {error: "Invalid email or password!"};
+                    if ((error as CredentialsSignin).code === "two_factor_required") {
+                        return {twoFactor: true};
+                    }
+                    if ((error as CredentialsSignin).code === "too_many_attempts") {
+                        return {error: "Too many failed attempts. Please try again later."};
+                    }
+                    return {error: code ? "Invalid authentication code!" : "Invalid email or password!"};
                 default:
                     return {error: "Something went wrongggggggggggggg!"};
             }
@@ -62,6 +75,176 @@ export async function RegisterApi({
 }
 
 
+export async function VerifyEmailApi(token: string) {
+    try{
+        const response = await fetch(`${API_BASE_URL}/auth/verify-email`, {
+            method: "post",
+            headers: {
+                "Content-Type": "application/json",
+                ...(await clientHeaders()),
+            },
+            body: JSON.stringify({ token }),
+            cache: 'no-store'
+        });
+        return await response.json();
+    }catch(error) {
+        throw error;
+    }
+}
+
+
+export async function ForgotPasswordApi(email: string) {
+    try{
+        const response = await fetch(`${API_BASE_URL}/auth/forgot-password`, {
+            method: "post",
+            headers: {
+                "Content-Type": "application/json",
+                ...(await clientHeaders()),
+            },
+            body: JSON.stringify({ email }),
+            cache: 'no-store'
+        });
+        return await response.json();
+    }catch(error) {
+        throw error;
+    }
+}
+
+
+export async function ResetPasswordApi({
+    token,
+    new_password,
+    new_password_confirm,
+}: {
+    token: string;
+    new_password: string;
+    new_password_confirm: string;
+}) {
+    try{
+        const response = await fetch(`${API_BASE_URL}/auth/reset-password`, {
+            method: "post",
+            headers: {
+                "Content-Type": "application/json",
+                ...(await clientHeaders()),
+            },
+            body: JSON.stringify({ token, new_password, new_password_confirm }),
+            cache: 'no-store'
+        });
+        return await response.json();
+    }catch(error) {
+        throw error;
+    }
+}
+
+
+export async function OidcProvidersApi() {
+    try{
+        const response = await fetch(`${API_BASE_URL}/auth/oidc`, {
+            cache: 'no-store'
+        });
+        const data = await response.json();
+        return (data.providers ?? []) as { name: string; display_name: string }[];
+    }catch(error) {
+        console.log(error);
+        return [];
+    }
+}
+
+
+export async function OidcAuthorizeApi(provider: string) {
+    const response = await fetch(`${API_BASE_URL}/auth/oidc/${encodeURIComponent(provider)}/authorize`, {
+        cache: 'no-store'
+    });
+    const data = await response.json();
+
+    if (!response.ok || !data.authorization_url) {
+        return {error: data.message ?? "Single sign-on is unavailable right now"};
+    }
+
+    redirect(data.authorization_url);
+}
+
+
+export async function OidcLoginApi({
+    provider,
+    code,
+    state,
+    challenge_token,
+    two_factor_code,
+}: {
+    provider: string;
+    code?: string;
+    state?: string;
+    challenge_token?: string;
+    two_factor_code?: string;
+}) {
+    try{
+        await signIn("oidc", {
+            provider,
+            code,
+            state,
+            challenge_token,
+            two_factor_code,
+            redirectTo: DEFAULT_LOGIN_REDIRECT,
+        });
+    }catch(error) {
+        if (error instanceof AuthError) {
+            const signInCode = (error as CredentialsSignin).code ?? "";
+            if (error.type === "CredentialsSignin" && signInCode.startsWith("two_factor_required:")) {
+                return {challengeToken: signInCode.slice("two_factor_required:".length)};
+            }
+            if (signInCode === "too_many_attempts") {
+                return {error: "Too many failed attempts. Please try again later."};
+            }
+            return {error: challenge_token ? "Invalid authentication code!" : "Single sign-on failed. Please try again."};
+        }
+
+        throw error;
+    }
+}
+
+
+// Ends the API session before the NextAuth one, so its tokens stop working as soon
+// as the user logs out. An expired access token is renewed with the refresh token
+// first, since logging out needs a valid one.
 export async function Logout() {
+    try {
+        const cookieStore = await cookies();
+        const token = await getToken({
+            req: { headers: await headers() },
+            secret: process.env.AUTH_SECRET,
+            secureCookie: cookieStore.has("__Secure-authjs.session-token"),
+        });
+
+        const accessToken = token?.accessToken as string | undefined;
+        const refreshToken = token?.refreshToken as string | undefined;
+        const res = accessToken ? await apiLogout(accessToken) : null;
+
+        if ((!res || res.status === 401) && refreshToken) {
+            const refreshed = await fetch(`${API_BASE_URL}/auth/refresh`, {
+                method: "post",
+                headers: { "content-Type": "application/json" },
+                body: JSON.stringify({ refresh_token: refreshToken }),
+            });
+            const data = await refreshed.json().catch(() => null);
+
+            if (refreshed.ok && data?.token) {
+                await apiLogout(data.token);
+            }
+        }
+    } catch (error) {
+        console.error("Logout Error: ", error);
+    }
+
     await signOut({ redirectTo: '/login' });
 }
+
+async function apiLogout(accessToken: string) {
+    return fetch(`${API_BASE_URL}/auth/logout`, {
+        method: "post",
+        headers: {
+            ...(await clientHeaders()),
+            Authorization: `Bearer ${accessToken}`,
+        },
+    });
+}
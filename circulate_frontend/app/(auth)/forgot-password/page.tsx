@@ -0,0 +1,10 @@
+import { ForgotPasswordForm } from "@/components/auth/ForgotPasswordForm";
+
+
+export default function ForgotPasswordPage() {
+  return (
+    <div>
+      <ForgotPasswordForm />
+    </div>
+  );
+}
@@ -1,10 +1,13 @@
+import { OidcProvidersApi } from "@/action/authHandler";
 import { LoginForm } from "@/components/auth/LoginForm";
 
 
-export default function LoginPage() {
+export default async function LoginPage() {
+  const providers = await OidcProvidersApi();
+
   return (
     <div>
-      <LoginForm />
+      <LoginForm providers={providers} />
     </div>
   );
 }
\ No newline at end of file
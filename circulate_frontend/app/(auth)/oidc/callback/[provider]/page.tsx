@@ -0,0 +1,24 @@
+import { OidcCallback } from "@/components/auth/OidcCallback";
+
+
+export default async function OidcCallbackPage({
+  params,
+  searchParams,
+}: {
+  params: Promise<{ provider: string }>;
+  searchParams: Promise<{ code?: string; state?: string; error?: string; error_description?: string }>;
+}) {
+  const { provider } = await params;
+  const { code, state, error, error_description } = await searchParams;
+
+  return (
+    <div>
+      <OidcCallback
+        provider={provider}
+        code={code}
+        state={state}
+        providerError={error ? error_description ?? "The identity provider refused the sign-in." : undefined}
+      />
+    </div>
+  );
+}
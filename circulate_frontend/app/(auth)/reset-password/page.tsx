@@ -0,0 +1,16 @@
+import { ResetPasswordForm } from "@/components/auth/ResetPasswordForm";
+
+
+export default async function ResetPasswordPage({
+  searchParams,
+}: {
+  searchParams: Promise<{ token?: string }>;
+}) {
+  const { token } = await searchParams;
+
+  return (
+    <div>
+      <ResetPasswordForm token={token ?? ""} />
+    </div>
+  );
+}
@@ -0,0 +1,28 @@
+import { VerifyEmailApi } from "@/action/authHandler";
+import { AuthCard } from "@/components/auth/AuthCard";
+
+
+export default async function VerifyEmailPage({
+  searchParams,
+}: {
+  searchParams: Promise<{ token?: string }>;
+}) {
+  const { token } = await searchParams;
+  const response = token ? await VerifyEmailApi(token) : null;
+
+  return (
+    <div>
+      <AuthCard
+        headerLabel="Email verification"
+        backButtonHref="/login"
+        backButtonLabel="Back to login"
+      >
+        <p className="text-center text-sm">
+          {response?.status === "success"
+            ? `${response.message}. You can now receive files.`
+            : response?.message ?? "The verification link is missing its token."}
+        </p>
+      </AuthCard>
+    </div>
+  );
+}
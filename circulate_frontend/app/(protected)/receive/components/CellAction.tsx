@@ -47,7 +47,7 @@ export const CellAction = ({ data, token }: CellActionProps) => {
                     Authorization: `Bearer ${token}`,
                     'Content-Type': 'application/json',
                 },
-                body: JSON.stringify({ shared_id: data.file_id, password: values.password }),
+                body: JSON.stringify({ shared_id: data.share_id, password: values.password }),
             });
 
             if (!response.ok) {
@@ -3,9 +3,12 @@ import { ColumnDef } from "@tanstack/react-table";
 import { CellAction } from "./CellAction";
 
 export type ReceiveColumnsType = {
+    share_id: string;
     file_id: string;
     file_name: string;
     recipient_email: string;
+    state: string;
+    available_from: string | null;
     expiration_date: string;
     created_at: string;
 };
@@ -14,7 +17,7 @@ export type ReceiveColumnsType = {
 export function useReceiveColumns({ token }: { token: string | null }): ColumnDef<ReceiveColumnsType>[]{
     return [
         {
-            accessorKey: "file_id",
+            accessorKey: "share_id",
             header: "ID",
         },
 
@@ -30,6 +33,25 @@ export function useReceiveColumns({ token }: { token: string | null }): ColumnDe
             header: "Sender Email",
         },
 
+        {
+            accessorKey: "state",
+            header: "State",
+            cell: ({ row }) => {
+                const { state, available_from } = row.original;
+                if (available_from && new Date(available_from) > new Date()) {
+                    const date = new Date(available_from);
+                    return `Locked until ${date.toLocaleString('en-US', {
+                        day: '2-digit',
+                        month: 'short',
+                        year: 'numeric',
+                        hour: '2-digit',
+                        minute: '2-digit',
+                    })}`;
+                }
+                return state ? state.charAt(0).toUpperCase() + state.slice(1) : "";
+            },
+        },
+
         {
             accessorKey: "expiration_date",
             header: "Expiration Date",
@@ -4,10 +4,13 @@ import { ColumnDef } from "@tanstack/react-table";
 
 export type UploadColumnType = {
     file_id: string;
+    share_id: string;
     file_name: string;
     recipient_email: string;
     expiration_date: string;
     created_at: string;
+    download_count: number;
+    downloaded_at: string | null;
 };
 
 export const UploadColumns: ColumnDef<UploadColumnType>[] = [
@@ -44,6 +47,24 @@ export const UploadColumns: ColumnDef<UploadColumnType>[] = [
     },
     
 
+    {
+        accessorKey: "downloaded_at",
+        header: "Delivery",
+        cell: ({ row }) => {
+            if (!row.original.downloaded_at) {
+                return "Not downloaded";
+            }
+            const date = new Date(row.original.downloaded_at);
+            const downloadedOn = date.toLocaleDateString('en-US', {
+                day: '2-digit',
+                month: 'short',
+                year: 'numeric',
+            });
+            return `Downloaded ${row.original.download_count}x (first on ${downloadedOn})`;
+        },
+    },
+
+
     {
         accessorKey: "created_at",
         header: "Created At",
@@ -28,6 +28,7 @@ const emailFormSchema = z.object({
       (val) => val >= new Date(),
       "Expiration date must be in the future"
     ),
+  available_from: z.date().optional(),
   fileUpload: z
     .instanceof(File, { message: "Please select a file" })
     .refine(
@@ -41,7 +42,10 @@ const emailFormSchema = z.object({
       (file) => file && file.size <= 4 * 1024 * 1024, // 4MB in bytes
       { message: "File size must be less than or equal to 4MB" }
     ),
-});
+}).refine(
+  (values) => !values.available_from || values.available_from < values.expiration_date,
+  { message: "Available from must be before the expiration date", path: ["available_from"] }
+);
 
 export const UploadNew = ({ token }: { token: string | null }) => {
   const [emailSuggestions, setEmailSuggestions] = useState<
@@ -109,6 +113,9 @@ export const UploadNew = ({ token }: { token: string | null }) => {
         formData.append('recipient_email', values.recipient_email);
         formData.append('password', values.password);
         formData.append('expiration_date', values.expiration_date.toISOString());
+        if (values.available_from) {
+          formData.append('available_from', values.available_from.toISOString());
+        }
         formData.append('fileUpload', values.fileUpload);
 
         const response = await fetch(`http://localhost:8000/api/file/upload`,
@@ -232,6 +239,46 @@ export const UploadNew = ({ token }: { token: string | null }) => {
                 </FormItem>
               )}
             />
+            <FormField
+              control={form.control}
+              name="available_from"
+              render={({ field }) => (
+                <FormItem className="flex flex-col w-full">
+                  <FormLabel>Available From (optional)</FormLabel>
+                  <Popover>
+                    <PopoverTrigger asChild>
+                      <FormControl>
+                        <Button
+                          variant={"outline"}
+                          className={cn(
+                            "pl-3 text-left font-normal",
+                            !field.value && "text-muted-foreground"
+                          )}
+                          disabled={isPending}
+                        >
+                          {field.value ? (
+                            format(field.value, "PPP")
+                          ) : (
+                            <span>Immediately</span>
+                          )}
+                          <CalendarIcon className="ml-auto h-4 w-4 opacity-50" />
+                        </Button>
+                      </FormControl>
+                    </PopoverTrigger>
+                    <PopoverContent className="w-auto p-0" align="start">
+                      <Calendar
+                        mode="single"
+                        selected={field.value}
+                        onSelect={field.onChange}
+                        disabled={(date) => date < new Date()}
+                        autoFocus
+                      />
+                    </PopoverContent>
+                  </Popover>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
             <FormField
               control={form.control}
               name="fileUpload"
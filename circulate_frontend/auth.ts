@@ -1,5 +1,46 @@
-import NextAuth from 'next-auth';
+import NextAuth, { CredentialsSignin } from 'next-auth';
 import CredentialsProvider from 'next-auth/providers/credentials';
+import { clientHeaders } from '@/components/utils/clientHeaders';
+
+class TwoFactorRequired extends CredentialsSignin {
+    code = "two_factor_required";
+}
+
+// Answered alike for real and unknown addresses once too many attempts failed.
+class TooManyLoginAttempts extends CredentialsSignin {
+    code = "too_many_attempts";
+}
+
+// The provider's authorization code can only be redeemed once, so the challenge
+// token travels back to the callback page to finish the second factor with.
+class OidcTwoFactorRequired extends CredentialsSignin {
+    constructor(challengeToken: string) {
+        super();
+        this.code = `two_factor_required:${challengeToken}`;
+    }
+}
+
+async function completeTwoFactor(challengeToken: string, code: string) {
+    const res = await fetch(`${process.env.API_BASE_URL}/auth/login/2fa`,{
+        method: 'post',
+        headers: {
+            "content-Type": "application/json",
+            ...(await clientHeaders()),
+        },
+        body: JSON.stringify({
+            challenge_token: challengeToken,
+            code,
+        })
+    });
+
+    if (res.status === 429) {
+        throw new TooManyLoginAttempts();
+    }
+
+    const data = await res.json();
+
+    return res.ok && data.token ? { token: data.token, refreshToken: data.refresh_token } : null;
+}
 
 export const {
     handlers: { GET, POST },
@@ -18,12 +59,14 @@ export const {
             credentials: {
                 email: { label: 'email', type: 'email' },
                 password: { label: 'password', type: 'password' },
+                code: { label: 'code', type: 'text' },
             },
             async authorize(credentials) {
                 const res = await fetch(`${process.env.API_BASE_URL}/auth/login`,{
                     method: 'post',
                     headers: {
                         "content-Type": "application/json",
+                        ...(await clientHeaders()),
                     },
                     body: JSON.stringify({
                         email: credentials?.email,
@@ -31,10 +74,66 @@ export const {
                     })
                 });
 
+                if (res.status === 429) {
+                    throw new TooManyLoginAttempts();
+                }
+
                 const data = await res.json();
 
+                if (res.ok && data.status === "two_factor_required") {
+                    if (!credentials?.code) {
+                        throw new TwoFactorRequired();
+                    }
+
+                    return completeTwoFactor(data.challenge_token, credentials.code as string);
+                }
+
+                if (res.ok && data.token){
+                    return { token: data.token, refreshToken: data.refresh_token }
+                }else{
+                    return null;
+                }
+            },
+        }),
+        CredentialsProvider({
+            id: 'oidc',
+            name: 'oidc',
+            credentials: {
+                provider: { label: 'provider', type: 'text' },
+                code: { label: 'code', type: 'text' },
+                state: { label: 'state', type: 'text' },
+                challenge_token: { label: 'challenge_token', type: 'text' },
+                two_factor_code: { label: 'two_factor_code', type: 'text' },
+            },
+            async authorize(credentials) {
+                if (credentials?.challenge_token) {
+                    return completeTwoFactor(
+                        credentials.challenge_token as string,
+                        credentials.two_factor_code as string,
+                    );
+                }
+
+                const provider = encodeURIComponent(credentials?.provider as string);
+                const res = await fetch(`${process.env.API_BASE_URL}/auth/oidc/${provider}/callback`,{
+                    method: 'post',
+                    headers: {
+                        "content-Type": "application/json",
+                        ...(await clientHeaders()),
+                    },
+                    body: JSON.stringify({
+                        code: credentials?.code,
+                        state: credentials?.state,
+                    })
+                });
+
+                const data = await res.json();
+
+                if (res.ok && data.status === "two_factor_required") {
+                    throw new OidcTwoFactorRequired(data.challenge_token);
+                }
+
                 if (res.ok && data.token){
-                    return { token: data.token }
+                    return { token: data.token, refreshToken: data.refresh_token }
                 }else{
                     return null;
                 }
@@ -48,6 +147,10 @@ export const {
         async jwt({ token, user }){
             if (user) {
                 token.accessToken = user.token;
+                // Kept in the encrypted session cookie only, never in the session
+                // handed to the browser; logging out needs it once the access token
+                // has expired.
+                token.refreshToken = user.refreshToken;
             }
             return token;
         },
@@ -0,0 +1,76 @@
+"use client"
+
+import { useTransition } from "react";
+import { AuthCard } from "./AuthCard";
+import { useForm } from "react-hook-form";
+import { z } from "zod";
+import { forgotPasswordSchema } from "../schema/authType";
+import { zodResolver } from "@hookform/resolvers/zod";
+import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from "../ui/form";
+import { Input } from "../ui/input";
+import { Button } from "../ui/button";
+import { ForgotPasswordApi } from "@/action/authHandler";
+import toast from "react-hot-toast";
+
+
+export const ForgotPasswordForm = () => {
+    const [isPending, startTransition] = useTransition();
+
+    const form = useForm<z.infer<typeof forgotPasswordSchema>>({
+        resolver: zodResolver(forgotPasswordSchema),
+        defaultValues: {
+            email: '',
+        }
+    })
+
+    const onSubmit = (values: z.infer<typeof forgotPasswordSchema>) => {
+        startTransition(() => {
+            ForgotPasswordApi(values.email)
+                .then((response) => {
+                    if(response?.status === "success") {
+                        toast.success(response.message);
+                    } else {
+                        toast.error(response?.message ?? "Something went wrong!");
+                    }
+                })
+                .catch((error) => {
+                    console.log(error);
+                })
+        })
+    }
+
+
+    return (
+        <AuthCard
+            headerLabel="Reset your password"
+            backButtonHref="/login"
+            backButtonLabel="Back to login"
+        >
+            <Form {...form}>
+                <form className="space-y-6" onSubmit={form.handleSubmit(onSubmit)}>
+                    <FormField 
+                        control={form.control}
+                        name="email"
+                        render={({ field }) => (
+                            <FormItem>
+                                <FormLabel>Email</FormLabel>
+                                <FormControl>
+                                    <Input 
+                                        {...field}
+                                        placeholder="abc.xyz@example.com"
+                                        type="email"
+                                        disabled={isPending}
+                                    />
+                                </FormControl>
+                                <FormMessage />
+                            </FormItem>
+                        )}
+                    />
+                    <Button type="submit" className="w-full" isLoading={isPending}>
+                        Send reset link
+                    </Button>
+                </form>
+            </Form>
+        </AuthCard>
+    )
+}
@@ -9,25 +9,50 @@ import { zodResolver } from "@hookform/resolvers/zod";
 import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from "../ui/form";
 import { Input } from "../ui/input";
 import { Button } from "../ui/button";
-import { LoginApi } from "@/action/authHandler";
+import { LoginApi, OidcAuthorizeApi } from "@/action/authHandler";
 import toast from "react-hot-toast";
+import Link from "next/link";
 
 
-export const LoginForm = () => {
+export const LoginForm = ({
+    providers = [],
+}: {
+    providers?: { name: string; display_name: string }[];
+}) => {
     const [isPending, startTransition] = useTransition();
     //const [showPassword, setShowPassword] = useState<boolean>(false);
+    const [needsCode, setNeedsCode] = useState<boolean>(false);
 
     const form = useForm<z.infer<typeof loginSchema>>({
         resolver: zodResolver(loginSchema),
         defaultValues: {
             email: '',
             password: '',
+            code: '',
         }
     })
 
     const onSubmit = (values: z.infer<typeof loginSchema>) => {
         startTransition(() => {
             LoginApi(values)
+                .then((response) => {
+                    if(response?.twoFactor) {
+                        setNeedsCode(true);
+                        toast("Enter the code from your authenticator app");
+                    } else if(response?.error) {
+                        toast.error(response.error);
+                    }
+                })
+                .catch((error) => {
+                    console.log(error);
+                })
+        })
+    }
+
+
+    const onProviderLogin = (provider: string) => {
+        startTransition(() => {
+            OidcAuthorizeApi(provider)
                 .then((response) => {
                     if(response?.error) {
                         toast.error(response.error);
@@ -86,12 +111,51 @@ export const LoginForm = () => {
                                 </FormItem>
                             )}
                         />
+                        {needsCode && (
+                            <FormField 
+                                control={form.control}
+                                name="code"
+                                render={({ field }) => (
+                                    <FormItem>
+                                        <FormLabel>Authentication code</FormLabel>
+                                        <FormControl>
+                                            <Input 
+                                                {...field}
+                                                placeholder="123456 or a backup code"
+                                                autoComplete="one-time-code"
+                                                disabled={isPending}
+                                            />
+                                        </FormControl>
+                                        <FormMessage />
+                                    </FormItem>
+                                )}
+                            />
+                        )}
                     </div>
                     <Button type="submit" className="w-full" isLoading={isPending}>
                         Login
                     </Button>
+                    <Button variant="link" className="font-normal w-full" size="sm" asChild>
+                        <Link href="/forgot-password">Forgot password?</Link>
+                    </Button>
                 </form>
             </Form>
+            {providers.length > 0 && (
+                <div className="mt-4 space-y-2">
+                    <p className="text-center text-sm text-muted-foreground">or</p>
+                    {providers.map((provider) => (
+                        <Button
+                            key={provider.name}
+                            variant="outline"
+                            className="w-full"
+                            disabled={isPending}
+                            onClick={() => onProviderLogin(provider.name)}
+                        >
+                            Continue with {provider.display_name}
+                        </Button>
+                    ))}
+                </div>
+            )}
         </AuthCard>
     )
 }
@@ -0,0 +1,97 @@
+"use client"
+
+import { useEffect, useRef, useState, useTransition } from "react";
+import { AuthCard } from "./AuthCard";
+import { Input } from "../ui/input";
+import { Button } from "../ui/button";
+import { OidcLoginApi } from "@/action/authHandler";
+import toast from "react-hot-toast";
+
+
+export const OidcCallback = ({
+    provider,
+    code,
+    state,
+    providerError,
+}: {
+    provider: string;
+    code?: string;
+    state?: string;
+    providerError?: string;
+}) => {
+    const [isPending, startTransition] = useTransition();
+    const [error, setError] = useState<string | undefined>(providerError);
+    const [challengeToken, setChallengeToken] = useState<string>();
+    const [twoFactorCode, setTwoFactorCode] = useState<string>('');
+    // The authorization code is single use, so it must not be sent twice when
+    // effects run twice in development.
+    const started = useRef<boolean>(false);
+
+    useEffect(() => {
+        if (started.current || providerError) {
+            return;
+        }
+        started.current = true;
+
+        if (!code || !state) {
+            setError("The sign-in response is missing its code.");
+            return;
+        }
+
+        startTransition(() => {
+            OidcLoginApi({ provider, code, state })
+                .then((response) => {
+                    if(response?.challengeToken) {
+                        setChallengeToken(response.challengeToken);
+                    } else if(response?.error) {
+                        setError(response.error);
+                    }
+                })
+                .catch((error) => {
+                    console.log(error);
+                })
+        })
+    }, [provider, code, state, providerError]);
+
+    const onSubmitCode = () => {
+        startTransition(() => {
+            OidcLoginApi({ provider, challenge_token: challengeToken, two_factor_code: twoFactorCode })
+                .then((response) => {
+                    if(response?.error) {
+                        toast.error(response.error);
+                    }
+                })
+                .catch((error) => {
+                    console.log(error);
+                })
+        })
+    }
+
+
+    return (
+        <AuthCard
+            headerLabel="Single sign-on"
+            backButtonHref="/login"
+            backButtonLabel="Back to login"
+        >
+            {challengeToken ? (
+                <div className="space-y-4">
+                    <Input
+                        value={twoFactorCode}
+                        onChange={(e) => setTwoFactorCode(e.target.value)}
+                        placeholder="123456 or a backup code"
+                        autoComplete="one-time-code"
+                        disabled={isPending}
+                    />
+                    <Button className="w-full" isLoading={isPending} onClick={onSubmitCode}>
+                        Verify
+                    </Button>
+                </div>
+            ) : (
+                <p className="text-center text-sm">
+                    {error ?? "Signing you in..."}
+                </p>
+            )}
+        </AuthCard>
+    )
+}
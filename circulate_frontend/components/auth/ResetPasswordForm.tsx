@@ -0,0 +1,100 @@
+"use client"
+
+import { useTransition } from "react";
+import { AuthCard } from "./AuthCard";
+import { useForm } from "react-hook-form";
+import { z } from "zod";
+import { resetPasswordSchema } from "../schema/authType";
+import { zodResolver } from "@hookform/resolvers/zod";
+import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from "../ui/form";
+import { Input } from "../ui/input";
+import { Button } from "../ui/button";
+import { ResetPasswordApi } from "@/action/authHandler";
+import toast from "react-hot-toast";
+import { useRouter } from "next/navigation";
+
+
+export const ResetPasswordForm = ({ token }: { token: string }) => {
+    const [isPending, startTransition] = useTransition();
+    const router = useRouter();
+
+    const form = useForm<z.infer<typeof resetPasswordSchema>>({
+        resolver: zodResolver(resetPasswordSchema),
+        defaultValues: {
+            new_password: '',
+            new_password_confirm: '',
+        }
+    })
+
+    const onSubmit = (values: z.infer<typeof resetPasswordSchema>) => {
+        startTransition(() => {
+            ResetPasswordApi({ token, ...values })
+                .then((response) => {
+                    if(response?.status === "success") {
+                        toast.success(`${response.message}. Redirecting to login...`);
+                        router.push("/login");
+                    } else {
+                        toast.error(response?.message ?? "Something went wrong!");
+                    }
+                })
+                .catch((error) => {
+                    console.log(error);
+                })
+        })
+    }
+
+
+    return (
+        <AuthCard
+            headerLabel="Choose a new password"
+            backButtonHref="/login"
+            backButtonLabel="Back to login"
+        >
+            <Form {...form}>
+                <form className="space-y-6" onSubmit={form.handleSubmit(onSubmit)}>
+                    <div className="space-y-4">
+                        <FormField 
+                            control={form.control}
+                            name="new_password"
+                            render={({ field }) => (
+                                <FormItem>
+                                    <FormLabel>New password</FormLabel>
+                                    <FormControl>
+                                        <Input 
+                                            {...field}
+                                            placeholder="********"
+                                            type="password"
+                                            disabled={isPending}
+                                        />
+                                    </FormControl>
+                                    <FormMessage />
+                                </FormItem>
+                            )}
+                        />
+                        <FormField 
+                            control={form.control}
+                            name="new_password_confirm"
+                            render={({ field }) => (
+                                <FormItem>
+                                    <FormLabel>Confirm new password</FormLabel>
+                                    <FormControl>
+                                        <Input 
+                                            {...field}
+                                            placeholder="********"
+                                            type="password"
+                                            disabled={isPending}
+                                        />
+                                    </FormControl>
+                                    <FormMessage />
+                                </FormItem>
+                            )}
+                        />
+                    </div>
+                    <Button type="submit" className="w-full" isLoading={isPending}>
+                        Reset password
+                    </Button>
+                </form>
+            </Form>
+        </AuthCard>
+    )
+}
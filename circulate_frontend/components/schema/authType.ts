@@ -8,9 +8,30 @@ export const loginSchema = z.object({
 
     password: z.string()
         .min(6, {message: "Password should be atleast 6 characters long"}),
+
+    code: z.string().optional(),
+})
+
+
+export const forgotPasswordSchema = z.object({
+    email: z.string()
+        .min(1, {message: "Email is required"})
+        .email({message: "Invalid email address"}),
 })
 
 
+export const resetPasswordSchema = z.object({
+    new_password: z.string()
+        .min(6, {message: "Password should be atleast 6 characters long"}),
+
+    new_password_confirm: z.string()
+        .min(1, {message: "Password confirmation required"}),
+}).refine((data) => data.new_password === data.new_password_confirm, {
+    message: "Password do not match",
+    path: ["new_password_confirm"]
+});
+
+
 export const registerSchema = z.object({
     email: z.string()
         .min(1, {message: "Email is required"})
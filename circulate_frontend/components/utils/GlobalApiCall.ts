@@ -1,4 +1,5 @@
 import { useCurrentUserServer } from "@/hooks/use-current-user-server";
+import { clientHeaders } from "./clientHeaders";
 import { RedirectError } from "./ErrorUtils";
 
 interface GlobalAPICallProps {
@@ -19,6 +20,7 @@ export const GlobalApiCall = async ({
             credentials: "include",
             headers: {
                 "Content-Type": "application/json",
+                ...(await clientHeaders()),
                 ...(token ? { Authorization: `Bearer ${token}` } : {}),
                 ...options.headers,
             },
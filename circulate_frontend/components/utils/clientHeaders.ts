@@ -0,0 +1,24 @@
+import { headers } from "next/headers";
+
+// Proxies in front of this server that append to x-forwarded-for.
+const trustedProxies = Math.max(Number.parseInt(process.env.TRUSTED_PROXY_COUNT ?? "", 10) || 1, 1);
+
+// Requests reach the API from this server, so pass on who the browser is. Each proxy
+// appends the address it got the request from, so the browser is the entry the
+// outermost of ours added; anything left of it was sent by the browser itself and
+// is dropped. The API only trusts the header when TRUST_PROXY_HEADERS=true, and uses
+// it for rate limiting and the session list.
+export const clientHeaders = async (): Promise<Record<string, string>> => {
+    const requestHeaders = await headers();
+    const hops = (requestHeaders.get("x-forwarded-for") ?? "")
+        .split(",")
+        .map((hop) => hop.trim())
+        .filter((hop) => hop.length > 0);
+    const clientAddress = hops[Math.max(hops.length - trustedProxies, 0)];
+    const userAgent = requestHeaders.get("user-agent");
+
+    return {
+        ...(clientAddress ? { "x-forwarded-for": clientAddress } : {}),
+        ...(userAgent ? { "user-agent": userAgent } : {}),
+    };
+}
@@ -7,6 +7,7 @@ export type ExtendedUser = DefaultSession["user"] & {
 declare module "next-auth"{
     interface User{
         token: string;
+        refreshToken?: string;
     }
 
     interface Session {
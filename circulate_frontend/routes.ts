@@ -1,4 +1,4 @@
-export const authRoutes = ["/login", "/register"];
+export const authRoutes = ["/login", "/register", "/forgot-password", "/reset-password"];
 
 export const apiAuthPrefix = "/api/auth";
 